@@ -66,11 +66,24 @@ impl GameObject for CameraController {
         camera.position += camera.right_direction() * translation.x;
         camera.position += camera.up_direction() * translation.y;
 
-        if input_handler.is_mouse_button_held(&MouseButton::Left) {
-            let cursor_delta = input_handler.cursor_delta();
+        if input_handler.is_mouse_button_down(&MouseButton::Left) {
+            input_handler.lock_cursor();
+        } else if input_handler.is_mouse_button_released(&MouseButton::Left) {
+            input_handler.unlock_cursor();
+        }
+
+        // Cursor movement is used where the platform can't lock the cursor
+        let rotation = if input_handler.is_cursor_locked() {
+            Some(input_handler.mouse_motion())
+        } else if input_handler.is_mouse_button_held(&MouseButton::Left) {
+            Some(input_handler.cursor_delta())
+        } else {
+            None
+        };
 
-            camera.yaw += Rad(cursor_delta.x) * Self::SENSITIVITY * dt;
-            camera.pitch += Rad(cursor_delta.y) * Self::SENSITIVITY * dt;
+        if let Some(rotation) = rotation {
+            camera.yaw += Rad(rotation.x) * Self::SENSITIVITY * dt;
+            camera.pitch += Rad(rotation.y) * Self::SENSITIVITY * dt;
 
             if camera.pitch < -Rad(FRAC_PI_2) {
                 camera.pitch = -Rad(FRAC_PI_2);
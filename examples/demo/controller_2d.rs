@@ -64,6 +64,7 @@ impl GameObject for Controller2D {
                 color: Color::BLACK,
                 scale: 40.0,
                 font: FontParameters::Default,
+                ..Default::default()
             },
             (0, 0).into(),
             400,
@@ -17,6 +17,9 @@ var t_diffuse: texture_2d<f32>;
 @group(1) @binding(1)
 var s_diffuse: sampler;
 
+@group(2) @binding(0)
+var<uniform> opacity: f32;
+
 
 @vertex
 fn vs_main(input: VertexInput) -> VertexOutput {
@@ -28,5 +31,6 @@ fn vs_main(input: VertexInput) -> VertexOutput {
 
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, input.tex_coords);
+    // Colors are premultiplied, so all channels are scaled by the opacity
+    return textureSample(t_diffuse, s_diffuse, input.tex_coords) * opacity;
 }
\ No newline at end of file
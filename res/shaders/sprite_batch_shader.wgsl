@@ -0,0 +1,49 @@
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+
+struct InstanceInput {
+    @location(2) position: vec2<f32>,
+    @location(3) size: vec2<f32>,
+    @location(4) rotation: f32,
+    @location(5) uv_rect: vec4<f32>,
+    @location(6) tint: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) tint: vec4<f32>,
+}
+
+
+@group(0) @binding(0)
+var<uniform> ortho_uniform: mat4x4<f32>;
+
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+
+
+@vertex
+fn vs_main(input: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let local = input.position * instance.size;
+    let c = cos(instance.rotation);
+    let s = sin(instance.rotation);
+    let rotated = vec2<f32>(local.x * c - local.y * s, local.x * s + local.y * c);
+
+    var out: VertexOutput;
+    out.clip_position = ortho_uniform * vec4<f32>(rotated + instance.position, 0.0, 1.0);
+    out.tex_coords = mix(instance.uv_rect.xy, instance.uv_rect.zw, input.tex_coords);
+    out.tint = instance.tint;
+    return out;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_diffuse, s_diffuse, input.tex_coords) * input.tint;
+    // Images are stored with straight alpha, but the blending expects premultiplied one
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
@@ -1,13 +1,31 @@
+use crate::gfx::texture::Image;
 use crate::{ResizeMode, WindowSettings};
-use log::info;
+use log::{info, warn};
 use std::rc::Rc;
 use wgpu::util::DeviceExt;
 use winit::dpi::PhysicalSize;
 
+mod animation;
+mod camera_2d;
+mod layer;
 mod sprite;
+mod sprite_batch;
+mod sprite_sheet;
 mod text;
+mod text_batch;
+mod tilemap;
+mod tilemap_batch;
+pub use animation::*;
+pub use camera_2d::*;
+pub use layer::*;
 pub use sprite::*;
+use sprite_batch::SpriteInstanceRaw;
+pub use sprite_batch::{SpriteBatch, SpriteInstance};
+pub use sprite_sheet::*;
 pub use text::*;
+use text_batch::TextBatch;
+pub use tilemap::*;
+use tilemap_batch::TilemapBatch;
 
 pub struct Renderer2D {
     device: Rc<wgpu::Device>,
@@ -21,16 +39,31 @@ pub struct Renderer2D {
     projection_buffer: wgpu::Buffer,
     projection_bind_group: wgpu::BindGroup,
 
+    camera: Camera2D,
+    /// Projection combined with the view of the camera
+    world_projection_buffer: wgpu::Buffer,
+    world_projection_bind_group: wgpu::BindGroup,
+
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
 
-    background_sprite: Sprite,
-    background_texture_bind_group: wgpu::BindGroup,
+    sprite_batch_pipeline: wgpu::RenderPipeline,
+    quad_vertex_buffer: wgpu::Buffer,
+    sprite_batches: Vec<SpriteBatch>,
+    tilemaps: Vec<TilemapBatch>,
+    text_batch: TextBatch,
+    fonts: FontRegistry,
 
-    foreground_sprite: Sprite,
-    foreground_texture_bind_group: wgpu::BindGroup,
+    opacity_bind_group_layout: wgpu::BindGroupLayout,
+    /// Sorted by z-order before every upload
+    layers: Vec<Layer>,
 }
 
+/// Name of the layer created behind the 3D scene, sprite batches are drawn right after it
+pub const BACKGROUND_LAYER: &str = "background";
+/// Name of the layer created in front of the 3D scene
+pub const FOREGROUND_LAYER: &str = "foreground";
+
 impl Renderer2D {
     pub(crate) fn new(
         device: Rc<wgpu::Device>,
@@ -82,66 +115,66 @@ impl Renderer2D {
             }],
         });
 
+        let camera = Camera2D::new(cgmath::Vector2::new(
+            screen_size.width as f32,
+            screen_size.height as f32,
+        ));
+
+        let world_projection_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("world_projection_buffer"),
+                contents: bytemuck::cast_slice(&projection_raw),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        let world_projection_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("world_projection_bind_group"),
+            layout: &projection_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: world_projection_buffer.as_entire_binding(),
+            }],
+        });
+
         let texture_bind_group_layout =
             crate::gfx::texture::Texture::texture_bind_group_layout(&device);
 
-        let render_pipeline = {
-            let render_pipeline_layout =
-                device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("render_pipeline_layout"),
-                    bind_group_layouts: &[
-                        &projection_bind_group_layout,
-                        &texture_bind_group_layout,
-                    ],
-                    push_constant_ranges: &[],
-                });
-
-            let gui_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        let opacity_bind_group_layout = Layer::opacity_bind_group_layout(&device);
+
+        let render_pipeline = Self::create_pipeline(
+            &device,
+            &[
+                &projection_bind_group_layout,
+                &texture_bind_group_layout,
+                &opacity_bind_group_layout,
+            ],
+            &device.create_shader_module(wgpu::ShaderModuleDescriptor {
                 label: Some("gui_shader_module"),
                 source: wgpu::ShaderSource::Wgsl(
                     include_str!("../../res/shaders/gui_shader.wgsl").into(),
                 ),
-            });
+            }),
+            &[Self::quad_vertex_format()],
+            surface_config.format,
+            Some(wgpu::Face::Back),
+            "gui_pipeline",
+        );
 
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("gui_pipeline"),
-                layout: Some(&render_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &gui_shader_module,
-                    entry_point: "vs_main",
-                    buffers: &[wgpu::VertexBufferLayout {
-                        array_stride: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
-                        step_mode: wgpu::VertexStepMode::Vertex,
-                        attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2],
-                    }],
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &gui_shader_module,
-                    entry_point: "fs_main",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: surface_config.format,
-                        blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
-                        write_mask: wgpu::ColorWrites::all(),
-                    })],
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: Some(wgpu::Face::Back),
-                    unclipped_depth: false,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    conservative: false,
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-            })
-        };
+        // Flipped sprites are drawn with clockwise winding order, so nothing is culled
+        let sprite_batch_pipeline = Self::create_pipeline(
+            &device,
+            &[&projection_bind_group_layout, &texture_bind_group_layout],
+            &device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("sprite_batch_shader_module"),
+                source: wgpu::ShaderSource::Wgsl(
+                    include_str!("../../res/shaders/sprite_batch_shader.wgsl").into(),
+                ),
+            }),
+            &[Self::quad_vertex_format(), SpriteInstanceRaw::format()],
+            surface_config.format,
+            None,
+            "sprite_batch_pipeline",
+        );
 
         let vertices = Self::create_screen_size_square(screen_size);
 
@@ -159,39 +192,51 @@ impl Renderer2D {
             usage: wgpu::BufferUsages::INDEX,
         });
 
-        let background_surface = Sprite::new(
-            screen_size.width,
-            screen_size.height,
-            crate::gfx::texture::Color::BLACK,
-        );
+        #[rustfmt::skip]
+        let quad_vertices: [f32; 16] = [
+            // Top left
+            -0.5, -0.5,
+            0.0, 0.0,
+            // Bottom left
+            -0.5, 0.5,
+            0.0, 1.0,
+            // Bottom right
+            0.5, 0.5,
+            1.0, 1.0,
+            // Top right
+            0.5, -0.5,
+            1.0, 0.0,
+        ];
 
-        let background_texture = crate::gfx::texture::Texture::from_image(
+        let quad_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("quad_vertex_buffer"),
+            contents: bytemuck::cast_slice(&quad_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let size = (screen_size.width, screen_size.height);
+        let mut background = Layer::new(
             &device,
             &queue,
-            &background_surface.image(),
-            "Background surface texture",
-            true,
-        );
-
-        let background_texture_bind_group =
-            crate::gfx::texture::Texture::texture_bind_group(&device, &background_texture);
-
-        let foreground_surface = Sprite::new(
-            screen_size.width,
-            screen_size.height,
-            crate::gfx::texture::Color::TRANSPARENT,
+            &opacity_bind_group_layout,
+            BACKGROUND_LAYER,
+            size,
+            0,
+            LayerPlacement::BehindScene,
         );
+        background.sprite.clear(crate::gfx::texture::Color::BLACK);
 
-        let foreground_texture = crate::gfx::texture::Texture::from_image(
+        let foreground = Layer::new(
             &device,
             &queue,
-            &foreground_surface.image(),
-            "Foreground surface texture",
-            true,
+            &opacity_bind_group_layout,
+            FOREGROUND_LAYER,
+            size,
+            0,
+            LayerPlacement::InFrontOfScene,
         );
 
-        let foreground_texture_bind_group =
-            crate::gfx::texture::Texture::texture_bind_group(&device, &foreground_texture);
+        let text_batch = TextBatch::new(&device, &queue);
 
         Self {
             device,
@@ -202,41 +247,153 @@ impl Renderer2D {
             projection,
             projection_buffer,
             projection_bind_group,
+            camera,
+            world_projection_buffer,
+            world_projection_bind_group,
             vertex_buffer,
             index_buffer,
-            background_sprite: background_surface,
-            background_texture_bind_group,
-            foreground_sprite: foreground_surface,
-            foreground_texture_bind_group,
+            sprite_batch_pipeline,
+            quad_vertex_buffer,
+            sprite_batches: vec![],
+            tilemaps: vec![],
+            text_batch,
+            fonts: FontRegistry::new(),
+            opacity_bind_group_layout,
+            layers: vec![background, foreground],
         }
     }
 
+    fn create_pipeline(
+        device: &wgpu::Device,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+        shader_module: &wgpu::ShaderModule,
+        buffer_layouts: &[wgpu::VertexBufferLayout],
+        format: wgpu::TextureFormat,
+        cull_mode: Option<wgpu::Face>,
+        label: &str,
+    ) -> wgpu::RenderPipeline {
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("render_pipeline_layout"),
+                bind_group_layouts,
+                push_constant_ranges: &[],
+            });
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader_module,
+                entry_point: "vs_main",
+                buffers: buffer_layouts,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader_module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::all(),
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+
+    fn quad_vertex_format<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const ATTRIBS: [wgpu::VertexAttribute; 2] =
+            wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &ATTRIBS,
+        }
+    }
+
+    /// Renders the layers behind the 3D scene and the sprite batches on top of the background layer
     pub(crate) fn render_background(
         &self,
         command_encoder: &mut wgpu::CommandEncoder,
         view: &wgpu::TextureView,
     ) {
-        self.render_panel(command_encoder, view, &self.background_texture_bind_group);
+        let mut render_pass =
+            self.begin_render_pass(command_encoder, view, "background_render_pass");
+        for layer in self.placed_layers(LayerPlacement::BehindScene) {
+            if layer.is_drawn() {
+                self.render_panel(&mut render_pass, layer);
+            }
+            if layer.name() == BACKGROUND_LAYER {
+                self.render_sprite_batches(&mut render_pass);
+            }
+        }
     }
 
+    /// Renders the layers in front of the 3D scene
     pub(crate) fn render_foreground(
         &self,
         command_encoder: &mut wgpu::CommandEncoder,
         view: &wgpu::TextureView,
     ) {
-        self.render_panel(command_encoder, view, &self.foreground_texture_bind_group);
+        let mut render_pass =
+            self.begin_render_pass(command_encoder, view, "foreground_render_pass");
+        for layer in self.placed_layers(LayerPlacement::InFrontOfScene) {
+            if layer.is_drawn() {
+                self.render_panel(&mut render_pass, layer);
+            }
+            if layer.name() == BACKGROUND_LAYER {
+                self.render_sprite_batches(&mut render_pass);
+            }
+        }
+    }
+
+    fn placed_layers(&self, placement: LayerPlacement) -> impl Iterator<Item = &Layer> {
+        self.layers
+            .iter()
+            .filter(move |layer| layer.placement == placement)
     }
 
-    fn render_panel(
+    /// Sprite batches are positioned in world coordinates, so they are drawn through the camera,
+    /// tilemaps are drawn below them and queued text on top of them
+    fn render_sprite_batches<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.sprite_batch_pipeline);
+        render_pass.set_bind_group(0, &self.world_projection_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.quad_vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        let visible_area = self.camera.visible_area();
+        for tilemap in &self.tilemaps {
+            tilemap.render(render_pass, &visible_area);
+        }
+        for sprite_batch in &self.sprite_batches {
+            sprite_batch.render(render_pass);
+        }
+        self.text_batch.render(render_pass);
+    }
+
+    fn begin_render_pass<'a>(
         &self,
-        command_encoder: &mut wgpu::CommandEncoder,
-        view: &wgpu::TextureView,
-        panel_bind_group: &wgpu::BindGroup,
-    ) {
+        command_encoder: &'a mut wgpu::CommandEncoder,
+        view: &'a wgpu::TextureView,
+        label: &str,
+    ) -> wgpu::RenderPass<'a> {
         let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("foreground_render_pass"),
+            label: Some(label),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
+                view,
                 resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Load,
@@ -247,17 +404,9 @@ impl Renderer2D {
         });
 
         if self.window_settings.resize_mode == ResizeMode::KeepAspectRatio {
-            let aspect = self.window_settings.logical_width as f32
-                / self.window_settings.logical_height as f32;
             // set up scissors rect with constant aspect ratio that stays in the center
-            let (width, height): (f32, f32) = self.screen_size.to_logical::<f32>(1.0).into();
-            let (scissors_width, scissors_height) = if width > height * aspect {
-                (height * aspect, height)
-            } else {
-                (width, width / aspect)
-            };
-            let scissors_x = (width - scissors_width) / 2.0;
-            let scissors_y = (height - scissors_height) / 2.0;
+            let (scissors_x, scissors_y, scissors_width, scissors_height) =
+                self.window_settings.game_area(self.screen_size);
             render_pass.set_viewport(
                 scissors_x,
                 scissors_y,
@@ -268,9 +417,19 @@ impl Renderer2D {
             );
         }
 
+        render_pass
+    }
+
+    /// World-space layers are drawn through the camera like the sprite batches,
+    /// so their pixels move with it without being redrawn
+    fn render_panel<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, layer: &'a Layer) {
         render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, &self.projection_bind_group, &[]);
-        render_pass.set_bind_group(1, &panel_bind_group, &[]);
+        let projection_bind_group = match layer.space {
+            LayerSpace::World => &self.world_projection_bind_group,
+            LayerSpace::Screen => &self.projection_bind_group,
+        };
+        render_pass.set_bind_group(0, projection_bind_group, &[]);
+        layer.bind(render_pass);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
 
@@ -279,24 +438,33 @@ impl Renderer2D {
 
     pub(crate) fn resize(&mut self, new_size: PhysicalSize<u32>) {
         self.screen_size = new_size;
+
+        if self.window_settings.resize_mode != ResizeMode::KeepAspectRatio {
+            for layer in &mut self.layers {
+                layer.sprite.resize(new_size);
+            }
+        }
+
+        // The projection covers the surfaces, so sprite batches are positioned in their pixels
+        // and the viewport stretches both to the window
+        let surface_size = self.surface_size();
+        self.camera.set_viewport_size(cgmath::Vector2::new(
+            surface_size.width as f32,
+            surface_size.height as f32,
+        ));
         self.projection = crate::util::ortho(
             0.0,
-            self.screen_size.width as f32,
-            self.screen_size.height as f32,
+            surface_size.width as f32,
+            surface_size.height as f32,
             0.0,
             -1.0,
             1000.0,
         );
 
-        let vertices = Self::create_screen_size_square(self.screen_size);
+        let vertices = Self::create_screen_size_square(surface_size);
 
         self.queue
             .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
-
-        if self.window_settings.resize_mode != ResizeMode::KeepAspectRatio {
-            self.background_sprite.resize(new_size);
-            self.foreground_sprite.resize(new_size);
-        }
     }
 
     pub(crate) fn update(&mut self) {
@@ -307,27 +475,46 @@ impl Renderer2D {
             bytemuck::cast_slice(&[projection_raw]),
         );
 
-        let background_texture = crate::gfx::texture::Texture::from_image(
-            &self.device,
-            &self.queue,
-            &self.background_sprite.image(),
-            "Background surface texture",
-            true,
+        let world_projection_raw: [[f32; 4]; 4] =
+            (self.projection * Self::view_matrix_3d(self.camera.view_matrix())).into();
+        self.queue.write_buffer(
+            &self.world_projection_buffer,
+            0,
+            bytemuck::cast_slice(&[world_projection_raw]),
         );
 
-        self.background_texture_bind_group =
-            crate::gfx::texture::Texture::texture_bind_group(&self.device, &background_texture);
+        self.layers.sort_by_key(|layer| layer.z_order);
+        for layer in &mut self.layers {
+            layer.update(&self.device, &self.queue);
+        }
 
-        let foreground_texture = crate::gfx::texture::Texture::from_image(
-            &self.device,
-            &self.queue,
-            &self.foreground_sprite.image(),
-            "Foreground surface texture",
-            true,
-        );
+        for tilemap in &mut self.tilemaps {
+            tilemap.update(&self.device, &self.queue);
+        }
+        for sprite_batch in &mut self.sprite_batches {
+            sprite_batch.update_buffer(&self.device, &self.queue);
+        }
+        self.text_batch.update(&self.device, &self.queue);
+    }
 
-        self.foreground_texture_bind_group =
-            crate::gfx::texture::Texture::texture_bind_group(&self.device, &foreground_texture);
+    /// Embeds the 2D view matrix into a 3D one that keeps the depth
+    #[rustfmt::skip]
+    fn view_matrix_3d(view: cgmath::Matrix3<f32>) -> cgmath::Matrix4<f32> {
+        cgmath::Matrix4::new(
+            view.x.x, view.x.y, 0.0, 0.0,
+            view.y.x, view.y.y, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            view.z.x, view.z.y, 0.0, 1.0,
+        )
+    }
+
+    /// Returns the size of the layers, which differs from the window size
+    /// when the aspect ratio is kept
+    fn surface_size(&self) -> PhysicalSize<u32> {
+        match self.layers.first() {
+            Some(layer) => PhysicalSize::new(layer.sprite.width(), layer.sprite.height()),
+            None => self.screen_size,
+        }
     }
 
     #[rustfmt::skip]
@@ -349,12 +536,170 @@ impl Renderer2D {
     }
 }
 
+/// Methods related to layers
+///
+/// Every layer is a sprite of the size of the 2D surface. The background and foreground layers
+/// are created with the renderer at z-order 0 and can be changed, but not removed
 impl Renderer2D {
+    /// Returns the sprite of the background layer, which is in screen space like other layers,
+    /// set its `space` to `LayerSpace::World` to draw it through the camera
     pub fn background(&mut self) -> &mut Sprite {
-        &mut self.background_sprite
+        &mut self.layer_by_name(BACKGROUND_LAYER).sprite
     }
 
     pub fn foreground(&mut self) -> &mut Sprite {
-        &mut self.foreground_sprite
+        &mut self.layer_by_name(FOREGROUND_LAYER).sprite
+    }
+
+    /// Adds a transparent layer, adding a layer with the same name replaces the old one
+    pub fn add_layer(&mut self, name: &str, z_order: i32, placement: LayerPlacement) -> &mut Layer {
+        let size = self.surface_size();
+        let layer = Layer::new(
+            &self.device,
+            &self.queue,
+            &self.opacity_bind_group_layout,
+            name,
+            (size.width, size.height),
+            z_order,
+            placement,
+        );
+
+        match self.layers.iter().position(|l| l.name() == name) {
+            Some(index) => {
+                self.layers[index] = layer;
+                &mut self.layers[index]
+            }
+            None => {
+                self.layers.push(layer);
+                self.layers.last_mut().unwrap()
+            }
+        }
+    }
+
+    pub fn get_layer(&mut self, name: &str) -> Option<&mut Layer> {
+        self.layers.iter_mut().find(|l| l.name() == name)
+    }
+
+    /// Removes the layer unless it is the background or the foreground layer
+    pub fn remove_layer(&mut self, name: &str) {
+        if name == BACKGROUND_LAYER || name == FOREGROUND_LAYER {
+            warn!("The {} layer can't be removed", name);
+            return;
+        }
+        self.layers.retain(|l| l.name() != name);
+    }
+
+    /// Returns names of the layers from the bottom one to the top one
+    pub fn layer_names(&self) -> Vec<&str> {
+        let mut layers: Vec<&Layer> = self.layers.iter().collect();
+        layers.sort_by_key(|layer| {
+            (
+                layer.placement == LayerPlacement::InFrontOfScene,
+                layer.z_order,
+            )
+        });
+        layers.iter().map(|layer| layer.name()).collect()
+    }
+
+    fn layer_by_name(&mut self, name: &str) -> &mut Layer {
+        self.get_layer(name)
+            .expect("the background and foreground layers always exist")
+    }
+}
+
+/// Methods related to the camera
+impl Renderer2D {
+    /// Returns the camera world-space layers and sprite batches are drawn through
+    pub fn camera(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    pub fn screen_to_world(&self, point: cgmath::Point2<f32>) -> cgmath::Point2<f32> {
+        self.camera.screen_to_world(point)
+    }
+
+    pub fn world_to_screen(&self, point: cgmath::Point2<f32>) -> cgmath::Point2<f32> {
+        self.camera.world_to_screen(point)
+    }
+}
+
+/// Methods related to sprite batches
+impl Renderer2D {
+    /// Adds a batch that draws instances of the `image` on top of the background
+    /// through the camera
+    ///
+    /// Batches are drawn in the order they were added,
+    /// adding a batch with the same name replaces the old one
+    pub fn add_sprite_batch(&mut self, name: &str, image: &Image, pixelated: bool) {
+        let sprite_batch = SpriteBatch::new(&self.device, &self.queue, name, image, pixelated);
+        match self.sprite_batches.iter_mut().find(|b| b.name == name) {
+            Some(old) => *old = sprite_batch,
+            None => self.sprite_batches.push(sprite_batch),
+        }
+    }
+
+    pub fn get_sprite_batch(&mut self, name: &str) -> Option<&mut SpriteBatch> {
+        self.sprite_batches.iter_mut().find(|b| b.name == name)
+    }
+
+    pub fn remove_sprite_batch(&mut self, name: &str) {
+        self.sprite_batches.retain(|b| b.name != name);
+    }
+}
+
+/// Methods related to tilemaps
+impl Renderer2D {
+    /// Adds sprite batches drawing the visible tile layers of the map with its top left corner
+    /// at the `position` in world coordinates, they are drawn below the sprite batches
+    ///
+    /// Changes of the tiles and the layers are shown after the map is added again,
+    /// adding a map with the same name replaces the old one
+    pub fn add_tilemap(&mut self, name: &str, tilemap: &Tilemap, position: cgmath::Point2<f32>) {
+        let batch = TilemapBatch::new(&self.device, &self.queue, name, tilemap, position);
+        match self.tilemaps.iter_mut().find(|t| t.name == name) {
+            Some(old) => *old = batch,
+            None => self.tilemaps.push(batch),
+        }
+    }
+
+    /// Shows the current frames of the animated tiles, call it after `Tilemap::update`
+    pub fn update_tilemap(&mut self, name: &str, tilemap: &Tilemap) {
+        if let Some(batch) = self.tilemaps.iter_mut().find(|t| t.name == name) {
+            batch.update_animations(tilemap);
+        }
+    }
+
+    pub fn remove_tilemap(&mut self, name: &str) {
+        self.tilemaps.retain(|t| t.name != name);
+    }
+}
+
+/// Methods related to text
+///
+/// Unlike `Sprite::draw_text`, queued text is drawn by the GPU as quads from an atlas texture,
+/// which is cheaper for text that changes often
+impl Renderer2D {
+    /// Returns the fonts loaded at runtime, their handles are used in `FontParameters::Loaded`
+    pub fn fonts(&mut self) -> &mut FontRegistry {
+        &mut self.fonts
+    }
+
+    /// Queues the text laid out in a box of the given size with its top left corner
+    /// at the `position` in world coordinates, it is drawn on top of the sprite batches
+    ///
+    /// Queued text is drawn every frame until `clear_text` is called
+    pub fn queue_text(
+        &mut self,
+        text: &TextParameters,
+        position: cgmath::Point2<f32>,
+        width: u32,
+        height: u32,
+    ) {
+        self.text_batch.queue_text(text, position, width, height);
+    }
+
+    /// Removes all queued text
+    pub fn clear_text(&mut self) {
+        self.text_batch.clear();
     }
 }
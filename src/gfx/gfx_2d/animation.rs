@@ -0,0 +1,356 @@
+use crate::gfx::gfx_2d::sprite::{DrawSpriteParameters, Sprite};
+use crate::gfx::gfx_2d::sprite_sheet::SpriteSheet;
+use log::warn;
+use std::rc::Rc;
+
+/// How a clip continues after its last frame
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AnimationMode {
+    /// Starts again from the first frame
+    Loop,
+    /// Plays the frames backwards to the first one and starts again
+    PingPong,
+    /// Stops at the last frame
+    Once,
+}
+
+/// Frame of a clip
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AnimationFrame {
+    /// Index of the frame in the sprite sheet
+    pub index: usize,
+    /// Time in seconds the frame is shown for
+    pub duration: f32,
+}
+
+/// Sequence of frames of a sprite sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationClip {
+    pub frames: Vec<AnimationFrame>,
+    pub mode: AnimationMode,
+}
+
+impl AnimationClip {
+    /// Creates a clip that shows each of the frames for `frame_duration` seconds
+    pub fn new(frames: &[usize], frame_duration: f32, mode: AnimationMode) -> Self {
+        Self {
+            frames: frames
+                .iter()
+                .map(|&index| AnimationFrame {
+                    index,
+                    duration: frame_duration,
+                })
+                .collect(),
+            mode,
+        }
+    }
+
+    pub fn with_durations(frames: Vec<AnimationFrame>, mode: AnimationMode) -> Self {
+        Self { frames, mode }
+    }
+
+    /// Returns the number of steps in one cycle, in ping-pong mode the frames
+    /// in between the first and the last one are visited twice
+    fn cycle_length(&self) -> usize {
+        match self.mode {
+            AnimationMode::PingPong if self.frames.len() > 1 => 2 * self.frames.len() - 2,
+            _ => self.frames.len(),
+        }
+    }
+
+    /// Returns the frame shown at the `step` of a cycle
+    fn frame_at(&self, step: usize) -> &AnimationFrame {
+        if step < self.frames.len() {
+            &self.frames[step]
+        } else {
+            &self.frames[2 * self.frames.len() - 2 - step]
+        }
+    }
+}
+
+/// Events of an animation, each event is reported only during one frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnimationEvent {
+    /// A looping or ping-pong clip has started again from its first frame
+    ClipLooped { clip: String },
+    /// A clip played once has reached the end of its last frame
+    ClipFinished { clip: String },
+}
+
+/// Plays clips of a sprite sheet
+///
+/// Call `update` with `GameState::dt` every frame and `draw` the current frame
+pub struct AnimatedSprite {
+    sheet: Rc<SpriteSheet>,
+    clip_name: Option<String>,
+    step: usize,
+    elapsed: f32,
+    paused: bool,
+    finished: bool,
+    events: Vec<AnimationEvent>,
+    /// Multiplier of the time, e.g. 2.0 plays clips twice as fast
+    pub speed: f32,
+}
+
+impl AnimatedSprite {
+    pub fn new(sheet: Rc<SpriteSheet>) -> Self {
+        Self {
+            sheet,
+            clip_name: None,
+            step: 0,
+            elapsed: 0.0,
+            paused: false,
+            finished: false,
+            events: vec![],
+            speed: 1.0,
+        }
+    }
+
+    pub fn sheet(&self) -> &Rc<SpriteSheet> {
+        &self.sheet
+    }
+
+    /// Starts playing the clip of the sheet with given name,
+    /// the clip continues if it is already playing and has not finished
+    pub fn play(&mut self, name: &str) {
+        if self.clip_name.as_deref() == Some(name) && !self.finished {
+            self.paused = false;
+            return;
+        }
+        if self.sheet.get_clip(name).is_none() {
+            warn!("Sprite sheet has no clip named {}", name);
+            return;
+        }
+
+        self.clip_name = Some(name.to_string());
+        self.restart();
+    }
+
+    /// Plays the current clip from its first frame
+    pub fn restart(&mut self) {
+        self.step = 0;
+        self.elapsed = 0.0;
+        self.paused = false;
+        self.finished = false;
+    }
+
+    /// Stops playing and shows no frame
+    pub fn stop(&mut self) {
+        self.clip_name = None;
+        self.restart();
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Returns true if the clip played once has reached its end
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn current_clip(&self) -> Option<&str> {
+        self.clip_name.as_deref()
+    }
+
+    /// Returns the index in the sprite sheet of the frame that is shown
+    pub fn current_frame(&self) -> Option<usize> {
+        self.clip()
+            .filter(|clip| !clip.frames.is_empty())
+            .map(|clip| clip.frame_at(self.step).index)
+    }
+
+    /// Returns events reported during the last update
+    pub fn events(&self) -> &[AnimationEvent] {
+        &self.events
+    }
+
+    fn clip(&self) -> Option<&AnimationClip> {
+        self.clip_name
+            .as_ref()
+            .and_then(|name| self.sheet.get_clip(name))
+    }
+
+    /// Advances the animation by `dt` seconds
+    pub fn update(&mut self, dt: f32) {
+        self.events.clear();
+        if self.paused || self.finished {
+            return;
+        }
+
+        let sheet = self.sheet.clone();
+        let (name, clip) = match &self.clip_name {
+            Some(name) => match sheet.get_clip(name) {
+                Some(clip) => (name.clone(), clip),
+                None => return,
+            },
+            None => return,
+        };
+        let cycle_length = clip.cycle_length();
+        let cycle_duration: f32 = (0..cycle_length).map(|s| clip.frame_at(s).duration).sum();
+        if cycle_length == 0 || cycle_duration <= 0.0 {
+            return;
+        }
+
+        self.step %= cycle_length;
+        self.elapsed += dt * self.speed;
+
+        // Whole cycles are skipped at once, so a long frame doesn't step through each of them
+        let cycle_time = (0..self.step)
+            .map(|s| clip.frame_at(s).duration)
+            .sum::<f32>()
+            + self.elapsed;
+        if cycle_time >= cycle_duration {
+            if clip.mode == AnimationMode::Once {
+                self.step = cycle_length - 1;
+                self.elapsed = 0.0;
+                self.finished = true;
+                self.events
+                    .push(AnimationEvent::ClipFinished { clip: name });
+                return;
+            }
+            self.step = 0;
+            self.elapsed = cycle_time % cycle_duration;
+            self.events.push(AnimationEvent::ClipLooped { clip: name });
+        }
+
+        while self.step + 1 < cycle_length && self.elapsed >= clip.frame_at(self.step).duration {
+            self.elapsed -= clip.frame_at(self.step).duration;
+            self.step += 1;
+        }
+    }
+
+    /// Draws the current frame on the `target`, see `SpriteSheet::draw_frame`
+    pub fn draw(
+        &self,
+        target: &mut Sprite,
+        position: cgmath::Point2<f32>,
+        parameters: &DrawSpriteParameters,
+    ) {
+        if let Some(index) = self.current_frame() {
+            self.sheet.draw_frame(target, index, position, parameters);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::texture::Color;
+
+    /// Animation of a sheet of four frames with a clip of the first three in the `mode`
+    fn walking(mode: AnimationMode) -> AnimatedSprite {
+        let mut sheet = SpriteSheet::from_grid(Sprite::new(40, 10, Color::TRANSPARENT), 10, 10);
+        sheet.add_clip("walk", AnimationClip::new(&[0, 1, 2], 0.25, mode));
+        let mut animation = AnimatedSprite::new(Rc::new(sheet));
+        animation.play("walk");
+        animation
+    }
+
+    fn looped() -> Vec<AnimationEvent> {
+        vec![AnimationEvent::ClipLooped {
+            clip: "walk".to_string(),
+        }]
+    }
+
+    #[test]
+    fn frames_advance_with_time() {
+        let mut animation = walking(AnimationMode::Loop);
+        assert_eq!(animation.current_frame(), Some(0));
+        animation.update(0.2);
+        assert_eq!(animation.current_frame(), Some(0));
+        animation.update(0.1);
+        assert_eq!(animation.current_frame(), Some(1));
+        animation.update(0.2);
+        assert_eq!(animation.current_frame(), Some(2));
+        assert!(animation.events().is_empty());
+
+        animation.update(0.25);
+        assert_eq!(animation.current_frame(), Some(0));
+        assert_eq!(animation.events(), looped());
+        animation.update(0.0);
+        assert!(animation.events().is_empty());
+    }
+
+    #[test]
+    fn loop_skips_whole_cycles_at_once() {
+        let mut animation = walking(AnimationMode::Loop);
+        // Two and two thirds cycles of 0.75 seconds
+        animation.update(2.0);
+        assert_eq!(animation.current_frame(), Some(2));
+        assert_eq!(animation.events(), looped());
+    }
+
+    #[test]
+    fn ping_pong_plays_frames_backwards() {
+        let mut animation = walking(AnimationMode::PingPong);
+        let mut frames = vec![];
+        for _ in 0..4 {
+            animation.update(0.25);
+            frames.push(animation.current_frame().unwrap());
+        }
+        assert_eq!(frames, [1, 2, 1, 0]);
+        assert_eq!(animation.events(), looped());
+
+        // The cycle of 0, 1, 2, 1 takes a second
+        let mut animation = walking(AnimationMode::PingPong);
+        animation.update(2.75);
+        assert_eq!(animation.current_frame(), Some(1));
+        assert_eq!(animation.events(), looped());
+    }
+
+    #[test]
+    fn once_stops_at_last_frame() {
+        let mut animation = walking(AnimationMode::Once);
+        animation.update(5.0);
+        assert!(animation.is_finished());
+        assert_eq!(animation.current_frame(), Some(2));
+        assert_eq!(
+            animation.events(),
+            [AnimationEvent::ClipFinished {
+                clip: "walk".to_string()
+            }]
+        );
+
+        animation.update(1.0);
+        assert_eq!(animation.current_frame(), Some(2));
+        assert!(animation.events().is_empty());
+
+        // Playing a finished clip starts it again
+        animation.play("walk");
+        assert!(!animation.is_finished());
+        assert_eq!(animation.current_frame(), Some(0));
+    }
+
+    #[test]
+    fn speed_and_pause_change_the_pace() {
+        let mut animation = walking(AnimationMode::Loop);
+        animation.speed = 2.0;
+        animation.update(0.25);
+        assert_eq!(animation.current_frame(), Some(2));
+
+        animation.pause();
+        animation.update(1.0);
+        assert_eq!(animation.current_frame(), Some(2));
+        animation.resume();
+        animation.update(0.125);
+        assert_eq!(animation.current_frame(), Some(0));
+    }
+
+    #[test]
+    fn unknown_clip_is_not_played() {
+        let mut animation = walking(AnimationMode::Loop);
+        animation.play("run");
+        assert_eq!(animation.current_clip(), Some("walk"));
+        animation.stop();
+        assert_eq!(animation.current_frame(), None);
+    }
+}
@@ -0,0 +1,278 @@
+use cgmath::{EuclideanSpace, SquareMatrix, Transform};
+
+/// Rectangle in world coordinates the view of a camera is kept inside of
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CameraBounds {
+    pub min: cgmath::Point2<f32>,
+    pub max: cgmath::Point2<f32>,
+}
+
+impl CameraBounds {
+    pub fn new(min: cgmath::Point2<f32>, max: cgmath::Point2<f32>) -> Self {
+        Self { min, max }
+    }
+
+    pub fn contains(&self, point: cgmath::Point2<f32>) -> bool {
+        point.x >= self.min.x
+            && point.x <= self.max.x
+            && point.y >= self.min.y
+            && point.y <= self.max.y
+    }
+}
+
+/// View into the 2D world used by world-space layers and sprite batches
+///
+/// Screen coordinates are pixels of the 2D surface, the same ones the cursor position is given in.
+/// The default camera shows the world with its origin in the top left corner of the screen,
+/// so world and screen coordinates match
+#[derive(Debug, Clone)]
+pub struct Camera2D {
+    /// World position shown in the center of the screen
+    pub position: cgmath::Point2<f32>,
+    /// Size of a world unit in screen pixels, values greater than 1.0 zoom in
+    pub zoom: f32,
+    /// Clockwise rotation of the camera, the world appears rotated the other way
+    pub rotation: cgmath::Rad<f32>,
+    /// Area the view is kept inside of, views larger than the area are centered on it
+    pub bounds: Option<CameraBounds>,
+    /// Half size in screen pixels of the area around the center of the screen
+    /// the followed target can move in without moving the camera
+    pub deadzone: cgmath::Vector2<f32>,
+    /// Time in seconds the camera takes to cover about two thirds of the distance
+    /// to the followed target, 0.0 keeps it exactly on the target
+    pub smoothing: f32,
+    viewport_size: cgmath::Vector2<f32>,
+}
+
+impl Camera2D {
+    /// Creates a camera for a screen of the given size in pixels
+    pub fn new(viewport_size: cgmath::Vector2<f32>) -> Self {
+        Self {
+            position: cgmath::Point2::from_vec(viewport_size / 2.0),
+            zoom: 1.0,
+            rotation: cgmath::Rad(0.0),
+            bounds: None,
+            deadzone: cgmath::Vector2::new(0.0, 0.0),
+            smoothing: 0.0,
+            viewport_size,
+        }
+    }
+
+    pub fn viewport_size(&self) -> cgmath::Vector2<f32> {
+        self.viewport_size
+    }
+
+    /// Keeps the world position at the top left corner of the screen in place
+    pub(super) fn set_viewport_size(&mut self, viewport_size: cgmath::Vector2<f32>) {
+        let top_left = self.screen_to_world(cgmath::Point2::new(0.0, 0.0));
+        self.viewport_size = viewport_size;
+        let offset = self.screen_to_world(cgmath::Point2::new(0.0, 0.0)) - top_left;
+        self.position -= offset;
+    }
+
+    /// Returns the matrix transforming world coordinates into screen coordinates
+    pub fn view_matrix(&self) -> cgmath::Matrix3<f32> {
+        let zoom = if self.zoom > 0.0 { self.zoom } else { 1.0 };
+        cgmath::Matrix3::from_translation(self.viewport_size / 2.0)
+            * cgmath::Matrix3::from_angle_z(-self.rotation)
+            * cgmath::Matrix3::from_scale(zoom)
+            * cgmath::Matrix3::from_translation(-self.bounded_position().to_vec())
+    }
+
+    pub fn world_to_screen(&self, point: cgmath::Point2<f32>) -> cgmath::Point2<f32> {
+        self.view_matrix().transform_point(point)
+    }
+
+    pub fn screen_to_world(&self, point: cgmath::Point2<f32>) -> cgmath::Point2<f32> {
+        match self.view_matrix().invert() {
+            Some(inverse) => inverse.transform_point(point),
+            None => point,
+        }
+    }
+
+    /// Returns the smallest rectangle in world coordinates containing the whole screen
+    pub fn visible_area(&self) -> CameraBounds {
+        let (width, height) = (self.viewport_size.x, self.viewport_size.y);
+        let corners = [(0.0, 0.0), (width, 0.0), (width, height), (0.0, height)]
+            .map(|(x, y)| self.screen_to_world(cgmath::Point2::new(x, y)));
+
+        let min_x = corners.iter().map(|c| c.x).fold(f32::INFINITY, f32::min);
+        let min_y = corners.iter().map(|c| c.y).fold(f32::INFINITY, f32::min);
+        let max_x = corners
+            .iter()
+            .map(|c| c.x)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let max_y = corners
+            .iter()
+            .map(|c| c.y)
+            .fold(f32::NEG_INFINITY, f32::max);
+        CameraBounds::new(
+            cgmath::Point2::new(min_x, min_y),
+            cgmath::Point2::new(max_x, max_y),
+        )
+    }
+
+    /// Moves the camera towards the `target` in world coordinates once it leaves the deadzone,
+    /// call it every frame with the time since the last one
+    pub fn follow(&mut self, target: cgmath::Point2<f32>, dt: f32) {
+        // The deadzone is measured on the screen, so it is rotated and scaled into the world
+        let zoom = if self.zoom > 0.0 { self.zoom } else { 1.0 };
+        let offset = cgmath::Matrix2::from_angle(-self.rotation) * (target - self.position) * zoom;
+        let outside = |offset: f32, deadzone: f32| {
+            let deadzone = deadzone.max(0.0);
+            offset - offset.clamp(-deadzone, deadzone)
+        };
+        let screen_shift = cgmath::Vector2::new(
+            outside(offset.x, self.deadzone.x),
+            outside(offset.y, self.deadzone.y),
+        );
+        let shift = cgmath::Matrix2::from_angle(self.rotation) * screen_shift / zoom;
+
+        let t = if self.smoothing > 0.0 {
+            1.0 - (-dt / self.smoothing).exp()
+        } else {
+            1.0
+        };
+        self.position += shift * t;
+        self.position = self.bounded_position();
+    }
+
+    /// Returns the position moved so that the visible area stays inside of the bounds
+    fn bounded_position(&self) -> cgmath::Point2<f32> {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return self.position,
+        };
+
+        // Half size of the visible area, which grows when the view is rotated
+        let zoom = if self.zoom > 0.0 { self.zoom } else { 1.0 };
+        let (sin, cos) = self.rotation.0.sin_cos();
+        let half = self.viewport_size / (2.0 * zoom);
+        let extent = cgmath::Vector2::new(
+            half.x * cos.abs() + half.y * sin.abs(),
+            half.x * sin.abs() + half.y * cos.abs(),
+        );
+
+        let clamp = |position: f32, min: f32, max: f32, extent: f32| {
+            if max - min <= 2.0 * extent {
+                (min + max) / 2.0
+            } else {
+                position.clamp(min + extent, max - extent)
+            }
+        };
+        cgmath::Point2::new(
+            clamp(self.position.x, bounds.min.x, bounds.max.x, extent.x),
+            clamp(self.position.y, bounds.min.y, bounds.max.y, extent.y),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::InnerSpace;
+
+    fn camera() -> Camera2D {
+        Camera2D::new(cgmath::Vector2::new(800.0, 600.0))
+    }
+
+    fn assert_near(actual: cgmath::Point2<f32>, expected: cgmath::Point2<f32>) {
+        assert!(
+            (actual - expected).x.abs() < 1e-3 && (actual - expected).y.abs() < 1e-3,
+            "{:?} is not {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn default_camera_keeps_world_coordinates_on_screen() {
+        let camera = camera();
+        let point = cgmath::Point2::new(120.0, 45.0);
+        assert_near(camera.world_to_screen(point), point);
+        assert_near(camera.screen_to_world(point), point);
+    }
+
+    #[test]
+    fn screen_and_world_coordinates_round_trip() {
+        let mut camera = camera();
+        camera.position = cgmath::Point2::new(100.0, 50.0);
+        camera.zoom = 2.0;
+        camera.rotation = cgmath::Rad(0.5);
+
+        assert_near(
+            camera.world_to_screen(camera.position),
+            cgmath::Point2::new(400.0, 300.0),
+        );
+        // A world unit to the right is two pixels away from the center of the screen
+        let right = camera.world_to_screen(cgmath::Point2::new(101.0, 50.0));
+        assert!(((right - cgmath::Point2::new(400.0, 300.0)).magnitude() - 2.0).abs() < 1e-3);
+
+        for point in [(0.0, 0.0), (-250.0, 75.5), (1000.0, -3.0)] {
+            let point = cgmath::Point2::new(point.0, point.1);
+            assert_near(camera.screen_to_world(camera.world_to_screen(point)), point);
+            assert_near(camera.world_to_screen(camera.screen_to_world(point)), point);
+        }
+    }
+
+    #[test]
+    fn view_is_kept_inside_of_bounds() {
+        let mut camera = camera();
+        camera.bounds = Some(CameraBounds::new(
+            cgmath::Point2::new(0.0, 0.0),
+            cgmath::Point2::new(2000.0, 1000.0),
+        ));
+
+        camera.position = cgmath::Point2::new(0.0, 0.0);
+        let area = camera.visible_area();
+        assert_near(area.min, cgmath::Point2::new(0.0, 0.0));
+        assert_near(area.max, cgmath::Point2::new(800.0, 600.0));
+
+        camera.position = cgmath::Point2::new(1900.0, 950.0);
+        let area = camera.visible_area();
+        assert_near(area.min, cgmath::Point2::new(1200.0, 400.0));
+        assert_near(area.max, cgmath::Point2::new(2000.0, 1000.0));
+
+        // Zooming out makes the view taller than the bounds, so it is centered vertically
+        camera.zoom = 0.5;
+        let area = camera.visible_area();
+        assert_near(area.min, cgmath::Point2::new(400.0, -100.0));
+        assert_near(area.max, cgmath::Point2::new(2000.0, 1100.0));
+    }
+
+    #[test]
+    fn follow_moves_target_to_edge_of_deadzone() {
+        let mut camera = camera();
+        camera.deadzone = cgmath::Vector2::new(50.0, 50.0);
+
+        camera.follow(cgmath::Point2::new(440.0, 260.0), 0.1);
+        assert_near(camera.position, cgmath::Point2::new(400.0, 300.0));
+        camera.follow(cgmath::Point2::new(500.0, 300.0), 0.1);
+        assert_near(camera.position, cgmath::Point2::new(450.0, 300.0));
+
+        // The deadzone is measured in screen pixels, so it is half as large in the world
+        camera.zoom = 2.0;
+        camera.follow(cgmath::Point2::new(550.0, 200.0), 0.1);
+        assert_near(camera.position, cgmath::Point2::new(525.0, 225.0));
+    }
+
+    #[test]
+    fn smoothed_follow_covers_part_of_distance() {
+        let mut camera = camera();
+        camera.smoothing = 1.0;
+        camera.follow(cgmath::Point2::new(500.0, 300.0), 1.0);
+        let covered = 100.0 * (1.0 - (-1.0f32).exp());
+        assert_near(camera.position, cgmath::Point2::new(400.0 + covered, 300.0));
+    }
+
+    #[test]
+    fn follow_stops_at_bounds() {
+        let mut camera = camera();
+        camera.bounds = Some(CameraBounds::new(
+            cgmath::Point2::new(0.0, 0.0),
+            cgmath::Point2::new(1000.0, 1000.0),
+        ));
+        camera.follow(cgmath::Point2::new(5000.0, -20.0), 0.1);
+        assert_near(camera.position, cgmath::Point2::new(600.0, 300.0));
+    }
+}
@@ -0,0 +1,155 @@
+use super::Sprite;
+use crate::gfx::texture::{Color, Texture};
+use wgpu::util::DeviceExt;
+
+/// Whether a layer is drawn before or after the 3D scene
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LayerPlacement {
+    BehindScene,
+    InFrontOfScene,
+}
+
+/// Whether a layer is drawn through the camera
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LayerSpace {
+    /// Pixels of the sprite are world coordinates and the layer is drawn through the camera,
+    /// so it covers the area of the world from the origin to the size of the sprite
+    World,
+    /// Drawing on the layer is in screen pixels, useful for the HUD
+    Screen,
+}
+
+/// Surface sized sprite drawn by the `Renderer2D`
+pub struct Layer {
+    name: String,
+    /// Call `clear` every frame for contents that change, the sprite keeps its pixels otherwise
+    pub sprite: Sprite,
+    /// Layers with greater z-order are drawn on top of layers with lesser one,
+    /// layers with the same z-order are drawn in the order they were added
+    pub z_order: i32,
+    /// Multiplier of the alpha of the whole layer from 0.0 to 1.0
+    pub opacity: f32,
+    pub visible: bool,
+    pub placement: LayerPlacement,
+    pub space: LayerSpace,
+    texture: Texture,
+    texture_bind_group: wgpu::BindGroup,
+    opacity_buffer: wgpu::Buffer,
+    opacity_bind_group: wgpu::BindGroup,
+}
+
+impl Layer {
+    pub(super) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        opacity_bind_group_layout: &wgpu::BindGroupLayout,
+        name: &str,
+        size: (u32, u32),
+        z_order: i32,
+        placement: LayerPlacement,
+    ) -> Self {
+        let mut sprite = Sprite::new(size.0, size.1, Color::TRANSPARENT);
+        let texture = Self::sprite_texture(device, queue, &mut sprite, name);
+        let texture_bind_group = Texture::texture_bind_group(device, &texture);
+
+        let opacity_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} layer opacity buffer", name)),
+            contents: bytemuck::cast_slice(&[1.0_f32, 0.0, 0.0, 0.0]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let opacity_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{} layer opacity bind group", name)),
+            layout: opacity_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: opacity_buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            name: name.to_string(),
+            sprite,
+            z_order,
+            opacity: 1.0,
+            visible: true,
+            placement,
+            space: LayerSpace::Screen,
+            texture,
+            texture_bind_group,
+            opacity_buffer,
+            opacity_bind_group,
+        }
+    }
+
+    pub(super) fn opacity_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("layer_opacity_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns true if drawing the layer would change the image
+    pub(super) fn is_drawn(&self) -> bool {
+        self.visible && self.opacity > 0.0
+    }
+
+    /// Uploads the opacity and the region of the sprite changed since the last upload
+    ///
+    /// The texture and its bind group are only recreated when the size of the sprite has changed
+    pub(super) fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let opacity = self.opacity.clamp(0.0, 1.0);
+        queue.write_buffer(
+            &self.opacity_buffer,
+            0,
+            bytemuck::cast_slice(&[opacity, 0.0, 0.0, 0.0]),
+        );
+
+        let texture_size = self.texture.size;
+        if texture_size.width != self.sprite.width() || texture_size.height != self.sprite.height()
+        {
+            self.texture = Self::sprite_texture(device, queue, &mut self.sprite, &self.name);
+            self.texture_bind_group = Texture::texture_bind_group(device, &self.texture);
+        } else if let Some(region) = self.sprite.take_dirty_region() {
+            self.texture
+                .write_region(queue, self.sprite.bytes(), self.sprite.width(), region);
+        }
+    }
+
+    /// Sets the texture and the opacity of the layer, the pipeline and the quad are set by the renderer
+    pub(super) fn bind<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_bind_group(1, &self.texture_bind_group, &[]);
+        render_pass.set_bind_group(2, &self.opacity_bind_group, &[]);
+    }
+
+    /// Creates a texture with the contents of the sprite
+    fn sprite_texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        sprite: &mut Sprite,
+        label: &str,
+    ) -> Texture {
+        sprite.take_dirty_region();
+        Texture::from_bytes_rgba(
+            device,
+            queue,
+            sprite.bytes(),
+            sprite.width(),
+            sprite.height(),
+            true,
+            Some(&format!("{} layer texture", label)),
+        )
+    }
+}
@@ -1,12 +1,114 @@
-use crate::gfx::gfx_2d::text::{TextParameters, TextRasterizer};
+use crate::gfx::gfx_2d::text::{TextMetrics, TextParameters, TextRasterizer};
 use crate::gfx::texture::Color;
+use cgmath::{EuclideanSpace, SquareMatrix, Transform};
+
+mod antialiasing;
+mod blend_mode;
+mod coverage;
+mod flood_fill;
+mod paint;
+mod path;
+mod shapes;
+mod state;
+pub use antialiasing::*;
+pub use blend_mode::*;
+pub use flood_fill::*;
+pub use paint::*;
+pub use path::*;
+use state::DrawState;
+
+/// Rectangle in pixels given its top left corner and size
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn contains(&self, point: cgmath::Point2<i32>) -> bool {
+        point.x >= self.x
+            && point.y >= self.y
+            && point.x < self.x + self.width as i32
+            && point.y < self.y + self.height as i32
+    }
+
+    /// Returns the overlapping part of both rectangles
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x0 = self.x.max(other.x);
+        let y0 = self.y.max(other.y);
+        let x1 = (self.x + self.width as i32).min(other.x + other.width as i32);
+        let y1 = (self.y + self.height as i32).min(other.y + other.height as i32);
+        if x0 >= x1 || y0 >= y1 {
+            return None;
+        }
+        Some(Rect::new(x0, y0, (x1 - x0) as u32, (y1 - y0) as u32))
+    }
+}
+
+/// How colors are picked from a sprite when it is transformed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Sampling {
+    /// The closest pixel, keeps pixel art sharp
+    Nearest,
+    /// Weighted average of the four closest pixels, smooths the result
+    Bilinear,
+}
+
+/// Describes how `Sprite::draw_sprite_ex` transforms the sprite
+#[derive(Debug, Copy, Clone)]
+pub struct DrawSpriteParameters {
+    /// Part of the sprite to draw, the whole sprite if `None`
+    pub source: Option<Rect>,
+    pub scale: cgmath::Vector2<f32>,
+    /// Clockwise rotation around the pivot
+    pub rotation: cgmath::Rad<f32>,
+    /// Point in pixels of the source rectangle that is placed at the position,
+    /// the sprite is rotated and scaled around it
+    pub pivot: cgmath::Point2<f32>,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    /// Color the sprite is multiplied by
+    pub tint: Color,
+    pub sampling: Sampling,
+}
+
+impl Default for DrawSpriteParameters {
+    fn default() -> Self {
+        Self {
+            source: None,
+            scale: cgmath::Vector2::new(1.0, 1.0),
+            rotation: cgmath::Rad(0.0),
+            pivot: cgmath::Point2::new(0.0, 0.0),
+            flip_horizontal: false,
+            flip_vertical: false,
+            tint: Color::WHITE,
+            sampling: Sampling::Nearest,
+        }
+    }
+}
 
 pub struct Sprite {
     width: u32,
     height: u32,
     clear_color: Color,
+    /// Call `mark_dirty` after changing the values directly, so they are uploaded to the GPU
     pub values: Vec<Color>,
     text_rasterizer: TextRasterizer,
+    /// Bounds of the pixels changed since the last upload: min x, min y, max x, max y
+    dirty_region: Option<(u32, u32, u32, u32)>,
+    state: DrawState,
+    saved_states: Vec<DrawState>,
 }
 
 impl Sprite {
@@ -17,6 +119,9 @@ impl Sprite {
             clear_color,
             values: vec![clear_color.into(); (width * height) as usize],
             text_rasterizer: TextRasterizer::new(),
+            dirty_region: Self::whole_region(width, height),
+            state: DrawState::default(),
+            saved_states: vec![],
         }
     }
 
@@ -33,16 +138,77 @@ impl Sprite {
             clear_color: Color::TRANSPARENT,
             values,
             text_rasterizer: TextRasterizer::new(),
+            dirty_region: Self::whole_region(width, height),
+            state: DrawState::default(),
+            saved_states: vec![],
         }
     }
 
-    /// Draws a point on the surface
+    /// Draws a point on the surface, a transformed point covers the transformed pixel
     pub fn draw_pixel(&mut self, position: cgmath::Point2<i32>, color: Color) {
-        if let Some(dst) = self
-            .values
-            .get_mut((position.y * self.width as i32 + position.x) as usize)
-        {
-            *dst = Color::blend(dst, &color.premultiply());
+        if self.is_translation() {
+            let position = self.device_pixel(position);
+            self.put_pixel(position.x, position.y, color);
+        } else {
+            let (x0, y0) = (position.x as f32, position.y as f32);
+            self.fill_device_polygon(&self.local_rectangle(x0, y0, x0 + 1.0, y0 + 1.0), color);
+        }
+    }
+
+    /// Blends the color with the pixel of the sprite using the current blend mode unless it lies
+    /// outside of the sprite or the clip rectangle, all drawing ends up here after applying the transform
+    fn put_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+        if self.is_clipped(x, y) {
+            return;
+        }
+
+        let index = (y as u32 * self.width + x as u32) as usize;
+        let dst = &mut self.values[index];
+        *dst = self.state.blend_mode.blend(dst, &color.premultiply());
+        self.mark_pixel_dirty(x as u32, y as u32);
+    }
+
+    /// Returns corners of the local rectangle transformed into pixels of the sprite
+    fn local_rectangle(&self, x0: f32, y0: f32, x1: f32, y1: f32) -> [cgmath::Point2<f32>; 4] {
+        [(x0, y0), (x1, y0), (x1, y1), (x0, y1)]
+            .map(|(x, y)| self.to_device(cgmath::Point2::new(x, y)))
+    }
+
+    /// Fills pixels of the sprite whose centers lie inside of the convex polygon
+    fn fill_device_polygon(&mut self, points: &[cgmath::Point2<f32>], color: Color) {
+        let area: f32 = (0..points.len())
+            .map(|i| {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum();
+        if points.len() < 3 || area == 0.0 {
+            return;
+        }
+
+        let min_x = points.iter().map(|p| p.x).fold(f32::INFINITY, f32::min);
+        let min_y = points.iter().map(|p| p.y).fold(f32::INFINITY, f32::min);
+        let max_x = points.iter().map(|p| p.x).fold(f32::NEG_INFINITY, f32::max);
+        let max_y = points.iter().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max);
+        let x0 = (min_x.floor() as i32).max(0);
+        let y0 = (min_y.floor() as i32).max(0);
+        let x1 = (max_x.ceil() as i32).min(self.width as i32);
+        let y1 = (max_y.ceil() as i32).min(self.height as i32);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let inside = (0..points.len()).all(|i| {
+                    let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                    ((b.x - a.x) * (py - a.y) - (b.y - a.y) * (px - a.x)) * area.signum() >= 0.0
+                });
+                if inside {
+                    self.put_pixel(x, y, color);
+                }
+            }
         }
     }
 
@@ -52,6 +218,17 @@ impl Sprite {
         start: cgmath::Point2<i32>,
         end: cgmath::Point2<i32>,
         color: Color,
+    ) {
+        let (start, end) = (self.device_pixel(start), self.device_pixel(end));
+        self.draw_device_line(start, end, color);
+    }
+
+    /// Draws line between pixels of the sprite ignoring the transform
+    fn draw_device_line(
+        &mut self,
+        start: cgmath::Point2<i32>,
+        end: cgmath::Point2<i32>,
+        color: Color,
     ) {
         let dx = i32::abs(end.x - start.x);
         let dy = i32::abs(end.y - start.y);
@@ -65,7 +242,7 @@ impl Sprite {
             };
 
             for y in y0..=y1 {
-                self.draw_pixel((start.x, y).into(), color);
+                self.put_pixel(start.x, y, color);
             }
             return;
         }
@@ -79,7 +256,7 @@ impl Sprite {
             };
 
             for x in x0..=x1 {
-                self.draw_pixel((x, start.y).into(), color);
+                self.put_pixel(x, start.y, color);
             }
             return;
         }
@@ -118,7 +295,7 @@ impl Sprite {
         let mut x = start.x;
 
         for y in start.y..=end.y {
-            self.draw_pixel((x, y).into(), color);
+            self.put_pixel(x, y, color);
 
             if d > 0 {
                 x += xi;
@@ -148,7 +325,7 @@ impl Sprite {
         let mut y = start.y;
 
         for x in start.x..=end.x {
-            self.draw_pixel((x, y).into(), color);
+            self.put_pixel(x, y, color);
             if d > 0 {
                 y += yi;
                 d += 2 * (dy - dx);
@@ -178,10 +355,20 @@ impl Sprite {
                 (start.y, end.y)
             };
 
-            for y in y0..=y1 {
-                for x in x0..=x1 {
-                    self.draw_pixel((x, y).into(), color);
+            if self.is_translation() {
+                let (min, max) = (
+                    self.device_pixel((x0, y0).into()),
+                    self.device_pixel((x1, y1).into()),
+                );
+                for y in min.y..=max.y {
+                    for x in min.x..=max.x {
+                        self.put_pixel(x, y, color);
+                    }
                 }
+            } else {
+                let corners =
+                    self.local_rectangle(x0 as f32, y0 as f32, x1 as f32 + 1.0, y1 as f32 + 1.0);
+                self.fill_device_polygon(&corners, color);
             }
         } else {
             self.draw_line((start.x, start.y).into(), (end.x, start.y).into(), color);
@@ -199,6 +386,23 @@ impl Sprite {
         color: Color,
         fill: bool,
     ) {
+        if !self.is_similarity() {
+            // The transformed circle is an ellipse, which is drawn as a path
+            // covering the same pixels the circle covers without the transform
+            let center = cgmath::Point2::new(center.x as f32 + 0.5, center.y as f32 + 0.5);
+            let radius = if fill {
+                radius as f32 + 0.5
+            } else {
+                radius as f32
+            };
+            self.draw_circle_aa(center, radius, color, fill);
+            return;
+        }
+
+        // The transform only rotates and scales uniformly, so the radius is scaled by its scale
+        let center = self.device_pixel(center);
+        let radius = (radius as f32 * self.device_scale()).round() as u32;
+
         let mut x = 0;
         let mut y: i32 = radius as i32;
         let mut d = 5 - 4 * radius as i32;
@@ -227,22 +431,22 @@ impl Sprite {
 
     #[rustfmt::skip]
     fn draw_circle_octants(&mut self, center: cgmath::Point2<i32>, x: i32, y: i32, color: Color) {
-        self.draw_pixel((center.x + x, center.y + y).into(), color);
-        self.draw_pixel((center.x - x, center.y + y).into(), color);
-        self.draw_pixel((center.x + x, center.y - y).into(), color);
-        self.draw_pixel((center.x - x, center.y - y).into(), color);
-        self.draw_pixel((center.x + y, center.y + x).into(), color);
-        self.draw_pixel((center.x - y, center.y + x).into(), color);
-        self.draw_pixel((center.x + y, center.y - x).into(), color);
-        self.draw_pixel((center.x - y, center.y - x).into(), color);
+        self.put_pixel(center.x + x, center.y + y, color);
+        self.put_pixel(center.x - x, center.y + y, color);
+        self.put_pixel(center.x + x, center.y - y, color);
+        self.put_pixel(center.x - x, center.y - y, color);
+        self.put_pixel(center.x + y, center.y + x, color);
+        self.put_pixel(center.x - y, center.y + x, color);
+        self.put_pixel(center.x + y, center.y - x, color);
+        self.put_pixel(center.x - y, center.y - x, color);
     }
 
     #[rustfmt::skip]
     fn draw_circle_octants_filled(&mut self, center: cgmath::Point2<i32>, x: i32, y: i32, color: Color) {
-        self.draw_line((center.x - x, center.y + y).into(), (center.x + x, center.y + y).into(), color);
-        self.draw_line((center.x - x, center.y - y).into(), (center.x + x, center.y - y).into(), color);
-        self.draw_line((center.x - y, center.y + x).into(), (center.x + y, center.y + x).into(), color);
-        self.draw_line((center.x - y, center.y - x).into(), (center.x + y, center.y - x).into(), color);
+        self.draw_device_line((center.x - x, center.y + y).into(), (center.x + x, center.y + y).into(), color);
+        self.draw_device_line((center.x - x, center.y - y).into(), (center.x + x, center.y - y).into(), color);
+        self.draw_device_line((center.x - y, center.y + x).into(), (center.x + y, center.y + x).into(), color);
+        self.draw_device_line((center.x - y, center.y - x).into(), (center.x + y, center.y - x).into(), color);
     }
 
     /// Draw triangle using [Standard Algorithm](http://www.sunshine2k.de/coding/java/TriangleRasterization/TriangleRasterization.html#:~:text=II.%20Standard%20Algorithm)
@@ -254,12 +458,17 @@ impl Sprite {
         color: Color,
         fill: bool,
     ) {
+        let (p0, p1, p2) = (
+            self.device_pixel(p0),
+            self.device_pixel(p1),
+            self.device_pixel(p2),
+        );
         if fill {
             self.draw_triangle_filled(p0, p1, p2, color);
         } else {
-            self.draw_line(p0, p1, color);
-            self.draw_line(p1, p2, color);
-            self.draw_line(p2, p0, color);
+            self.draw_device_line(p0, p1, color);
+            self.draw_device_line(p1, p2, color);
+            self.draw_device_line(p2, p0, color);
         }
     }
 
@@ -320,7 +529,7 @@ impl Sprite {
         let mut current_x2 = p0.x as f32;
 
         for scanline_y in p0.y..=p1.y {
-            self.draw_line(
+            self.draw_device_line(
                 cgmath::Point2::new(current_x1 as i32, scanline_y),
                 cgmath::Point2::new(current_x2 as i32, scanline_y),
                 color,
@@ -344,7 +553,7 @@ impl Sprite {
         let mut current_x2 = p2.x as f32;
 
         for scanline_y in (p0.y..p2.y).rev() {
-            self.draw_line(
+            self.draw_device_line(
                 cgmath::Point2::new(current_x1 as i32, scanline_y),
                 cgmath::Point2::new(current_x2 as i32, scanline_y),
                 color,
@@ -359,20 +568,164 @@ impl Sprite {
             *el = clear_color;
         }
         self.clear_color = clear_color;
+        self.mark_dirty();
     }
 
     /// Draws sprite given its top left corner as position
     pub fn draw_sprite(&mut self, sprite: &Sprite, position: cgmath::Point2<i32>) {
+        if !self.is_translation() {
+            let position = cgmath::Point2::new(position.x as f32, position.y as f32);
+            self.draw_sprite_ex(sprite, position, &DrawSpriteParameters::default());
+            return;
+        }
+
+        let position = self.device_pixel(position);
         for y in 0..sprite.height {
             for x in 0..sprite.width {
                 let pixel = sprite.pixel(x, y);
                 let x = x as i32 + position.x;
                 let y = y as i32 + position.y;
-                self.draw_pixel((x, y).into(), pixel);
+                self.put_pixel(x, y, pixel);
+            }
+        }
+    }
+
+    /// Draws a part of the sprite scaled, rotated and flipped so that its pivot is at `position`
+    ///
+    /// Only the pixels inside of this sprite are visited, so transformed sprites are clipped
+    /// against its bounds
+    pub fn draw_sprite_ex(
+        &mut self,
+        sprite: &Sprite,
+        position: cgmath::Point2<f32>,
+        parameters: &DrawSpriteParameters,
+    ) {
+        let source = parameters
+            .source
+            .unwrap_or_else(|| Rect::new(0, 0, sprite.width, sprite.height));
+        let scale = parameters.scale;
+        if source.width == 0 || source.height == 0 || scale.x == 0.0 || scale.y == 0.0 {
+            return;
+        }
+
+        let pivot = parameters.pivot;
+        let (width, height) = (source.width as f32, source.height as f32);
+
+        // Source pixels are moved so that the pivot is at the origin, scaled, rotated,
+        // moved to the position and finally transformed by the current transform
+        let transform = self.state.transform
+            * cgmath::Matrix3::from_translation(position.to_vec())
+            * cgmath::Matrix3::from_angle_z(parameters.rotation)
+            * cgmath::Matrix3::from_nonuniform_scale(scale.x, scale.y)
+            * cgmath::Matrix3::from_translation(-pivot.to_vec());
+        let inverse = match transform.invert() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+
+        // Find the bounds of the transformed source rectangle on this sprite
+        let corners = [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)]
+            .map(|(x, y)| transform.transform_point(cgmath::Point2::new(x, y)));
+        let min_x = corners.iter().map(|c| c.x).fold(f32::INFINITY, f32::min);
+        let max_x = corners
+            .iter()
+            .map(|c| c.x)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let min_y = corners.iter().map(|c| c.y).fold(f32::INFINITY, f32::min);
+        let max_y = corners
+            .iter()
+            .map(|c| c.y)
+            .fold(f32::NEG_INFINITY, f32::max);
+
+        let x0 = (min_x.floor() as i32).max(0);
+        let x1 = (max_x.ceil() as i32).min(self.width as i32);
+        let y0 = (min_y.floor() as i32).max(0);
+        let y1 = (max_y.ceil() as i32).min(self.height as i32);
+
+        // Map centers of the covered pixels back onto the source rectangle
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let center = cgmath::Point2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let source_point = inverse.transform_point(center);
+                let (mut u, mut v) = (source_point.x, source_point.y);
+                if u < 0.0 || u >= width || v < 0.0 || v >= height {
+                    continue;
+                }
+
+                if parameters.flip_horizontal {
+                    u = width - u;
+                }
+                if parameters.flip_vertical {
+                    v = height - v;
+                }
+
+                let color = match parameters.sampling {
+                    Sampling::Nearest => sprite.sample_nearest(&source, u, v),
+                    Sampling::Bilinear => sprite.sample_bilinear(&source, u, v),
+                };
+
+                let tint = parameters.tint;
+                let color = Color::new(
+                    (color.r as u16 * tint.r as u16 / 255) as u8,
+                    (color.g as u16 * tint.g as u16 / 255) as u8,
+                    (color.b as u16 * tint.b as u16 / 255) as u8,
+                    (color.a as u16 * tint.a as u16 / 255) as u8,
+                );
+                self.put_pixel(x, y, color);
             }
         }
     }
 
+    /// Returns the pixel of the source rectangle at `u`, `v`
+    /// or a transparent one if it lies outside of the sprite
+    fn source_pixel(&self, source: &Rect, u: i32, v: i32) -> Color {
+        let (x, y) = (source.x + u, source.y + v);
+        let inside_source = u >= 0 && v >= 0 && u < source.width as i32 && v < source.height as i32;
+        if inside_source && x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
+            self.pixel(x as u32, y as u32)
+        } else {
+            Color::TRANSPARENT
+        }
+    }
+
+    fn sample_nearest(&self, source: &Rect, u: f32, v: f32) -> Color {
+        self.source_pixel(source, u.floor() as i32, v.floor() as i32)
+    }
+
+    /// Interpolates premultiplied colors of the four closest pixels,
+    /// pixels outside of the source rectangle are transparent, which smooths its edges
+    fn sample_bilinear(&self, source: &Rect, u: f32, v: f32) -> Color {
+        let (u, v) = (u - 0.5, v - 0.5);
+        let (u0, v0) = (u.floor(), v.floor());
+        let (tu, tv) = (u - u0, v - v0);
+        let (u0, v0) = (u0 as i32, v0 as i32);
+
+        let mut sum = [0.0_f32; 4];
+        for (du, dv, weight) in [
+            (0, 0, (1.0 - tu) * (1.0 - tv)),
+            (1, 0, tu * (1.0 - tv)),
+            (0, 1, (1.0 - tu) * tv),
+            (1, 1, tu * tv),
+        ] {
+            let pixel = self.source_pixel(source, u0 + du, v0 + dv);
+            let alpha = pixel.a as f32 * weight;
+            sum[0] += pixel.r as f32 * alpha;
+            sum[1] += pixel.g as f32 * alpha;
+            sum[2] += pixel.b as f32 * alpha;
+            sum[3] += alpha;
+        }
+
+        if sum[3] <= 0.0 {
+            return Color::TRANSPARENT;
+        }
+        Color::new(
+            (sum[0] / sum[3]).round() as u8,
+            (sum[1] / sum[3]).round() as u8,
+            (sum[2] / sum[3]).round() as u8,
+            sum[3].round() as u8,
+        )
+    }
+
     pub fn image(&self) -> image::DynamicImage {
         let img_buffer =
             image::ImageBuffer::from_fn(self.width as u32, self.height as u32, |x, y| {
@@ -390,25 +743,61 @@ impl Sprite {
         width: u32,
         height: u32,
     ) {
-        let raw_data = self
-            .text_rasterizer
-            .get_rgba_from_text(&text, width, height);
-        for i in (0..raw_data.len()).step_by(4) {
-            let pixel_index = i / 4;
-            let x = position.x + (pixel_index as i32 % width as i32);
-            let y = position.y + (pixel_index as i32 - x) / width as i32;
-
-            let color = Color::new(
-                raw_data[i],
-                raw_data[i + 1],
-                raw_data[i + 2],
-                raw_data[i + 3],
-            );
+        if !self.is_translation() {
+            // Text is drawn on a sprite of its own first, which is then drawn following the transform
+            let mut text_sprite = Sprite::new(width, height, Color::TRANSPARENT);
+            std::mem::swap(&mut self.text_rasterizer, &mut text_sprite.text_rasterizer);
+            text_sprite.draw_text(text, cgmath::Point2::new(0, 0), width, height);
+            std::mem::swap(&mut self.text_rasterizer, &mut text_sprite.text_rasterizer);
+            let position = cgmath::Point2::new(position.x as f32, position.y as f32);
+            self.draw_sprite_ex(&text_sprite, position, &DrawSpriteParameters::default());
+            return;
+        }
+
+        let position = self.device_pixel(position);
+        let area = Rect::new(position.x, position.y, width, height);
+        let placed = self.text_rasterizer.placed_text(text, width, height);
+        // Aligned text can overflow the box, those parts are cut off
+        let mut put_clipped = |x: i32, y: i32, color: Color| {
+            if area.contains(cgmath::Point2::new(x, y)) {
+                self.put_pixel(x, y, color);
+            }
+        };
 
-            self.draw_pixel((x, y).into(), color);
+        for glyph in &placed.glyphs {
+            let color = glyph.color;
+            glyph.glyph.for_each_pixel(|x, y, coverage| {
+                let alpha = (coverage as u16 * color.a as u16 / 255) as u8;
+                put_clipped(
+                    position.x + glyph.origin.x + x,
+                    position.y + glyph.origin.y + y,
+                    Color::new(color.r, color.g, color.b, alpha),
+                );
+            });
+        }
+        for image in &placed.images {
+            for (x, y, pixel) in image.image.image().enumerate_pixels() {
+                put_clipped(
+                    position.x + image.origin.x + x as i32,
+                    position.y + image.origin.y + y as i32,
+                    Color::from(*pixel),
+                );
+            }
+        }
+        for (line, color) in &placed.lines {
+            for y in line.y..line.y + line.height as i32 {
+                for x in line.x..line.x + line.width as i32 {
+                    put_clipped(position.x + x, position.y + y, *color);
+                }
+            }
         }
     }
 
+    /// Returns the area `draw_text` would fill with lines of the text in a box of the given size
+    pub fn measure_text(&self, text: &TextParameters, width: u32, height: u32) -> TextMetrics {
+        self.text_rasterizer.measure_text(text, width, height)
+    }
+
     pub fn width(&self) -> u32 {
         self.width
     }
@@ -422,9 +811,46 @@ impl Sprite {
         self.height = new_size.height;
         self.values
             .resize((self.width * self.height) as usize, self.clear_color);
+        self.mark_dirty();
     }
 
     pub fn pixel(&self, x: u32, y: u32) -> Color {
         self.values[(y * self.width + x) as usize]
     }
+
+    /// Marks the whole sprite as changed, so it is uploaded to the GPU entirely
+    pub fn mark_dirty(&mut self) {
+        self.dirty_region = Self::whole_region(self.width, self.height);
+    }
+
+    fn whole_region(width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some((0, 0, width - 1, height - 1))
+    }
+
+    fn mark_pixel_dirty(&mut self, x: u32, y: u32) {
+        self.dirty_region = Some(match self.dirty_region {
+            Some((min_x, min_y, max_x, max_y)) => {
+                (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+            }
+            None => (x, y, x, y),
+        });
+    }
+
+    /// Returns the region changed since the last call as `(x, y, width, height)`
+    /// and considers the sprite unchanged
+    pub(crate) fn take_dirty_region(&mut self) -> Option<(u32, u32, u32, u32)> {
+        self.dirty_region
+            .take()
+            .map(|(min_x, min_y, max_x, max_y)| {
+                (min_x, min_y, max_x - min_x + 1, max_y - min_y + 1)
+            })
+    }
+
+    /// Returns pixels of the sprite as RGBA bytes
+    pub(crate) fn bytes(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.values)
+    }
 }
@@ -0,0 +1,412 @@
+use super::coverage::CoverageMask;
+use super::paint::Paint;
+use super::path::{FillRule, Path};
+use super::Sprite;
+use crate::gfx::texture::Color;
+use cgmath::{InnerSpace, Transform};
+
+/// Shape of the ends of open strokes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineCap {
+    /// The stroke ends exactly at its end points
+    Butt,
+    /// The stroke is extended by a half circle
+    Round,
+    /// The stroke is extended by half of its width
+    Square,
+}
+
+/// Shape of the corners where segments of a stroke meet
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineJoin {
+    /// Sharp corner, replaced by a bevel when longer than the miter limit
+    Miter,
+    Round,
+    /// Corner cut off by a straight line
+    Bevel,
+}
+
+/// Describes how the anti-aliased strokes are drawn
+#[derive(Debug, Copy, Clone)]
+pub struct StrokeParameters {
+    pub color: Color,
+    /// Width of the stroke in pixels
+    pub width: f32,
+    pub cap: LineCap,
+    pub join: LineJoin,
+    /// Longest ratio of the length of a miter to the width of the stroke
+    pub miter_limit: f32,
+}
+
+impl Default for StrokeParameters {
+    fn default() -> Self {
+        Self {
+            color: Color::BLACK,
+            width: 1.0,
+            cap: LineCap::Butt,
+            join: LineJoin::Miter,
+            miter_limit: 4.0,
+        }
+    }
+}
+
+/// Methods related to anti-aliased drawing
+///
+/// Positions are in pixels, pixel `(x, y)` spans from `x` to `x + 1`,
+/// so its center is at `x + 0.5`
+impl Sprite {
+    /// Draws a thin line from `start` to `end` using [Xiaolin Wu's line algorithm](https://en.wikipedia.org/wiki/Xiaolin_Wu%27s_line_algorithm)
+    pub fn draw_line_aa(
+        &mut self,
+        start: cgmath::Point2<f32>,
+        end: cgmath::Point2<f32>,
+        color: Color,
+    ) {
+        // The algorithm expects pixel centers at whole coordinates
+        let (start, end) = (
+            self.to_device(start) - cgmath::Vector2::new(0.5, 0.5),
+            self.to_device(end) - cgmath::Vector2::new(0.5, 0.5),
+        );
+        let steep = (end.y - start.y).abs() > (end.x - start.x).abs();
+        let (mut x0, mut y0, mut x1, mut y1) = if steep {
+            (start.y, start.x, end.y, end.x)
+        } else {
+            (start.x, start.y, end.x, end.y)
+        };
+        if x0 > x1 {
+            std::mem::swap(&mut x0, &mut x1);
+            std::mem::swap(&mut y0, &mut y1);
+        }
+
+        let dx = x1 - x0;
+        let gradient = if dx == 0.0 { 1.0 } else { (y1 - y0) / dx };
+        let mut plot = |x: i32, y: i32, coverage: f32| {
+            if steep {
+                self.blend_pixel(y, x, color, coverage);
+            } else {
+                self.blend_pixel(x, y, color, coverage);
+            }
+        };
+
+        // First end point
+        let x_end = x0.round();
+        let y_end = y0 + gradient * (x_end - x0);
+        let x_gap = 1.0 - (x0 + 0.5).fract();
+        let x_start_pixel = x_end as i32;
+        plot(
+            x_start_pixel,
+            y_end.floor() as i32,
+            (1.0 - y_end.fract()) * x_gap,
+        );
+        plot(
+            x_start_pixel,
+            y_end.floor() as i32 + 1,
+            y_end.fract() * x_gap,
+        );
+        let mut y = y_end + gradient;
+
+        // Second end point
+        let x_end = x1.round();
+        let y_end = y1 + gradient * (x_end - x1);
+        let x_gap = (x1 + 0.5).fract();
+        let x_end_pixel = x_end as i32;
+        plot(
+            x_end_pixel,
+            y_end.floor() as i32,
+            (1.0 - y_end.fract()) * x_gap,
+        );
+        plot(x_end_pixel, y_end.floor() as i32 + 1, y_end.fract() * x_gap);
+
+        for x in (x_start_pixel + 1)..x_end_pixel {
+            plot(x, y.floor() as i32, 1.0 - y.fract());
+            plot(x, y.floor() as i32 + 1, y.fract());
+            y += gradient;
+        }
+    }
+
+    /// Draws circle with edges smoothed by the area of the pixels it covers
+    pub fn draw_circle_aa(
+        &mut self,
+        center: cgmath::Point2<f32>,
+        radius: f32,
+        color: Color,
+        fill: bool,
+    ) {
+        if !self.is_similarity() {
+            let ellipse = Path::ellipse(center, cgmath::Vector2::new(radius, radius));
+            if fill {
+                self.fill_path(&ellipse, FillRule::NonZero, color);
+            } else {
+                let parameters = StrokeParameters {
+                    color,
+                    ..Default::default()
+                };
+                self.stroke_path(&ellipse, &parameters);
+            }
+            return;
+        }
+
+        let (center, radius) = (self.to_device(center), radius * self.device_scale());
+        let mut mask = CoverageMask::around(&[center], radius + 1.0, self.width, self.height);
+        if fill {
+            mask.add_disc(center, radius);
+        } else {
+            mask.add_ring(center, radius, 1.0);
+        }
+        self.fill_mask(&mask, &Paint::Solid(color), 1.0);
+    }
+
+    /// Draws triangle with edges smoothed by the area of the pixels it covers
+    pub fn draw_triangle_aa(
+        &mut self,
+        p0: cgmath::Point2<f32>,
+        p1: cgmath::Point2<f32>,
+        p2: cgmath::Point2<f32>,
+        color: Color,
+        fill: bool,
+    ) {
+        if fill {
+            let (p0, p1, p2) = (self.to_device(p0), self.to_device(p1), self.to_device(p2));
+            let mut mask = CoverageMask::around(&[p0, p1, p2], 1.0, self.width, self.height);
+            mask.add_convex_polygon(&[p0, p1, p2]);
+            self.fill_mask(&mask, &Paint::Solid(color), 1.0);
+        } else {
+            self.draw_line_aa(p0, p1, color);
+            self.draw_line_aa(p1, p2, color);
+            self.draw_line_aa(p2, p0, color);
+        }
+    }
+
+    pub fn stroke_line(
+        &mut self,
+        start: cgmath::Point2<f32>,
+        end: cgmath::Point2<f32>,
+        parameters: &StrokeParameters,
+    ) {
+        self.stroke_polyline(&[start, end], false, parameters);
+    }
+
+    pub fn stroke_triangle(
+        &mut self,
+        p0: cgmath::Point2<f32>,
+        p1: cgmath::Point2<f32>,
+        p2: cgmath::Point2<f32>,
+        parameters: &StrokeParameters,
+    ) {
+        self.stroke_polyline(&[p0, p1, p2], true, parameters);
+    }
+
+    pub fn stroke_circle(
+        &mut self,
+        center: cgmath::Point2<f32>,
+        radius: f32,
+        parameters: &StrokeParameters,
+    ) {
+        if !self.is_similarity() {
+            let ellipse = Path::ellipse(center, cgmath::Vector2::new(radius, radius));
+            self.stroke_path(&ellipse, parameters);
+            return;
+        }
+
+        let (width, opacity) = self.stroke_width(parameters);
+        let (center, radius) = (self.to_device(center), radius * self.device_scale());
+        let mut mask = CoverageMask::around(&[center], radius + width, self.width, self.height);
+        mask.add_ring(center, radius, width);
+        self.fill_mask(&mask, &Paint::Solid(parameters.color), opacity);
+    }
+
+    /// Draws connected segments through the `points`, the last point is connected
+    /// to the first one if the polyline is `closed`
+    ///
+    /// Overlapping parts of the stroke are blended only once
+    pub fn stroke_polyline(
+        &mut self,
+        points: &[cgmath::Point2<f32>],
+        closed: bool,
+        parameters: &StrokeParameters,
+    ) {
+        self.stroke_polylines(&[(points.to_vec(), closed)], parameters);
+    }
+
+    /// Strokes all the polylines given with their closedness at once,
+    /// so that their overlapping parts are blended only once
+    pub(super) fn stroke_polylines(
+        &mut self,
+        polylines: &[(Vec<cgmath::Point2<f32>>, bool)],
+        parameters: &StrokeParameters,
+    ) {
+        let (width, opacity) = self.stroke_width(parameters);
+        let half_width = width / 2.0;
+        let margin = half_width * parameters.miter_limit.max(1.0) + 1.0;
+        let polylines: Vec<(Vec<cgmath::Point2<f32>>, bool)> = polylines
+            .iter()
+            .map(|(points, closed)| (points.iter().map(|p| self.to_device(*p)).collect(), *closed))
+            .collect();
+        let all_points: Vec<cgmath::Point2<f32>> = polylines
+            .iter()
+            .flat_map(|(points, _)| points.iter().copied())
+            .collect();
+        let mut mask = CoverageMask::around(&all_points, margin, self.width, self.height);
+
+        for (points, closed) in polylines.iter() {
+            Self::add_polyline_stroke(&mut mask, points, *closed, half_width, parameters);
+        }
+        self.fill_mask(&mask, &Paint::Solid(parameters.color), opacity);
+    }
+
+    fn add_polyline_stroke(
+        mask: &mut CoverageMask,
+        points: &[cgmath::Point2<f32>],
+        closed: bool,
+        half_width: f32,
+        parameters: &StrokeParameters,
+    ) {
+        let mut points: Vec<cgmath::Point2<f32>> = points.to_vec();
+        points.dedup_by(|a, b| (*a - *b).magnitude2() < f32::EPSILON);
+        if closed
+            && points.len() > 1
+            && (points[0] - points[points.len() - 1]).magnitude2() < f32::EPSILON
+        {
+            points.pop();
+        }
+        if points.is_empty() {
+            return;
+        }
+
+        if points.len() == 1 {
+            let point = points[0];
+            match parameters.cap {
+                LineCap::Butt => {}
+                LineCap::Round => mask.add_disc(point, half_width),
+                LineCap::Square => {
+                    let extent = cgmath::Vector2::new(half_width, half_width);
+                    let (min, max) = (point - extent, point + extent);
+                    mask.add_convex_polygon(&[
+                        min,
+                        cgmath::Point2::new(max.x, min.y),
+                        max,
+                        cgmath::Point2::new(min.x, max.y),
+                    ]);
+                }
+            }
+            return;
+        }
+
+        let segment_count = if closed && points.len() > 2 {
+            points.len()
+        } else {
+            points.len() - 1
+        };
+        let segment = |i: usize| (points[i], points[(i + 1) % points.len()]);
+
+        for i in 0..segment_count {
+            let (mut a, mut b) = segment(i);
+            let direction = (b - a).normalize();
+            let normal = cgmath::Vector2::new(-direction.y, direction.x) * half_width;
+
+            if !closed && parameters.cap == LineCap::Square {
+                if i == 0 {
+                    a -= direction * half_width;
+                }
+                if i == segment_count - 1 {
+                    b += direction * half_width;
+                }
+            }
+            mask.add_convex_polygon(&[a + normal, b + normal, b - normal, a - normal]);
+        }
+
+        if !closed && parameters.cap == LineCap::Round {
+            mask.add_disc(points[0], half_width);
+            mask.add_disc(points[points.len() - 1], half_width);
+        }
+
+        // Joins at the end of every segment followed by another one
+        let join_count = if closed && points.len() > 2 {
+            segment_count
+        } else {
+            segment_count - 1
+        };
+        for i in 0..join_count {
+            let (a, vertex) = segment(i);
+            let (_, c) = segment((i + 1) % segment_count);
+            let incoming = (vertex - a).normalize();
+            let outgoing = (c - vertex).normalize();
+            Self::add_join(mask, vertex, incoming, outgoing, half_width, parameters);
+        }
+    }
+
+    /// Fills the corner on the outer side of the turn from the `incoming` to the `outgoing` direction
+    fn add_join(
+        mask: &mut CoverageMask,
+        vertex: cgmath::Point2<f32>,
+        incoming: cgmath::Vector2<f32>,
+        outgoing: cgmath::Vector2<f32>,
+        half_width: f32,
+        parameters: &StrokeParameters,
+    ) {
+        let cross = incoming.x * outgoing.y - incoming.y * outgoing.x;
+        if cross.abs() < 1e-6 && incoming.dot(outgoing) > 0.0 {
+            return;
+        }
+
+        let side = if cross > 0.0 { -1.0 } else { 1.0 };
+        let normal_in = cgmath::Vector2::new(-incoming.y, incoming.x) * side;
+        let normal_out = cgmath::Vector2::new(-outgoing.y, outgoing.x) * side;
+        let corner_in = vertex + normal_in * half_width;
+        let corner_out = vertex + normal_out * half_width;
+
+        match parameters.join {
+            LineJoin::Round => mask.add_disc(vertex, half_width),
+            LineJoin::Bevel => mask.add_convex_polygon(&[vertex, corner_in, corner_out]),
+            LineJoin::Miter => {
+                let bisector = normal_in + normal_out;
+                let cos_half_angle = if bisector.magnitude2() > f32::EPSILON {
+                    bisector.normalize().dot(normal_in)
+                } else {
+                    0.0
+                };
+
+                if cos_half_angle > 1.0 / parameters.miter_limit.max(1.0) {
+                    let miter = vertex + bisector.normalize() * (half_width / cos_half_angle);
+                    mask.add_convex_polygon(&[vertex, corner_in, miter, corner_out]);
+                } else {
+                    mask.add_convex_polygon(&[vertex, corner_in, corner_out]);
+                }
+            }
+        }
+    }
+
+    /// Returns the width in pixels of the sprite strokes are built with and the opacity
+    /// they are drawn with, strokes thinner than a pixel are drawn one pixel wide
+    /// and more transparent instead
+    fn stroke_width(&self, parameters: &StrokeParameters) -> (f32, f32) {
+        let width = parameters.width * self.device_scale();
+        if width < 1.0 {
+            (1.0, width.max(0.0))
+        } else {
+            (width, 1.0)
+        }
+    }
+
+    /// Blends the paint over the pixels of the sprite covered by the `mask`,
+    /// the paint is evaluated in local coordinates
+    pub(super) fn fill_mask(&mut self, mask: &CoverageMask, paint: &Paint, opacity: f32) {
+        let inverse = self.inverse_transform();
+        for (x, y, coverage) in mask.pixels() {
+            let center = cgmath::Point2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let color = paint.color_at(inverse.transform_point(center));
+            self.blend_pixel(x, y, color, coverage * opacity);
+        }
+    }
+
+    /// Blends the `color` with its alpha multiplied by `coverage` over the pixel of the sprite,
+    /// pixels outside of the sprite or the clip rectangle are ignored
+    pub(super) fn blend_pixel(&mut self, x: i32, y: i32, color: Color, coverage: f32) {
+        if coverage <= 0.0 {
+            return;
+        }
+
+        let alpha = (color.a as f32 * coverage.min(1.0)).round() as u8;
+        self.put_pixel(x, y, Color { a: alpha, ..color });
+    }
+}
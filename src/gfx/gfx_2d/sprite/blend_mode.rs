@@ -0,0 +1,148 @@
+use crate::gfx::texture::Color;
+
+/// How the colors drawn on a sprite are combined with its pixels,
+/// the formulas follow the [compositing and blending spec](https://www.w3.org/TR/compositing-1/)
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The drawn color is blended over the pixel using its alpha
+    #[default]
+    SourceOver,
+    /// The drawn color replaces the pixel including its alpha
+    Replace,
+    /// Colors are added together, which brightens the pixel, useful for glows and lights
+    Additive,
+    /// Colors are multiplied, which darkens the pixel, useful for shadows
+    Multiply,
+    /// Inverted colors are multiplied, which brightens the pixel
+    Screen,
+    /// Multiplies dark and screens light parts of the pixel, which increases the contrast
+    Overlay,
+    /// Keeps the darker of the colors for each channel
+    Darken,
+    /// Keeps the lighter of the colors for each channel
+    Lighten,
+    /// Erases the pixel by the alpha of the drawn color, its color is ignored
+    DestinationOut,
+}
+
+impl BlendMode {
+    /// Combines the premultiplied colors of the pixel `dst` and the drawn `src`
+    pub(super) fn blend(&self, dst: &Color, src: &Color) -> Color {
+        match self {
+            BlendMode::SourceOver => Color::blend(dst, src),
+            BlendMode::Replace => *src,
+            BlendMode::Additive => Color::new(
+                dst.r.saturating_add(src.r),
+                dst.g.saturating_add(src.g),
+                dst.b.saturating_add(src.b),
+                dst.a.saturating_add(src.a),
+            ),
+            BlendMode::DestinationOut => {
+                let inv_a = 255 - src.a as u16;
+                let scale = |c: u8| (c as u16 * inv_a / 255) as u8;
+                Color::new(scale(dst.r), scale(dst.g), scale(dst.b), scale(dst.a))
+            }
+            BlendMode::Multiply => Self::separable(dst, src, |d, s| d * s),
+            BlendMode::Screen => Self::separable(dst, src, |d, s| d + s - d * s),
+            BlendMode::Overlay => Self::separable(dst, src, |d, s| {
+                if d <= 0.5 {
+                    2.0 * d * s
+                } else {
+                    1.0 - 2.0 * (1.0 - d) * (1.0 - s)
+                }
+            }),
+            BlendMode::Darken => Self::separable(dst, src, f32::min),
+            BlendMode::Lighten => Self::separable(dst, src, f32::max),
+        }
+    }
+
+    /// Composites premultiplied colors with a blend function of straight channels in 0..=1,
+    /// parts of the colors not overlapping each other are kept as in source-over
+    fn separable(dst: &Color, src: &Color, function: impl Fn(f32, f32) -> f32) -> Color {
+        let (dst_a, src_a) = (dst.a as f32 / 255.0, src.a as f32 / 255.0);
+        let channel = |d: u8, s: u8| {
+            let (d, s) = (d as f32 / 255.0, s as f32 / 255.0);
+            let straight = |c: f32, a: f32| if a > 0.0 { (c / a).min(1.0) } else { 0.0 };
+            let mixed = dst_a * src_a * function(straight(d, dst_a), straight(s, src_a));
+            let value = s * (1.0 - dst_a) + d * (1.0 - src_a) + mixed;
+            (value * 255.0).round().clamp(0.0, 255.0) as u8
+        };
+        let a = ((src_a + dst_a - src_a * dst_a) * 255.0).round() as u8;
+
+        Color::new(
+            channel(dst.r, src.r),
+            channel(dst.g, src.g),
+            channel(dst.b, src.b),
+            a,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEPARABLE: [BlendMode; 5] = [
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Overlay,
+        BlendMode::Darken,
+        BlendMode::Lighten,
+    ];
+
+    fn channels(color: Color) -> (u8, u8, u8, u8) {
+        (color.r, color.g, color.b, color.a)
+    }
+
+    #[test]
+    fn separable_modes_mix_opaque_colors() {
+        let dst = Color::new(200, 100, 50, 255);
+        let src = Color::new(100, 200, 255, 255);
+        let expected = [
+            (78, 78, 50, 255),
+            (222, 222, 255, 255),
+            // Overlay screens the light red and multiplies the dark green and blue
+            (188, 157, 100, 255),
+            (100, 100, 50, 255),
+            (200, 200, 255, 255),
+        ];
+        for (mode, expected) in SEPARABLE.iter().zip(expected) {
+            assert_eq!(channels(mode.blend(&dst, &src)), expected, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn separable_modes_over_transparent_pixel_are_source_over() {
+        let src = Color::new(64, 32, 0, 128);
+        let expected = channels(BlendMode::SourceOver.blend(&Color::TRANSPARENT, &src));
+        assert_eq!(expected, (64, 32, 0, 128));
+        for mode in SEPARABLE {
+            assert_eq!(
+                channels(mode.blend(&Color::TRANSPARENT, &src)),
+                expected,
+                "{:?}",
+                mode
+            );
+        }
+    }
+
+    #[test]
+    fn additive_saturates() {
+        let dst = Color::new(200, 100, 50, 128);
+        let src = Color::new(100, 100, 250, 200);
+        assert_eq!(
+            channels(BlendMode::Additive.blend(&dst, &src)),
+            (255, 200, 255, 255)
+        );
+    }
+
+    #[test]
+    fn destination_out_erases_by_source_alpha() {
+        let dst = Color::new(200, 100, 50, 255);
+        let erase =
+            |alpha| channels(BlendMode::DestinationOut.blend(&dst, &Color::new(9, 9, 9, alpha)));
+        assert_eq!(erase(51), (160, 80, 40, 204));
+        assert_eq!(erase(255), (0, 0, 0, 0));
+        assert_eq!(erase(0), (200, 100, 50, 255));
+    }
+}
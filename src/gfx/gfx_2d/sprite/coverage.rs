@@ -0,0 +1,237 @@
+use super::path::FillRule;
+use cgmath::{InnerSpace, MetricSpace};
+
+/// Fraction of each pixel inside of a bounding box covered by shapes
+///
+/// Shapes added to the mask are merged, so their overlapping parts are blended only once.
+/// Pixel `(x, y)` spans from `x` to `x + 1`, its center is at `x + 0.5`
+pub(super) struct CoverageMask {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    values: Vec<f32>,
+}
+
+impl CoverageMask {
+    /// Creates an empty mask around `min` and `max` clipped to the surface of given size
+    pub(super) fn new(
+        min: cgmath::Point2<f32>,
+        max: cgmath::Point2<f32>,
+        surface_width: u32,
+        surface_height: u32,
+    ) -> Self {
+        let x0 = (min.x.floor() as i32).saturating_sub(1).max(0);
+        let y0 = (min.y.floor() as i32).saturating_sub(1).max(0);
+        let x1 = (max.x.ceil() as i32)
+            .saturating_add(1)
+            .min(surface_width as i32);
+        let y1 = (max.y.ceil() as i32)
+            .saturating_add(1)
+            .min(surface_height as i32);
+        let width = x1.saturating_sub(x0).max(0) as u32;
+        let height = y1.saturating_sub(y0).max(0) as u32;
+
+        Self {
+            x: x0,
+            y: y0,
+            width,
+            height,
+            values: vec![0.0; (width * height) as usize],
+        }
+    }
+
+    /// Creates an empty mask around all `points` expanded by `margin`
+    pub(super) fn around(
+        points: &[cgmath::Point2<f32>],
+        margin: f32,
+        surface_width: u32,
+        surface_height: u32,
+    ) -> Self {
+        let (min, max) = bounds(points);
+        let margin = cgmath::Vector2::new(margin, margin);
+        Self::new(min - margin, max + margin, surface_width, surface_height)
+    }
+
+    /// Evaluates `coverage` at centers of the pixels between `min` and `max`
+    /// and keeps the greater coverage of each pixel
+    fn add(
+        &mut self,
+        min: cgmath::Point2<f32>,
+        max: cgmath::Point2<f32>,
+        coverage: impl Fn(cgmath::Point2<f32>) -> f32,
+    ) {
+        let x0 = (min.x.floor() as i32).saturating_sub(1).max(self.x);
+        let y0 = (min.y.floor() as i32).saturating_sub(1).max(self.y);
+        let x1 = (max.x.ceil() as i32)
+            .saturating_add(1)
+            .min(self.x + self.width as i32);
+        let y1 = (max.y.ceil() as i32)
+            .saturating_add(1)
+            .min(self.y + self.height as i32);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let center = cgmath::Point2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let index = ((y - self.y) as u32 * self.width + (x - self.x) as u32) as usize;
+                let value = &mut self.values[index];
+                *value = value.max(coverage(center).clamp(0.0, 1.0));
+            }
+        }
+    }
+
+    /// Adds a convex polygon given its vertices in any winding order
+    pub(super) fn add_convex_polygon(&mut self, points: &[cgmath::Point2<f32>]) {
+        let area: f32 = (0..points.len())
+            .map(|i| {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum();
+        if points.len() < 3 || area.abs() < f32::EPSILON {
+            return;
+        }
+        let winding = area.signum();
+
+        // Inward facing unit normals and offsets of the edges
+        let edges: Vec<(cgmath::Vector2<f32>, f32)> = (0..points.len())
+            .filter_map(|i| {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                let edge = b - a;
+                if edge.magnitude2() < f32::EPSILON {
+                    return None;
+                }
+                let normal = cgmath::Vector2::new(-edge.y, edge.x).normalize() * winding;
+                Some((normal, normal.dot(a - cgmath::Point2::new(0.0, 0.0))))
+            })
+            .collect();
+
+        let (min, max) = bounds(points);
+        self.add(min, max, |p| {
+            let v = p - cgmath::Point2::new(0.0, 0.0);
+            let distance = edges
+                .iter()
+                .map(|(normal, offset)| normal.dot(v) - offset)
+                .fold(f32::INFINITY, f32::min);
+            distance + 0.5
+        });
+    }
+
+    pub(super) fn add_disc(&mut self, center: cgmath::Point2<f32>, radius: f32) {
+        let extent = cgmath::Vector2::new(radius, radius);
+        self.add(center - extent, center + extent, |p| {
+            radius - p.distance(center) + 0.5
+        });
+    }
+
+    /// Adds a ring of given `width` centered on the circle of given `radius`
+    pub(super) fn add_ring(&mut self, center: cgmath::Point2<f32>, radius: f32, width: f32) {
+        let extent = cgmath::Vector2::new(radius + width, radius + width);
+        self.add(center - extent, center + extent, |p| {
+            width / 2.0 - (p.distance(center) - radius).abs() + 0.5
+        });
+    }
+
+    /// Adds the area enclosed by closed contours, which may be concave and intersect each other
+    ///
+    /// Each row of pixels is sampled along several horizontal lines and the exact horizontal
+    /// coverage of the spans inside is accumulated
+    pub(super) fn add_contours(&mut self, contours: &[Vec<cgmath::Point2<f32>>], rule: FillRule) {
+        const SAMPLES: u32 = 5;
+
+        let edges: Vec<(cgmath::Point2<f32>, cgmath::Point2<f32>)> = contours
+            .iter()
+            .flat_map(|points| {
+                (0..points.len()).map(move |i| (points[i], points[(i + 1) % points.len()]))
+            })
+            .filter(|(a, b)| a.y != b.y)
+            .collect();
+        let all_points: Vec<cgmath::Point2<f32>> = contours.iter().flatten().copied().collect();
+        if edges.is_empty() {
+            return;
+        }
+
+        let (min, max) = bounds(&all_points);
+        let y0 = (min.y.floor() as i32).max(self.y);
+        let y1 = (max.y.ceil() as i32).min(self.y + self.height as i32);
+        let mut crossings: Vec<(f32, i32)> = vec![];
+        let mut row = vec![0.0; self.width as usize];
+
+        for y in y0..y1 {
+            row.iter_mut().for_each(|value| *value = 0.0);
+
+            for sample in 0..SAMPLES {
+                let sample_y = y as f32 + (sample as f32 + 0.5) / SAMPLES as f32;
+                crossings.clear();
+                for (a, b) in edges.iter() {
+                    if (a.y <= sample_y) != (b.y <= sample_y) {
+                        let x = a.x + (sample_y - a.y) / (b.y - a.y) * (b.x - a.x);
+                        crossings.push((x, if b.y > a.y { 1 } else { -1 }));
+                    }
+                }
+                crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+                let mut winding = 0;
+                for pair in crossings.windows(2) {
+                    winding += pair[0].1;
+                    let inside = match rule {
+                        FillRule::EvenOdd => winding % 2 != 0,
+                        FillRule::NonZero => winding != 0,
+                    };
+                    if inside {
+                        self.add_span(&mut row, pair[0].0, pair[1].0, 1.0 / SAMPLES as f32);
+                    }
+                }
+            }
+
+            let start = ((y - self.y) as u32 * self.width) as usize;
+            for (value, coverage) in self.values[start..start + row.len()]
+                .iter_mut()
+                .zip(row.iter())
+            {
+                *value = value.max(coverage.min(1.0));
+            }
+        }
+    }
+
+    /// Adds the parts of the pixels of the `row` between `start` and `end` multiplied by `weight`
+    fn add_span(&self, row: &mut [f32], start: f32, end: f32, weight: f32) {
+        let x0 = (start.floor() as i32).max(self.x);
+        let x1 = (end.ceil() as i32).min(self.x + self.width as i32);
+        for x in x0..x1 {
+            let overlap = end.min(x as f32 + 1.0) - start.max(x as f32);
+            if overlap > 0.0 {
+                row[(x - self.x) as usize] += overlap * weight;
+            }
+        }
+    }
+
+    /// Returns covered pixels with their coverage
+    pub(super) fn pixels(&self) -> impl Iterator<Item = (i32, i32, f32)> + '_ {
+        self.values
+            .iter()
+            .enumerate()
+            .filter(|(_, &coverage)| coverage > 0.0)
+            .map(|(index, &coverage)| {
+                let x = self.x + (index as u32 % self.width) as i32;
+                let y = self.y + (index as u32 / self.width) as i32;
+                (x, y, coverage)
+            })
+    }
+}
+
+/// Returns the smallest and the greatest coordinates of the points
+fn bounds(points: &[cgmath::Point2<f32>]) -> (cgmath::Point2<f32>, cgmath::Point2<f32>) {
+    points.iter().fold(
+        (
+            cgmath::Point2::new(f32::INFINITY, f32::INFINITY),
+            cgmath::Point2::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+        ),
+        |(min, max), p| {
+            (
+                cgmath::Point2::new(min.x.min(p.x), min.y.min(p.y)),
+                cgmath::Point2::new(max.x.max(p.x), max.y.max(p.y)),
+            )
+        },
+    )
+}
@@ -0,0 +1,338 @@
+use super::paint::Paint;
+use super::{Rect, Sprite};
+use crate::gfx::texture::Color;
+use cgmath::Transform;
+
+/// Which neighbours of a pixel belong to the same region
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Pixels sharing an edge
+    Four,
+    /// Pixels sharing an edge or a corner
+    Eight,
+}
+
+/// Describes which pixels are selected by a flood fill
+#[derive(Debug, Copy, Clone)]
+pub struct FloodFillParameters {
+    /// Largest difference of any channel from the color of the seed pixel
+    pub tolerance: u8,
+    pub connectivity: Connectivity,
+}
+
+impl Default for FloodFillParameters {
+    fn default() -> Self {
+        Self {
+            tolerance: 0,
+            connectivity: Connectivity::Four,
+        }
+    }
+}
+
+/// Pixels of a sprite selected by a flood fill
+#[derive(Debug, Clone)]
+pub struct RegionMask {
+    width: u32,
+    height: u32,
+    values: Vec<bool>,
+    count: usize,
+    /// Bounds of the selected pixels: min x, min y, max x, max y
+    bounds: Option<(u32, u32, u32, u32)>,
+}
+
+impl RegionMask {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            values: vec![false; (width * height) as usize],
+            count: 0,
+            bounds: None,
+        }
+    }
+
+    fn select_span(&mut self, x0: u32, x1: u32, y: u32) {
+        let start = (y * self.width) as usize;
+        self.values[start + x0 as usize..=start + x1 as usize].fill(true);
+        self.count += (x1 - x0 + 1) as usize;
+        self.bounds = Some(match self.bounds {
+            Some((min_x, min_y, max_x, max_y)) => {
+                (min_x.min(x0), min_y.min(y), max_x.max(x1), max_y.max(y))
+            }
+            None => (x0, y, x1, y),
+        });
+    }
+
+    pub fn contains(&self, point: cgmath::Point2<i32>) -> bool {
+        point.x >= 0
+            && point.y >= 0
+            && (point.x as u32) < self.width
+            && (point.y as u32) < self.height
+            && self.values[(point.y as u32 * self.width + point.x as u32) as usize]
+    }
+
+    /// Returns the number of selected pixels
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the smallest rectangle containing all selected pixels
+    pub fn bounds(&self) -> Option<Rect> {
+        self.bounds.map(|(min_x, min_y, max_x, max_y)| {
+            Rect::new(
+                min_x as i32,
+                min_y as i32,
+                max_x - min_x + 1,
+                max_y - min_y + 1,
+            )
+        })
+    }
+
+    /// Returns positions of the selected pixels row by row
+    pub fn pixels(&self) -> impl Iterator<Item = cgmath::Point2<i32>> + '_ {
+        self.values
+            .iter()
+            .enumerate()
+            .filter(|(_, &selected)| selected)
+            .map(|(index, _)| {
+                cgmath::Point2::new(
+                    (index as u32 % self.width) as i32,
+                    (index as u32 / self.width) as i32,
+                )
+            })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+/// Methods related to flood filling
+impl Sprite {
+    /// Selects the pixels connected to the `seed` with colors similar to its color
+    /// using a [scanline flood fill](https://en.wikipedia.org/wiki/Flood_fill#Span_filling)
+    ///
+    /// The `seed` is transformed by the current transform, the mask is in pixels of the sprite
+    ///
+    /// Every pixel is visited a constant number of times and spans waiting to be filled
+    /// are kept on a stack, so large sprites don't overflow the call stack
+    pub fn select_region(
+        &self,
+        seed: cgmath::Point2<i32>,
+        parameters: &FloodFillParameters,
+    ) -> RegionMask {
+        let mut mask = RegionMask::new(self.width, self.height);
+        let seed = self.device_pixel(seed);
+        if seed.x < 0 || seed.y < 0 || seed.x as u32 >= self.width || seed.y as u32 >= self.height {
+            return mask;
+        }
+
+        let seed_color = self.pixel(seed.x as u32, seed.y as u32);
+        let tolerance = parameters.tolerance;
+        let matches = |mask: &RegionMask, x: u32, y: u32| {
+            let index = (y * self.width + x) as usize;
+            !mask.values[index] && Self::is_similar(&self.values[index], &seed_color, tolerance)
+        };
+
+        let mut stack = vec![(seed.x as u32, seed.y as u32)];
+        while let Some((x, y)) = stack.pop() {
+            if !matches(&mask, x, y) {
+                continue;
+            }
+
+            let mut x0 = x;
+            while x0 > 0 && matches(&mask, x0 - 1, y) {
+                x0 -= 1;
+            }
+            let mut x1 = x;
+            while x1 + 1 < self.width && matches(&mask, x1 + 1, y) {
+                x1 += 1;
+            }
+            mask.select_span(x0, x1, y);
+
+            // Neighbours of the span in the rows above and below, diagonal ones included for 8-connectivity
+            let (scan_x0, scan_x1) = match parameters.connectivity {
+                Connectivity::Four => (x0, x1),
+                Connectivity::Eight => (x0.saturating_sub(1), (x1 + 1).min(self.width - 1)),
+            };
+            let rows = [y.checked_sub(1), Some(y + 1).filter(|&y| y < self.height)];
+            for row in rows.into_iter().flatten() {
+                // Push one seed for every run of matching pixels
+                let mut in_run = false;
+                for scan_x in scan_x0..=scan_x1 {
+                    let is_match = matches(&mask, scan_x, row);
+                    if is_match && !in_run {
+                        stack.push((scan_x, row));
+                    }
+                    in_run = is_match;
+                }
+            }
+        }
+
+        mask
+    }
+
+    /// Fills the region connected to the `seed` like a paint bucket and returns it
+    pub fn flood_fill<'a>(
+        &mut self,
+        seed: cgmath::Point2<i32>,
+        paint: impl Into<Paint<'a>>,
+        parameters: &FloodFillParameters,
+    ) -> RegionMask {
+        let mask = self.select_region(seed, parameters);
+        self.fill_region(&mask, paint);
+        mask
+    }
+
+    /// Blends the paint over all pixels of the `mask`, the paint is evaluated in local coordinates
+    pub fn fill_region<'a>(&mut self, mask: &RegionMask, paint: impl Into<Paint<'a>>) {
+        let paint = paint.into();
+        let inverse = self.inverse_transform();
+        for point in mask.pixels() {
+            let center = cgmath::Point2::new(point.x as f32 + 0.5, point.y as f32 + 0.5);
+            let color = paint.color_at(inverse.transform_point(center));
+            self.blend_pixel(point.x, point.y, color, 1.0);
+        }
+    }
+
+    fn is_similar(a: &Color, b: &Color, tolerance: u8) -> bool {
+        a.r.abs_diff(b.r) <= tolerance
+            && a.g.abs_diff(b.g) <= tolerance
+            && a.b.abs_diff(b.b) <= tolerance
+            && a.a.abs_diff(b.a) <= tolerance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// White sprite with black pixels at the `walls`
+    fn sprite(width: u32, height: u32, walls: &[(u32, u32)]) -> Sprite {
+        let mut sprite = Sprite::new(width, height, Color::WHITE);
+        for (x, y) in walls {
+            sprite.values[(y * width + x) as usize] = Color::BLACK;
+        }
+        sprite
+    }
+
+    fn is_color(color: Color, expected: Color) -> bool {
+        (color.r, color.g, color.b, color.a) == (expected.r, expected.g, expected.b, expected.a)
+    }
+
+    #[test]
+    fn region_stops_at_different_colors() {
+        let walls: Vec<(u32, u32)> = (0..4).map(|y| (3, y)).collect();
+        let sprite = sprite(8, 4, &walls);
+        let mask = sprite.select_region(cgmath::Point2::new(0, 0), &FloodFillParameters::default());
+        assert_eq!(mask.len(), 12);
+        assert_eq!(mask.bounds(), Some(Rect::new(0, 0, 3, 4)));
+        assert!(mask.contains(cgmath::Point2::new(2, 3)));
+        assert!(!mask.contains(cgmath::Point2::new(3, 0)));
+        assert!(!mask.contains(cgmath::Point2::new(4, 0)));
+    }
+
+    #[test]
+    fn region_fills_around_obstacles() {
+        // A wall with the opening at the bottom, the region has to turn back up behind it
+        let walls: Vec<(u32, u32)> = (0..4).map(|y| (2, y)).collect();
+        let sprite = sprite(5, 5, &walls);
+        let mask = sprite.select_region(cgmath::Point2::new(0, 0), &FloodFillParameters::default());
+        assert_eq!(mask.len(), 25 - 4);
+        assert!(mask.contains(cgmath::Point2::new(4, 0)));
+    }
+
+    #[test]
+    fn eight_connectivity_crosses_diagonals() {
+        let sprite = sprite(3, 3, &[(0, 0), (1, 1), (2, 2)]);
+        let seed = cgmath::Point2::new(0, 0);
+        let four = sprite.select_region(seed, &FloodFillParameters::default());
+        assert_eq!(four.len(), 1);
+
+        let parameters = FloodFillParameters {
+            connectivity: Connectivity::Eight,
+            ..Default::default()
+        };
+        let eight = sprite.select_region(seed, &parameters);
+        assert_eq!(eight.len(), 3);
+        assert_eq!(eight.bounds(), Some(Rect::new(0, 0, 3, 3)));
+    }
+
+    #[test]
+    fn tolerance_selects_similar_colors() {
+        let mut sprite = Sprite::new(3, 1, Color::WHITE);
+        sprite.values = vec![
+            Color::new(100, 100, 100, 255),
+            Color::new(105, 100, 100, 255),
+            Color::new(120, 100, 100, 255),
+        ];
+        let seed = cgmath::Point2::new(0, 0);
+        assert_eq!(
+            sprite
+                .select_region(seed, &FloodFillParameters::default())
+                .len(),
+            1
+        );
+
+        let parameters = FloodFillParameters {
+            tolerance: 10,
+            ..Default::default()
+        };
+        assert_eq!(sprite.select_region(seed, &parameters).len(), 2);
+    }
+
+    #[test]
+    fn seed_outside_of_sprite_selects_nothing() {
+        let sprite = sprite(4, 4, &[]);
+        let parameters = FloodFillParameters::default();
+        let mask = sprite.select_region(cgmath::Point2::new(-1, 0), &parameters);
+        assert!(mask.is_empty());
+        assert_eq!(mask.bounds(), None);
+        assert!(sprite
+            .select_region(cgmath::Point2::new(0, 4), &parameters)
+            .is_empty());
+    }
+
+    #[test]
+    fn seed_is_transformed() {
+        let mut sprite = sprite(4, 1, &[(1, 0)]);
+        sprite.translate(cgmath::Vector2::new(2.0, 0.0));
+        let mask = sprite.select_region(cgmath::Point2::new(0, 0), &FloodFillParameters::default());
+        assert_eq!(mask.bounds(), Some(Rect::new(2, 0, 2, 1)));
+    }
+
+    #[test]
+    fn flood_fill_paints_only_the_region() {
+        let walls: Vec<(u32, u32)> = (0..3).map(|y| (1, y)).collect();
+        let mut sprite = sprite(3, 3, &walls);
+        let mask = sprite.flood_fill(
+            cgmath::Point2::new(2, 1),
+            Color::RED,
+            &FloodFillParameters::default(),
+        );
+        assert_eq!(mask.len(), 3);
+        for y in 0..3 {
+            assert!(is_color(sprite.pixel(0, y), Color::WHITE));
+            assert!(is_color(sprite.pixel(1, y), Color::BLACK));
+            assert!(is_color(sprite.pixel(2, y), Color::RED));
+        }
+    }
+
+    #[test]
+    fn large_region_does_not_overflow_the_stack() {
+        let sprite = sprite(1024, 1024, &[]);
+        let mask = sprite.select_region(
+            cgmath::Point2::new(512, 512),
+            &FloodFillParameters::default(),
+        );
+        assert_eq!(mask.len(), 1024 * 1024);
+    }
+}
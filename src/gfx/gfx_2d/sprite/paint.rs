@@ -0,0 +1,153 @@
+use super::Sprite;
+use crate::gfx::texture::Color;
+use cgmath::InnerSpace;
+
+/// Color at a position along a gradient
+#[derive(Debug, Copy, Clone)]
+pub struct ColorStop {
+    /// Position from 0.0 at the start to 1.0 at the end of the gradient
+    pub offset: f32,
+    pub color: Color,
+}
+
+impl ColorStop {
+    pub fn new(offset: f32, color: Color) -> Self {
+        Self { offset, color }
+    }
+}
+
+/// Colors smoothly changing between color stops,
+/// positions before the first and after the last stop get their colors
+#[derive(Debug, Clone)]
+pub struct Gradient {
+    stops: Vec<ColorStop>,
+}
+
+impl Gradient {
+    /// Creates a gradient from the stops in any order
+    pub fn new(stops: &[ColorStop]) -> Self {
+        let mut stops = stops.to_vec();
+        stops.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+        Self { stops }
+    }
+
+    pub fn stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    /// Returns the color at `offset`, colors are interpolated with premultiplied alpha,
+    /// so transparent stops don't darken their neighbours
+    fn color_at(&self, offset: f32) -> Color {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Color::TRANSPARENT,
+        };
+        if offset <= first.offset {
+            return first.color;
+        }
+        if offset >= last.offset {
+            return last.color;
+        }
+
+        let next = self
+            .stops
+            .iter()
+            .position(|s| s.offset > offset)
+            .unwrap_or(self.stops.len() - 1);
+        let (a, b) = (self.stops[next - 1], self.stops[next]);
+        let t = (offset - a.offset) / (b.offset - a.offset);
+
+        let premultiplied = |c: Color| {
+            let alpha = c.a as f32 / 255.0;
+            [
+                c.r as f32 * alpha,
+                c.g as f32 * alpha,
+                c.b as f32 * alpha,
+                c.a as f32,
+            ]
+        };
+        let (a, b) = (premultiplied(a.color), premultiplied(b.color));
+        let [r, g, b, alpha] = [0, 1, 2, 3].map(|i| a[i] + (b[i] - a[i]) * t);
+        if alpha <= 0.0 {
+            return Color::TRANSPARENT;
+        }
+
+        let straight = |v: f32| (v * 255.0 / alpha).round().clamp(0.0, 255.0) as u8;
+        Color::new(straight(r), straight(g), straight(b), alpha.round() as u8)
+    }
+}
+
+/// Describes the color of each pixel of a filled shape
+#[derive(Clone)]
+pub enum Paint<'a> {
+    Solid(Color),
+    /// Gradient along the line from `start` to `end`
+    LinearGradient {
+        start: cgmath::Point2<f32>,
+        end: cgmath::Point2<f32>,
+        gradient: Gradient,
+    },
+    /// Gradient from the `center` to the circle of given `radius`
+    RadialGradient {
+        center: cgmath::Point2<f32>,
+        radius: f32,
+        gradient: Gradient,
+    },
+    /// Sprite repeated in both directions, its top left corner is at `offset`
+    Pattern {
+        sprite: &'a Sprite,
+        offset: cgmath::Vector2<f32>,
+    },
+}
+
+impl From<Color> for Paint<'_> {
+    fn from(color: Color) -> Self {
+        Self::Solid(color)
+    }
+}
+
+impl Paint<'_> {
+    /// Returns the color at the `point` in local coordinates of the sprite being drawn on
+    pub(super) fn color_at(&self, point: cgmath::Point2<f32>) -> Color {
+        match self {
+            Paint::Solid(color) => *color,
+            Paint::LinearGradient {
+                start,
+                end,
+                gradient,
+            } => {
+                let direction = end - start;
+                let length2 = direction.magnitude2();
+                let offset = if length2 > 0.0 {
+                    (point - start).dot(direction) / length2
+                } else {
+                    0.0
+                };
+                gradient.color_at(offset)
+            }
+            Paint::RadialGradient {
+                center,
+                radius,
+                gradient,
+            } => {
+                let offset = if *radius > 0.0 {
+                    (point - center).magnitude() / radius
+                } else {
+                    1.0
+                };
+                gradient.color_at(offset)
+            }
+            Paint::Pattern { sprite, offset } => {
+                if sprite.width == 0 || sprite.height == 0 {
+                    return Color::TRANSPARENT;
+                }
+                let x = (point.x - offset.x).floor() as i64;
+                let y = (point.y - offset.y).floor() as i64;
+                sprite.pixel(
+                    x.rem_euclid(sprite.width as i64) as u32,
+                    y.rem_euclid(sprite.height as i64) as u32,
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use cgmath::{EuclideanSpace, InnerSpace};
+
+/// Largest distance in pixels between a curve and the segments it is approximated with
+const TOLERANCE: f32 = 0.2;
+
+/// Decides which parts of self-intersecting or nested shapes are inside
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FillRule {
+    /// A point is inside if a ray from it crosses the outline an odd number of times
+    EvenOdd,
+    /// A point is inside if the outline winds around it at least once
+    NonZero,
+}
+
+/// Radii of the corners of a rounded rectangle in pixels
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CornerRadii {
+    pub top_left: f32,
+    pub top_right: f32,
+    pub bottom_right: f32,
+    pub bottom_left: f32,
+}
+
+impl CornerRadii {
+    /// Same radius for all corners
+    pub fn uniform(radius: f32) -> Self {
+        Self {
+            top_left: radius,
+            top_right: radius,
+            bottom_right: radius,
+            bottom_left: radius,
+        }
+    }
+}
+
+/// Outline made of lines, arcs and Bézier curves that can be filled or stroked
+///
+/// Curves are approximated by line segments as they are added. Angles are measured
+/// clockwise from the positive x axis, because the y axis points down
+#[derive(Debug, Clone, Default)]
+pub struct Path {
+    /// Points of each contour with its closedness
+    pub(super) contours: Vec<(Vec<cgmath::Point2<f32>>, bool)>,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self { contours: vec![] }
+    }
+
+    /// Starts a new contour at the `point`
+    pub fn move_to(&mut self, point: cgmath::Point2<f32>) -> &mut Self {
+        self.contours.push((vec![point], false));
+        self
+    }
+
+    /// Adds a line from the last point, starts a new contour if there is none
+    pub fn line_to(&mut self, point: cgmath::Point2<f32>) -> &mut Self {
+        match self.contours.last_mut() {
+            Some((points, false)) => points.push(point),
+            _ => {
+                self.move_to(point);
+            }
+        }
+        self
+    }
+
+    /// Adds a quadratic Bézier curve from the last point
+    pub fn quadratic_to(
+        &mut self,
+        control: cgmath::Point2<f32>,
+        end: cgmath::Point2<f32>,
+    ) -> &mut Self {
+        let start = self.last_point(control);
+        let deviation = (start - control * 2.0 + end.to_vec()).magnitude();
+        let segments = ((0.25 * deviation / TOLERANCE).sqrt().ceil() as usize).clamp(1, 256);
+
+        for i in 1..=segments {
+            let t = i as f32 / segments as f32;
+            let u = 1.0 - t;
+            let point = start * (u * u) + control.to_vec() * (2.0 * u * t) + end.to_vec() * (t * t);
+            self.line_to(point);
+        }
+        self
+    }
+
+    /// Adds a cubic Bézier curve from the last point
+    pub fn cubic_to(
+        &mut self,
+        control1: cgmath::Point2<f32>,
+        control2: cgmath::Point2<f32>,
+        end: cgmath::Point2<f32>,
+    ) -> &mut Self {
+        let start = self.last_point(control1);
+        let deviation = (start - control1 * 2.0 + control2.to_vec())
+            .magnitude()
+            .max((control1 - control2 * 2.0 + end.to_vec()).magnitude());
+        let segments = ((0.75 * deviation / TOLERANCE).sqrt().ceil() as usize).clamp(1, 256);
+
+        for i in 1..=segments {
+            let t = i as f32 / segments as f32;
+            let u = 1.0 - t;
+            let point = start * (u * u * u)
+                + control1.to_vec() * (3.0 * u * u * t)
+                + control2.to_vec() * (3.0 * u * t * t)
+                + end.to_vec() * (t * t * t);
+            self.line_to(point);
+        }
+        self
+    }
+
+    /// Adds a part of the ellipse starting at `start_angle` and going clockwise by `sweep`,
+    /// the arc is connected to the last point by a line
+    pub fn arc(
+        &mut self,
+        center: cgmath::Point2<f32>,
+        radii: cgmath::Vector2<f32>,
+        start_angle: cgmath::Rad<f32>,
+        sweep: cgmath::Rad<f32>,
+    ) -> &mut Self {
+        let radius = radii.x.abs().max(radii.y.abs());
+        let step = if radius > TOLERANCE {
+            2.0 * (1.0 - TOLERANCE / radius).acos()
+        } else {
+            std::f32::consts::FRAC_PI_2
+        };
+        let segments = ((sweep.0.abs() / step).ceil() as usize).clamp(1, 1024);
+
+        for i in 0..=segments {
+            let angle = start_angle.0 + sweep.0 * i as f32 / segments as f32;
+            let (sin, cos) = angle.sin_cos();
+            self.line_to(center + cgmath::Vector2::new(radii.x * cos, radii.y * sin));
+        }
+        self
+    }
+
+    /// Connects the last point to the first one of the current contour
+    pub fn close(&mut self) -> &mut Self {
+        if let Some((_, closed)) = self.contours.last_mut() {
+            *closed = true;
+        }
+        self
+    }
+
+    pub fn ellipse(center: cgmath::Point2<f32>, radii: cgmath::Vector2<f32>) -> Self {
+        let mut path = Self::new();
+        path.arc(
+            center,
+            radii,
+            cgmath::Rad(0.0),
+            cgmath::Rad(std::f32::consts::TAU),
+        )
+        .close();
+        path
+    }
+
+    /// Slice of the ellipse bounded by the arc and two lines to the center
+    pub fn pie(
+        center: cgmath::Point2<f32>,
+        radii: cgmath::Vector2<f32>,
+        start_angle: cgmath::Rad<f32>,
+        sweep: cgmath::Rad<f32>,
+    ) -> Self {
+        let mut path = Self::new();
+        path.move_to(center)
+            .arc(center, radii, start_angle, sweep)
+            .close();
+        path
+    }
+
+    /// Rectangle given its top left corner and size with rounded corners,
+    /// radii too large to fit are scaled down proportionally
+    pub fn rounded_rectangle(
+        position: cgmath::Point2<f32>,
+        size: cgmath::Vector2<f32>,
+        radii: CornerRadii,
+    ) -> Self {
+        let (width, height) = (size.x.abs(), size.y.abs());
+        let position = cgmath::Point2::new(
+            position.x.min(position.x + size.x),
+            position.y.min(position.y + size.y),
+        );
+        let radii = [
+            radii.top_left.max(0.0),
+            radii.top_right.max(0.0),
+            radii.bottom_right.max(0.0),
+            radii.bottom_left.max(0.0),
+        ];
+        let scale = [
+            width / (radii[0] + radii[1]),
+            height / (radii[1] + radii[2]),
+            width / (radii[2] + radii[3]),
+            height / (radii[3] + radii[0]),
+        ]
+        .iter()
+        .fold(
+            1.0_f32,
+            |scale, s| if s.is_finite() { scale.min(*s) } else { scale },
+        );
+        let [top_left, top_right, bottom_right, bottom_left] = radii.map(|r| r * scale);
+
+        let (left, top) = (position.x, position.y);
+        let (right, bottom) = (left + width, top + height);
+        let quarter = std::f32::consts::FRAC_PI_2;
+        let radius = |r: f32| cgmath::Vector2::new(r, r);
+
+        let mut path = Self::new();
+        path.move_to(cgmath::Point2::new(left + top_left, top))
+            .arc(
+                cgmath::Point2::new(right - top_right, top + top_right),
+                radius(top_right),
+                cgmath::Rad(-quarter),
+                cgmath::Rad(quarter),
+            )
+            .arc(
+                cgmath::Point2::new(right - bottom_right, bottom - bottom_right),
+                radius(bottom_right),
+                cgmath::Rad(0.0),
+                cgmath::Rad(quarter),
+            )
+            .arc(
+                cgmath::Point2::new(left + bottom_left, bottom - bottom_left),
+                radius(bottom_left),
+                cgmath::Rad(quarter),
+                cgmath::Rad(quarter),
+            )
+            .arc(
+                cgmath::Point2::new(left + top_left, top + top_left),
+                radius(top_left),
+                cgmath::Rad(2.0 * quarter),
+                cgmath::Rad(quarter),
+            )
+            .close();
+        path
+    }
+
+    /// Closed polygon through the `points`
+    pub fn polygon(points: &[cgmath::Point2<f32>]) -> Self {
+        Self {
+            contours: vec![(points.to_vec(), true)],
+        }
+    }
+
+    /// Returns the last point of the current contour or `fallback` if there is none
+    fn last_point(&mut self, fallback: cgmath::Point2<f32>) -> cgmath::Point2<f32> {
+        match self.contours.last() {
+            Some((points, false)) => *points.last().unwrap_or(&fallback),
+            _ => {
+                self.move_to(fallback);
+                fallback
+            }
+        }
+    }
+}
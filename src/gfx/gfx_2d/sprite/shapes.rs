@@ -0,0 +1,193 @@
+use super::antialiasing::StrokeParameters;
+use super::coverage::CoverageMask;
+use super::paint::Paint;
+use super::path::{CornerRadii, FillRule, Path};
+use super::Sprite;
+
+/// Methods related to drawing paths and the shapes built from them
+///
+/// All shapes are anti-aliased, filled ones blend every pixel only once
+/// even where their outlines overlap
+impl Sprite {
+    /// Fills all contours of the path with the paint, open contours are closed implicitly
+    pub fn fill_path<'a>(&mut self, path: &Path, rule: FillRule, paint: impl Into<Paint<'a>>) {
+        let contours: Vec<Vec<cgmath::Point2<f32>>> = path
+            .contours
+            .iter()
+            .map(|(points, _)| points.iter().map(|p| self.to_device(*p)).collect())
+            .collect();
+        let all_points: Vec<cgmath::Point2<f32>> = contours.iter().flatten().copied().collect();
+
+        let mut mask = CoverageMask::around(&all_points, 1.0, self.width, self.height);
+        mask.add_contours(&contours, rule);
+        self.fill_mask(&mask, &paint.into(), 1.0);
+    }
+
+    pub fn stroke_path(&mut self, path: &Path, parameters: &StrokeParameters) {
+        self.stroke_polylines(&path.contours, parameters);
+    }
+
+    pub fn fill_ellipse<'a>(
+        &mut self,
+        center: cgmath::Point2<f32>,
+        radii: cgmath::Vector2<f32>,
+        paint: impl Into<Paint<'a>>,
+    ) {
+        self.fill_path(&Path::ellipse(center, radii), FillRule::NonZero, paint);
+    }
+
+    pub fn stroke_ellipse(
+        &mut self,
+        center: cgmath::Point2<f32>,
+        radii: cgmath::Vector2<f32>,
+        parameters: &StrokeParameters,
+    ) {
+        self.stroke_path(&Path::ellipse(center, radii), parameters);
+    }
+
+    /// Draws a part of the ellipse outline starting at `start_angle` and going clockwise by `sweep`
+    pub fn stroke_arc(
+        &mut self,
+        center: cgmath::Point2<f32>,
+        radii: cgmath::Vector2<f32>,
+        start_angle: cgmath::Rad<f32>,
+        sweep: cgmath::Rad<f32>,
+        parameters: &StrokeParameters,
+    ) {
+        let mut path = Path::new();
+        path.arc(center, radii, start_angle, sweep);
+        self.stroke_path(&path, parameters);
+    }
+
+    /// Fills a slice of the ellipse starting at `start_angle` and going clockwise by `sweep`
+    pub fn fill_pie<'a>(
+        &mut self,
+        center: cgmath::Point2<f32>,
+        radii: cgmath::Vector2<f32>,
+        start_angle: cgmath::Rad<f32>,
+        sweep: cgmath::Rad<f32>,
+        paint: impl Into<Paint<'a>>,
+    ) {
+        let path = Path::pie(center, radii, start_angle, sweep);
+        self.fill_path(&path, FillRule::NonZero, paint);
+    }
+
+    pub fn stroke_pie(
+        &mut self,
+        center: cgmath::Point2<f32>,
+        radii: cgmath::Vector2<f32>,
+        start_angle: cgmath::Rad<f32>,
+        sweep: cgmath::Rad<f32>,
+        parameters: &StrokeParameters,
+    ) {
+        let path = Path::pie(center, radii, start_angle, sweep);
+        self.stroke_path(&path, parameters);
+    }
+
+    /// Fills rectangle given its top left corner and size
+    pub fn fill_rectangle<'a>(
+        &mut self,
+        position: cgmath::Point2<f32>,
+        size: cgmath::Vector2<f32>,
+        paint: impl Into<Paint<'a>>,
+    ) {
+        let path = Path::rounded_rectangle(position, size, CornerRadii::uniform(0.0));
+        self.fill_path(&path, FillRule::NonZero, paint);
+    }
+
+    /// Fills rectangle given its top left corner and size with rounded corners
+    pub fn fill_rounded_rectangle<'a>(
+        &mut self,
+        position: cgmath::Point2<f32>,
+        size: cgmath::Vector2<f32>,
+        radii: CornerRadii,
+        paint: impl Into<Paint<'a>>,
+    ) {
+        let path = Path::rounded_rectangle(position, size, radii);
+        self.fill_path(&path, FillRule::NonZero, paint);
+    }
+
+    pub fn stroke_rounded_rectangle(
+        &mut self,
+        position: cgmath::Point2<f32>,
+        size: cgmath::Vector2<f32>,
+        radii: CornerRadii,
+        parameters: &StrokeParameters,
+    ) {
+        let path = Path::rounded_rectangle(position, size, radii);
+        self.stroke_path(&path, parameters);
+    }
+
+    /// Fills polygon through the `points`, which may be concave or self-intersecting
+    pub fn fill_polygon<'a>(
+        &mut self,
+        points: &[cgmath::Point2<f32>],
+        rule: FillRule,
+        paint: impl Into<Paint<'a>>,
+    ) {
+        self.fill_path(&Path::polygon(points), rule, paint);
+    }
+
+    pub fn stroke_polygon(
+        &mut self,
+        points: &[cgmath::Point2<f32>],
+        parameters: &StrokeParameters,
+    ) {
+        self.stroke_polyline(points, true, parameters);
+    }
+
+    pub fn stroke_quadratic_bezier(
+        &mut self,
+        start: cgmath::Point2<f32>,
+        control: cgmath::Point2<f32>,
+        end: cgmath::Point2<f32>,
+        parameters: &StrokeParameters,
+    ) {
+        let mut path = Path::new();
+        path.move_to(start).quadratic_to(control, end);
+        self.stroke_path(&path, parameters);
+    }
+
+    /// Fills the area between the curve and the line connecting its ends
+    pub fn fill_quadratic_bezier<'a>(
+        &mut self,
+        start: cgmath::Point2<f32>,
+        control: cgmath::Point2<f32>,
+        end: cgmath::Point2<f32>,
+        paint: impl Into<Paint<'a>>,
+    ) {
+        let mut path = Path::new();
+        path.move_to(start).quadratic_to(control, end).close();
+        self.fill_path(&path, FillRule::NonZero, paint);
+    }
+
+    pub fn stroke_cubic_bezier(
+        &mut self,
+        start: cgmath::Point2<f32>,
+        control1: cgmath::Point2<f32>,
+        control2: cgmath::Point2<f32>,
+        end: cgmath::Point2<f32>,
+        parameters: &StrokeParameters,
+    ) {
+        let mut path = Path::new();
+        path.move_to(start).cubic_to(control1, control2, end);
+        self.stroke_path(&path, parameters);
+    }
+
+    /// Fills the area between the curve and the line connecting its ends,
+    /// loops of the curve are filled using the even-odd rule
+    pub fn fill_cubic_bezier<'a>(
+        &mut self,
+        start: cgmath::Point2<f32>,
+        control1: cgmath::Point2<f32>,
+        control2: cgmath::Point2<f32>,
+        end: cgmath::Point2<f32>,
+        paint: impl Into<Paint<'a>>,
+    ) {
+        let mut path = Path::new();
+        path.move_to(start)
+            .cubic_to(control1, control2, end)
+            .close();
+        self.fill_path(&path, FillRule::EvenOdd, paint);
+    }
+}
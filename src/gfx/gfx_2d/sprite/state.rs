@@ -0,0 +1,312 @@
+use super::blend_mode::BlendMode;
+use super::{Rect, Sprite};
+use cgmath::{InnerSpace, SquareMatrix, Transform};
+
+/// Transform, clipping and blend mode applied to everything drawn on a sprite
+#[derive(Debug, Clone)]
+pub(super) struct DrawState {
+    pub(super) transform: cgmath::Matrix3<f32>,
+    /// Pushed clip rectangles in pixels of the sprite, each one already
+    /// intersected with the previous ones
+    pub(super) clips: Vec<Rect>,
+    pub(super) blend_mode: BlendMode,
+}
+
+impl Default for DrawState {
+    fn default() -> Self {
+        Self {
+            transform: cgmath::Matrix3::identity(),
+            clips: vec![],
+            blend_mode: BlendMode::default(),
+        }
+    }
+}
+
+/// Methods related to the drawing state
+///
+/// Like in a canvas, transforms are applied to the local coordinates of the following draws
+/// and apply in the reverse order, so the last one is applied first.
+/// `clear` ignores the state and clears the whole sprite
+impl Sprite {
+    /// Pushes the transform, the clip rectangles and the blend mode on the stack
+    pub fn save(&mut self) {
+        self.saved_states.push(self.state.clone());
+    }
+
+    /// Pops the transform, the clip rectangles and the blend mode from the stack,
+    /// does nothing if there is no saved state
+    pub fn restore(&mut self) {
+        if let Some(state) = self.saved_states.pop() {
+            self.state = state;
+        }
+    }
+
+    pub fn translate(&mut self, offset: cgmath::Vector2<f32>) {
+        self.state.transform = self.state.transform * cgmath::Matrix3::from_translation(offset);
+    }
+
+    pub fn scale(&mut self, scale: cgmath::Vector2<f32>) {
+        self.state.transform =
+            self.state.transform * cgmath::Matrix3::from_nonuniform_scale(scale.x, scale.y);
+    }
+
+    /// Rotates clockwise around the local origin
+    pub fn rotate(&mut self, angle: cgmath::Rad<f32>) {
+        self.state.transform = self.state.transform * cgmath::Matrix3::from_angle_z(angle);
+    }
+
+    /// Returns the matrix transforming local coordinates into pixels of the sprite
+    pub fn transform(&self) -> cgmath::Matrix3<f32> {
+        self.state.transform
+    }
+
+    pub fn set_transform(&mut self, transform: cgmath::Matrix3<f32>) {
+        self.state.transform = transform;
+    }
+
+    pub fn reset_transform(&mut self) {
+        self.state.transform = cgmath::Matrix3::identity();
+    }
+
+    /// Restricts drawing to the rectangle in local coordinates intersected with the current clip,
+    /// a transformed rectangle is replaced by its bounds
+    pub fn push_clip_rect(&mut self, rect: Rect) {
+        let (x0, y0) = (rect.x as f32, rect.y as f32);
+        let (x1, y1) = (x0 + rect.width as f32, y0 + rect.height as f32);
+        let corners = [(x0, y0), (x1, y0), (x1, y1), (x0, y1)]
+            .map(|(x, y)| self.to_device(cgmath::Point2::new(x, y)));
+
+        let min_x = corners.iter().map(|c| c.x).fold(f32::INFINITY, f32::min);
+        let min_y = corners.iter().map(|c| c.y).fold(f32::INFINITY, f32::min);
+        let max_x = corners
+            .iter()
+            .map(|c| c.x)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let max_y = corners
+            .iter()
+            .map(|c| c.y)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let (x, y) = (min_x.round() as i32, min_y.round() as i32);
+        let device_rect = Rect::new(
+            x,
+            y,
+            (max_x.round() as i32 - x).max(0) as u32,
+            (max_y.round() as i32 - y).max(0) as u32,
+        );
+
+        let clip = match self.state.clips.last() {
+            Some(clip) => clip
+                .intersection(&device_rect)
+                .unwrap_or(Rect::new(0, 0, 0, 0)),
+            None => device_rect,
+        };
+        self.state.clips.push(clip);
+    }
+
+    /// Removes the last clip rectangle pushed since the last `save`
+    pub fn pop_clip_rect(&mut self) {
+        let saved_clips = self.saved_states.last().map_or(0, |s| s.clips.len());
+        if self.state.clips.len() > saved_clips {
+            self.state.clips.pop();
+        }
+    }
+
+    /// Returns the area drawing is restricted to in pixels of the sprite
+    pub fn clip_rect(&self) -> Option<Rect> {
+        self.state.clips.last().copied()
+    }
+
+    /// Sets how the following draws are combined with the pixels of the sprite
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.state.blend_mode = blend_mode;
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.state.blend_mode
+    }
+
+    /// Returns the min and max corners of the smallest rectangle in local coordinates
+    /// containing all pixels that can be drawn on, which are limited by the clip rectangle
+    pub fn local_bounds(&self) -> (cgmath::Point2<f32>, cgmath::Point2<f32>) {
+        let area = self
+            .clip_rect()
+            .unwrap_or_else(|| Rect::new(0, 0, self.width, self.height));
+        let (x0, y0) = (area.x as f32, area.y as f32);
+        let (x1, y1) = (x0 + area.width as f32, y0 + area.height as f32);
+        let corners = [(x0, y0), (x1, y0), (x1, y1), (x0, y1)]
+            .map(|(x, y)| self.to_local(cgmath::Point2::new(x, y)));
+
+        let min_x = corners.iter().map(|c| c.x).fold(f32::INFINITY, f32::min);
+        let min_y = corners.iter().map(|c| c.y).fold(f32::INFINITY, f32::min);
+        let max_x = corners
+            .iter()
+            .map(|c| c.x)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let max_y = corners
+            .iter()
+            .map(|c| c.y)
+            .fold(f32::NEG_INFINITY, f32::max);
+        (
+            cgmath::Point2::new(min_x, min_y),
+            cgmath::Point2::new(max_x, max_y),
+        )
+    }
+
+    /// Transforms a point in local coordinates into pixels of the sprite
+    pub(super) fn to_device(&self, point: cgmath::Point2<f32>) -> cgmath::Point2<f32> {
+        self.state.transform.transform_point(point)
+    }
+
+    /// Transforms a point in pixels of the sprite into local coordinates,
+    /// use `inverse_transform` when transforming many points
+    pub(super) fn to_local(&self, point: cgmath::Point2<f32>) -> cgmath::Point2<f32> {
+        self.inverse_transform().transform_point(point)
+    }
+
+    /// Returns the matrix transforming pixels of the sprite into local coordinates,
+    /// a transform that can't be inverted is ignored
+    pub(super) fn inverse_transform(&self) -> cgmath::Matrix3<f32> {
+        self.state
+            .transform
+            .invert()
+            .unwrap_or_else(cgmath::Matrix3::identity)
+    }
+
+    /// Returns the pixel of the sprite the center of the local pixel falls into
+    pub(super) fn device_pixel(&self, pixel: cgmath::Point2<i32>) -> cgmath::Point2<i32> {
+        let center = cgmath::Point2::new(pixel.x as f32 + 0.5, pixel.y as f32 + 0.5);
+        let device = self.to_device(center);
+        cgmath::Point2::new(device.x.floor() as i32, device.y.floor() as i32)
+    }
+
+    /// Returns the factor areas are scaled by on average in both directions
+    pub(super) fn device_scale(&self) -> f32 {
+        let m = self.state.transform;
+        (m.x.x * m.y.y - m.x.y * m.y.x).abs().sqrt()
+    }
+
+    /// Returns true if the transform only moves the drawing
+    pub(super) fn is_translation(&self) -> bool {
+        let m = self.state.transform;
+        m.x.x == 1.0 && m.x.y == 0.0 && m.y.x == 0.0 && m.y.y == 1.0
+    }
+
+    /// Returns true if the transform keeps circles circular
+    pub(super) fn is_similarity(&self) -> bool {
+        let m = self.state.transform;
+        let (x_axis, y_axis) = (m.x.truncate(), m.y.truncate());
+        (x_axis.magnitude() - y_axis.magnitude()).abs() < 1e-4 && x_axis.dot(y_axis).abs() < 1e-4
+    }
+
+    /// Returns true if the pixel of the sprite lies outside of the clip rectangle
+    pub(super) fn is_clipped(&self, x: i32, y: i32) -> bool {
+        match self.state.clips.last() {
+            Some(clip) => !clip.contains(cgmath::Point2::new(x, y)),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::texture::Color;
+
+    fn sprite() -> Sprite {
+        Sprite::new(16, 16, Color::TRANSPARENT)
+    }
+
+    #[test]
+    fn restore_returns_to_each_saved_state() {
+        let mut sprite = sprite();
+        sprite.translate(cgmath::Vector2::new(2.0, 0.0));
+        sprite.save();
+        sprite.scale(cgmath::Vector2::new(2.0, 2.0));
+        sprite.set_blend_mode(BlendMode::Additive);
+        sprite.save();
+        sprite.reset_transform();
+        sprite.push_clip_rect(Rect::new(0, 0, 4, 4));
+
+        sprite.restore();
+        assert_eq!(
+            sprite.to_device(cgmath::Point2::new(1.0, 1.0)),
+            cgmath::Point2::new(4.0, 2.0)
+        );
+        assert_eq!(sprite.blend_mode(), BlendMode::Additive);
+        assert_eq!(sprite.clip_rect(), None);
+
+        sprite.restore();
+        assert_eq!(
+            sprite.to_device(cgmath::Point2::new(1.0, 1.0)),
+            cgmath::Point2::new(3.0, 1.0)
+        );
+        assert_eq!(sprite.blend_mode(), BlendMode::SourceOver);
+
+        // Restoring without a saved state keeps the current one
+        sprite.restore();
+        assert_eq!(
+            sprite.to_device(cgmath::Point2::new(1.0, 1.0)),
+            cgmath::Point2::new(3.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn clip_rects_are_intersected() {
+        let mut sprite = sprite();
+        sprite.push_clip_rect(Rect::new(2, 2, 8, 8));
+        sprite.push_clip_rect(Rect::new(6, 0, 8, 4));
+        assert_eq!(sprite.clip_rect(), Some(Rect::new(6, 2, 4, 2)));
+
+        sprite.push_clip_rect(Rect::new(12, 12, 2, 2));
+        assert_eq!(sprite.clip_rect(), Some(Rect::new(0, 0, 0, 0)));
+        sprite.put_pixel(6, 2, Color::WHITE);
+        assert_eq!(sprite.values[2 * 16 + 6].a, 0);
+
+        sprite.pop_clip_rect();
+        sprite.put_pixel(6, 2, Color::WHITE);
+        sprite.put_pixel(5, 2, Color::WHITE);
+        assert_eq!(sprite.values[2 * 16 + 6].a, 255);
+        assert_eq!(sprite.values[2 * 16 + 5].a, 0);
+        sprite.pop_clip_rect();
+        assert_eq!(sprite.clip_rect(), Some(Rect::new(2, 2, 8, 8)));
+    }
+
+    #[test]
+    fn clip_rects_are_transformed_to_their_bounds() {
+        let mut sprite = sprite();
+        sprite.translate(cgmath::Vector2::new(8.0, 8.0));
+        sprite.rotate(cgmath::Rad(std::f32::consts::FRAC_PI_4));
+        sprite.push_clip_rect(Rect::new(-2, -2, 4, 4));
+        // The rotated square's diagonal is 4√2 ≈ 5.66 pixels long
+        assert_eq!(sprite.clip_rect(), Some(Rect::new(5, 5, 6, 6)));
+    }
+
+    #[test]
+    fn clip_rects_pushed_before_save_are_kept_until_restore() {
+        let mut sprite = sprite();
+        sprite.push_clip_rect(Rect::new(0, 0, 8, 8));
+        sprite.save();
+        sprite.push_clip_rect(Rect::new(4, 4, 8, 8));
+        sprite.pop_clip_rect();
+        sprite.pop_clip_rect();
+        assert_eq!(sprite.clip_rect(), Some(Rect::new(0, 0, 8, 8)));
+        sprite.restore();
+        sprite.pop_clip_rect();
+        assert_eq!(sprite.clip_rect(), None);
+    }
+
+    #[test]
+    fn local_points_are_transformed_back() {
+        let mut sprite = sprite();
+        sprite.translate(cgmath::Vector2::new(3.0, 1.0));
+        sprite.rotate(cgmath::Rad(1.0));
+        sprite.scale(cgmath::Vector2::new(2.0, 0.5));
+        let point = cgmath::Point2::new(1.5, -4.0);
+        let local = sprite.to_local(sprite.to_device(point));
+        assert!((local.x - point.x).abs() < 1e-4 && (local.y - point.y).abs() < 1e-4);
+
+        // A transform that can't be inverted is ignored
+        sprite.scale(cgmath::Vector2::new(0.0, 1.0));
+        assert_eq!(sprite.to_local(point), point);
+    }
+}
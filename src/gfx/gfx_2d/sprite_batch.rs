@@ -0,0 +1,165 @@
+use crate::gfx::texture::{Color, Image, Texture};
+use wgpu::util::DeviceExt;
+
+/// Single textured quad drawn by a `SpriteBatch`
+#[derive(Debug, Copy, Clone)]
+pub struct SpriteInstance {
+    /// Position of the center of the quad in world coordinates,
+    /// which match pixels of the 2D surfaces with the default camera
+    pub position: cgmath::Point2<f32>,
+    /// Multiplier of the size of the `uv_rect` in texture's pixels,
+    /// negative values flip the quad
+    pub scale: cgmath::Vector2<f32>,
+    /// Clockwise rotation around the center
+    pub rotation: cgmath::Rad<f32>,
+    /// Color the texture is multiplied by
+    pub tint: Color,
+    /// Part of the texture drawn on the quad: `[min_u, min_v, max_u, max_v]`
+    pub uv_rect: [f32; 4],
+    /// Instances with greater depth are drawn behind instances with lesser one
+    pub depth: f32,
+}
+
+impl Default for SpriteInstance {
+    fn default() -> Self {
+        Self {
+            position: cgmath::Point2::new(0.0, 0.0),
+            scale: cgmath::Vector2::new(1.0, 1.0),
+            rotation: cgmath::Rad(0.0),
+            tint: Color::WHITE,
+            uv_rect: [0.0, 0.0, 1.0, 1.0],
+            depth: 0.0,
+        }
+    }
+}
+
+impl SpriteInstance {
+    fn as_raw(&self, texture_size: (u32, u32)) -> SpriteInstanceRaw {
+        let [min_u, min_v, max_u, max_v] = self.uv_rect;
+        SpriteInstanceRaw {
+            position: self.position.into(),
+            size: [
+                (max_u - min_u) * texture_size.0 as f32 * self.scale.x,
+                (max_v - min_v) * texture_size.1 as f32 * self.scale.y,
+            ],
+            rotation: self.rotation.0,
+            uv_rect: self.uv_rect,
+            tint: [
+                self.tint.r as f32 / 255.0,
+                self.tint.g as f32 / 255.0,
+                self.tint.b as f32 / 255.0,
+                self.tint.a as f32 / 255.0,
+            ],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(super) struct SpriteInstanceRaw {
+    position: [f32; 2],
+    size: [f32; 2],
+    rotation: f32,
+    uv_rect: [f32; 4],
+    tint: [f32; 4],
+}
+
+impl SpriteInstanceRaw {
+    pub(super) fn format<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const ATTRIBS: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![2 => Float32x2, 3 => Float32x2, 4 => Float32, 5 => Float32x4, 6 => Float32x4];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBS,
+        }
+    }
+}
+
+/// Many instances of one texture drawn with a single draw call
+pub struct SpriteBatch {
+    pub name: String,
+    pub instances: Vec<SpriteInstance>,
+    texture_size: (u32, u32),
+    texture_bind_group: wgpu::BindGroup,
+    instance_buffer: Option<wgpu::Buffer>,
+    instance_capacity: usize,
+    instance_count: u32,
+}
+
+impl SpriteBatch {
+    pub(super) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: &str,
+        image: &Image,
+        pixelated: bool,
+    ) -> Self {
+        let texture = Texture::from_image(device, queue, &image.file, &image.name, pixelated);
+        Self::from_texture(device, name, &texture)
+    }
+
+    pub(super) fn from_texture(device: &wgpu::Device, name: &str, texture: &Texture) -> Self {
+        Self {
+            name: name.to_string(),
+            instances: vec![],
+            texture_size: (texture.size.width, texture.size.height),
+            texture_bind_group: Texture::texture_bind_group(device, texture),
+            instance_buffer: None,
+            instance_capacity: 0,
+            instance_count: 0,
+        }
+    }
+
+    /// Draws the instances with another texture, their `uv_rect`s are kept
+    pub(super) fn set_texture(&mut self, device: &wgpu::Device, texture: &Texture) {
+        self.texture_size = (texture.size.width, texture.size.height);
+        self.texture_bind_group = Texture::texture_bind_group(device, texture);
+    }
+
+    /// Returns width and height of the texture in pixels
+    pub fn texture_size(&self) -> (u32, u32) {
+        self.texture_size
+    }
+
+    /// Writes the instances sorted by depth into the instance buffer,
+    /// the buffer is only recreated when it is too small
+    pub(super) fn update_buffer(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut instances: Vec<&SpriteInstance> = self.instances.iter().collect();
+        instances.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+        let instance_data: Vec<SpriteInstanceRaw> = instances
+            .iter()
+            .map(|instance| instance.as_raw(self.texture_size))
+            .collect();
+        self.instance_count = instance_data.len() as u32;
+
+        match &self.instance_buffer {
+            Some(buffer) if self.instance_capacity >= instance_data.len() => {
+                queue.write_buffer(buffer, 0, bytemuck::cast_slice(&instance_data));
+            }
+            _ => {
+                self.instance_capacity = instance_data.len().next_power_of_two();
+                let mut contents = instance_data;
+                contents.resize(self.instance_capacity, bytemuck::Zeroable::zeroed());
+                self.instance_buffer = Some(device.create_buffer_init(
+                    &wgpu::util::BufferInitDescriptor {
+                        label: Some(&format!("{}'s instance buffer", self.name)),
+                        contents: bytemuck::cast_slice(&contents),
+                        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                    },
+                ));
+            }
+        }
+    }
+
+    /// Draws all instances, expects the quad's vertex and index buffers to be set
+    pub(super) fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.instance_count == 0 {
+            return;
+        }
+        if let Some(instance_buffer) = &self.instance_buffer {
+            render_pass.set_bind_group(1, &self.texture_bind_group, &[]);
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+            render_pass.draw_indexed(0..6, 0, 0..self.instance_count);
+        }
+    }
+}
@@ -0,0 +1,437 @@
+use crate::gfx::gfx_2d::animation::{AnimationClip, AnimationFrame, AnimationMode};
+use crate::gfx::gfx_2d::sprite::{DrawSpriteParameters, Rect, Sprite};
+use anyhow::bail;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Part of a sprite sheet drawn as a single image
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SpriteFrame {
+    /// Part of the sheet's sprite
+    pub rect: Rect,
+    /// Position of the rect inside of the original frame,
+    /// frames trimmed by packing tools are drawn shifted by it
+    pub offset: cgmath::Vector2<i32>,
+}
+
+impl SpriteFrame {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            offset: cgmath::Vector2::new(0, 0),
+        }
+    }
+}
+
+/// Image sliced into frames, e.g. a texture atlas or frames of animations
+pub struct SpriteSheet {
+    pub sprite: Sprite,
+    frames: Vec<SpriteFrame>,
+    frame_names: HashMap<String, usize>,
+    clips: HashMap<String, AnimationClip>,
+}
+
+impl SpriteSheet {
+    /// Creates a sheet with frames given by `rects`
+    pub fn from_rects(sprite: Sprite, rects: &[Rect]) -> Self {
+        Self {
+            sprite,
+            frames: rects.iter().map(|r| SpriteFrame::new(*r)).collect(),
+            frame_names: HashMap::new(),
+            clips: HashMap::new(),
+        }
+    }
+
+    /// Slices the sprite into frames of the same size row by row,
+    /// incomplete frames at the right and bottom edges are ignored
+    pub fn from_grid(sprite: Sprite, frame_width: u32, frame_height: u32) -> Self {
+        let columns = sprite.width() / frame_width.max(1);
+        let rows = sprite.height() / frame_height.max(1);
+        let rects: Vec<Rect> = (0..rows)
+            .flat_map(|row| {
+                (0..columns).map(move |column| {
+                    Rect::new(
+                        (column * frame_width) as i32,
+                        (row * frame_height) as i32,
+                        frame_width,
+                        frame_height,
+                    )
+                })
+            })
+            .collect();
+
+        Self::from_rects(sprite, &rects)
+    }
+
+    /// Creates a sheet from the JSON exported by Aseprite with either the hash or the array layout
+    ///
+    /// Frames keep the order of the file and are named by their file names,
+    /// frame tags become clips named after them
+    pub fn from_aseprite_json(sprite: Sprite, json: &str) -> anyhow::Result<Self> {
+        let data: AtlasData = serde_json::from_str(json)?;
+        let mut sheet = Self::from_atlas(sprite, &data)?;
+
+        for tag in data.meta.frame_tags.iter() {
+            if tag.from > tag.to || tag.to >= sheet.frames.len() {
+                bail!("Frame tag {} refers to missing frames", tag.name);
+            }
+            let mut frames: Vec<AnimationFrame> = (tag.from..=tag.to)
+                .map(|index| AnimationFrame {
+                    index,
+                    duration: data.frames[index].1.duration.unwrap_or(100) as f32 / 1000.0,
+                })
+                .collect();
+
+            let mode = match tag.direction.as_str() {
+                "forward" => AnimationMode::Loop,
+                "reverse" => {
+                    frames.reverse();
+                    AnimationMode::Loop
+                }
+                "pingpong" => AnimationMode::PingPong,
+                "pingpong_reverse" => {
+                    frames.reverse();
+                    AnimationMode::PingPong
+                }
+                direction => bail!("Unknown direction {} of frame tag {}", direction, tag.name),
+            };
+            let mode = match tag.repeat.as_deref() {
+                Some("1") => AnimationMode::Once,
+                _ => mode,
+            };
+
+            sheet.add_clip(&tag.name, AnimationClip::with_durations(frames, mode));
+        }
+
+        Ok(sheet)
+    }
+
+    /// Creates a sheet from the JSON exported by TexturePacker with either the hash or the array layout
+    ///
+    /// Frames keep the order of the file and are named by their file names
+    pub fn from_texture_packer_json(sprite: Sprite, json: &str) -> anyhow::Result<Self> {
+        let data: AtlasData = serde_json::from_str(json)?;
+        Self::from_atlas(sprite, &data)
+    }
+
+    fn from_atlas(sprite: Sprite, data: &AtlasData) -> anyhow::Result<Self> {
+        let mut sheet = Self::from_rects(sprite, &[]);
+
+        for (name, frame) in data.frames.iter() {
+            if frame.rotated {
+                bail!(
+                    "Frame {} is rotated, rotated frames are not supported",
+                    name
+                );
+            }
+            let rect = Rect::new(frame.frame.x, frame.frame.y, frame.frame.w, frame.frame.h);
+            let right = u32::try_from(rect.x)
+                .ok()
+                .and_then(|x| x.checked_add(rect.width));
+            let bottom = u32::try_from(rect.y)
+                .ok()
+                .and_then(|y| y.checked_add(rect.height));
+            match (right, bottom) {
+                (Some(right), Some(bottom))
+                    if right <= sheet.sprite.width() && bottom <= sheet.sprite.height() => {}
+                _ => bail!("Frame {} lies outside of the sprite", name),
+            }
+
+            let offset = frame
+                .sprite_source_size
+                .map(|s| cgmath::Vector2::new(s.x, s.y))
+                .unwrap_or(cgmath::Vector2::new(0, 0));
+            sheet.add_frame(name, SpriteFrame { rect, offset });
+        }
+
+        Ok(sheet)
+    }
+
+    /// Adds a frame and returns its index, the name can be used to look the index up
+    pub fn add_frame(&mut self, name: &str, frame: SpriteFrame) -> usize {
+        self.frames.push(frame);
+        self.frame_names
+            .insert(name.to_string(), self.frames.len() - 1);
+        self.frames.len() - 1
+    }
+
+    pub fn frame(&self, index: usize) -> Option<&SpriteFrame> {
+        self.frames.get(index)
+    }
+
+    /// Returns the index of the frame with given name
+    pub fn frame_index(&self, name: &str) -> Option<usize> {
+        self.frame_names.get(name).copied()
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Adds the clip, a clip with the same name is replaced
+    pub fn add_clip(&mut self, name: &str, clip: AnimationClip) {
+        self.clips.insert(name.to_string(), clip);
+    }
+
+    pub fn get_clip(&self, name: &str) -> Option<&AnimationClip> {
+        self.clips.get(name)
+    }
+
+    pub fn remove_clip(&mut self, name: &str) -> Option<AnimationClip> {
+        self.clips.remove(name)
+    }
+
+    /// Draws the frame on the `target` with `Sprite::draw_sprite_ex`,
+    /// the pivot is given in pixels of the original untrimmed frame
+    pub fn draw_frame(
+        &self,
+        target: &mut Sprite,
+        index: usize,
+        position: cgmath::Point2<f32>,
+        parameters: &DrawSpriteParameters,
+    ) {
+        let frame = match self.frames.get(index) {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let parameters = DrawSpriteParameters {
+            source: Some(frame.rect),
+            pivot: cgmath::Point2::new(
+                parameters.pivot.x - frame.offset.x as f32,
+                parameters.pivot.y - frame.offset.y as f32,
+            ),
+            ..*parameters
+        };
+        target.draw_sprite_ex(&self.sprite, position, &parameters);
+    }
+}
+
+/// Data shared by the JSON formats of Aseprite and TexturePacker
+#[derive(Deserialize)]
+struct AtlasData {
+    #[serde(deserialize_with = "deserialize_frames")]
+    frames: Vec<(String, AtlasFrame)>,
+    #[serde(default)]
+    meta: AtlasMeta,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AtlasFrame {
+    #[serde(default)]
+    filename: Option<String>,
+    frame: AtlasRect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    sprite_source_size: Option<AtlasRect>,
+    /// Duration in milliseconds, only exported by Aseprite
+    #[serde(default)]
+    duration: Option<u32>,
+}
+
+#[derive(Copy, Clone, Deserialize)]
+struct AtlasRect {
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AtlasMeta {
+    #[serde(default)]
+    frame_tags: Vec<FrameTag>,
+}
+
+#[derive(Deserialize)]
+struct FrameTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default = "default_direction")]
+    direction: String,
+    /// Number of repetitions, missing when the tag loops forever
+    #[serde(default)]
+    repeat: Option<String>,
+}
+
+fn default_direction() -> String {
+    "forward".to_string()
+}
+
+/// Reads frames stored either as an object of named frames or as an array of frames
+/// with file names, the order of the file is kept in both cases
+fn deserialize_frames<'de, D>(deserializer: D) -> Result<Vec<(String, AtlasFrame)>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    struct FramesVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for FramesVisitor {
+        type Value = Vec<(String, AtlasFrame)>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("an object or an array of frames")
+        }
+
+        fn visit_map<A: serde::de::MapAccess<'de>>(
+            self,
+            mut map: A,
+        ) -> Result<Self::Value, A::Error> {
+            let mut frames = vec![];
+            while let Some(entry) = map.next_entry::<String, AtlasFrame>()? {
+                frames.push(entry);
+            }
+            Ok(frames)
+        }
+
+        fn visit_seq<A: serde::de::SeqAccess<'de>>(
+            self,
+            mut seq: A,
+        ) -> Result<Self::Value, A::Error> {
+            let mut frames = vec![];
+            while let Some(frame) = seq.next_element::<AtlasFrame>()? {
+                let name = frame
+                    .filename
+                    .clone()
+                    .unwrap_or_else(|| frames.len().to_string());
+                frames.push((name, frame));
+            }
+            Ok(frames)
+        }
+    }
+
+    deserializer.deserialize_any(FramesVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::texture::Color;
+
+    fn sprite() -> Sprite {
+        Sprite::new(64, 32, Color::TRANSPARENT)
+    }
+
+    fn error(json: &str) -> String {
+        match SpriteSheet::from_texture_packer_json(sprite(), json) {
+            Ok(_) => panic!("{} was imported", json),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    const ASEPRITE_HASH: &str = r#"{
+        "frames": {
+            "hero 0.aseprite": {"frame": {"x": 0, "y": 0, "w": 16, "h": 16}, "duration": 100},
+            "hero 1.aseprite": {"frame": {"x": 16, "y": 0, "w": 16, "h": 16}, "duration": 200},
+            "hero 2.aseprite": {"frame": {"x": 32, "y": 0, "w": 16, "h": 16}, "duration": 100},
+            "hero 3.aseprite": {"frame": {"x": 48, "y": 0, "w": 16, "h": 16}}
+        },
+        "meta": {"frameTags": [
+            {"name": "walk", "from": 0, "to": 2, "direction": "forward"},
+            {"name": "back", "from": 0, "to": 1, "direction": "reverse"},
+            {"name": "swing", "from": 1, "to": 3, "direction": "pingpong"},
+            {"name": "die", "from": 2, "to": 3, "repeat": "1"}
+        ]}
+    }"#;
+
+    #[test]
+    fn aseprite_hash_frames_and_tags_are_imported() {
+        let sheet = SpriteSheet::from_aseprite_json(sprite(), ASEPRITE_HASH).unwrap();
+        assert_eq!(sheet.frame_count(), 4);
+        assert_eq!(sheet.frame_index("hero 2.aseprite"), Some(2));
+        assert_eq!(sheet.frame(1).unwrap().rect, Rect::new(16, 0, 16, 16));
+
+        let clip = |name| sheet.get_clip(name).unwrap();
+        let indices = |name| -> Vec<usize> { clip(name).frames.iter().map(|f| f.index).collect() };
+        assert_eq!(indices("walk"), [0, 1, 2]);
+        assert_eq!(clip("walk").mode, AnimationMode::Loop);
+        assert_eq!(clip("walk").frames[1].duration, 0.2);
+        assert_eq!(indices("back"), [1, 0]);
+        assert_eq!(clip("swing").mode, AnimationMode::PingPong);
+        assert_eq!(clip("die").mode, AnimationMode::Once);
+        // Frames without a duration are shown for 100 milliseconds
+        assert_eq!(clip("die").frames[1].duration, 0.1);
+    }
+
+    #[test]
+    fn texture_packer_array_frames_are_imported_with_offsets() {
+        let json = r#"{"frames": [
+            {"filename": "coin", "frame": {"x": 0, "y": 0, "w": 10, "h": 12},
+             "spriteSourceSize": {"x": 3, "y": 2, "w": 10, "h": 12}},
+            {"filename": "gem", "frame": {"x": 10, "y": 0, "w": 16, "h": 16}},
+            {"frame": {"x": 26, "y": 0, "w": 4, "h": 4}}
+        ]}"#;
+        let sheet = SpriteSheet::from_texture_packer_json(sprite(), json).unwrap();
+        assert_eq!(sheet.frame_count(), 3);
+        assert_eq!(
+            *sheet.frame(0).unwrap(),
+            SpriteFrame {
+                rect: Rect::new(0, 0, 10, 12),
+                offset: cgmath::Vector2::new(3, 2),
+            }
+        );
+        assert_eq!(sheet.frame_index("gem"), Some(1));
+        assert_eq!(sheet.frame(1).unwrap().offset, cgmath::Vector2::new(0, 0));
+        // Frames without file names are named by their index
+        assert_eq!(sheet.frame_index("2"), Some(2));
+    }
+
+    #[test]
+    fn rotated_frames_are_rejected() {
+        assert_eq!(
+            error(
+                r#"{"frames": {"coin": {"frame": {"x": 0, "y": 0, "w": 8, "h": 8}, "rotated": true}}}"#
+            ),
+            "Frame coin is rotated, rotated frames are not supported"
+        );
+    }
+
+    #[test]
+    fn frames_outside_of_sprite_are_rejected() {
+        for frame in [
+            r#"{"x": 60, "y": 0, "w": 8, "h": 8}"#,
+            r#"{"x": 0, "y": 30, "w": 8, "h": 8}"#,
+            r#"{"x": -1, "y": 0, "w": 8, "h": 8}"#,
+            r#"{"x": 1, "y": 0, "w": 4294967295, "h": 8}"#,
+        ] {
+            let json = format!(r#"{{"frames": {{"coin": {{"frame": {}}}}}}}"#, frame);
+            assert_eq!(error(&json), "Frame coin lies outside of the sprite");
+        }
+        assert!(SpriteSheet::from_texture_packer_json(
+            sprite(),
+            r#"{"frames": {"all": {"frame": {"x": 0, "y": 0, "w": 64, "h": 32}}}}"#
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn invalid_frame_tags_are_rejected() {
+        let frames = r#""frames": [{"frame": {"x": 0, "y": 0, "w": 8, "h": 8}}]"#;
+        let error = |tag: &str| {
+            let json = format!(r#"{{{}, "meta": {{"frameTags": [{}]}}}}"#, frames, tag);
+            match SpriteSheet::from_aseprite_json(sprite(), &json) {
+                Ok(_) => panic!("{} was imported", tag),
+                Err(error) => error.to_string(),
+            }
+        };
+        assert_eq!(
+            error(r#"{"name": "run", "from": 0, "to": 1}"#),
+            "Frame tag run refers to missing frames"
+        );
+        assert_eq!(
+            error(r#"{"name": "run", "from": 0, "to": 0, "direction": "sideways"}"#),
+            "Unknown direction sideways of frame tag run"
+        );
+    }
+
+    #[test]
+    fn grid_ignores_incomplete_frames() {
+        let sheet = SpriteSheet::from_grid(Sprite::new(50, 25, Color::TRANSPARENT), 16, 12);
+        assert_eq!(sheet.frame_count(), 6);
+        assert_eq!(sheet.frame(4).unwrap().rect, Rect::new(16, 12, 16, 12));
+    }
+}
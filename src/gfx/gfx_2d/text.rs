@@ -1,122 +1,424 @@
+use crate::gfx::gfx_2d::Rect;
 use crate::gfx::texture::Color;
 use ab_glyph::*;
+use layout::{FontChain, StyledSpan, TextLayout};
+use log::warn;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 
+mod font_registry;
+mod glyph_atlas;
+mod glyph_cache;
+mod layout;
+mod rich_text;
+
+pub use font_registry::*;
+pub(crate) use glyph_atlas::GlyphAtlas;
+use glyph_cache::LruCache;
+pub(crate) use glyph_cache::{FontKey, GlyphKey, RasterizedGlyph};
+pub use rich_text::*;
+
+const DEFAULT_FONT: &[u8] = include_bytes!("../../../res/fonts/HoneyRoom.ttf");
+const GLYPH_CACHE_CAPACITY: usize = 1024;
+const LAYOUT_CACHE_CAPACITY: usize = 64;
+/// Number of horizontal positions between pixels each glyph is rasterized at
+const SUBPIXEL_STEPS: f32 = 4.0;
+
+/// Lays out and rasterizes text, keeping the results for the next calls with the same parameters
 pub(crate) struct TextRasterizer {
-    default_font: FontRef<'static>,
+    default_font: FontArc,
+    /// Custom fonts by the address of their data, `None` if the data isn't a valid font
+    fonts: RefCell<HashMap<usize, Option<FontArc>>>,
+    glyphs: RefCell<LruCache<GlyphKey, Rc<RasterizedGlyph>>>,
+    layouts: RefCell<LruCache<LayoutKey, Rc<TextLayout>>>,
+}
+
+/// Font with the key identifying it in the caches and its scale for the size of the text
+type ResolvedFont = (FontKey, FontArc, PxScale);
+
+/// Everything the layout of a span depends on, colors are applied after the layout
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum SpanKey {
+    Text {
+        text: String,
+        fonts: Vec<FontKey>,
+        scale: u32,
+        underline: bool,
+        strikethrough: bool,
+    },
+    Image(usize),
+}
+
+/// Everything the layout of a text depends on
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct LayoutKey {
+    spans: Vec<SpanKey>,
+    base_fonts: Vec<FontKey>,
+    horizontal_alignment: HorizontalAlignment,
+    vertical_alignment: VerticalAlignment,
+    line_spacing: u32,
+    letter_spacing: u32,
+    width: u32,
+    height: u32,
+}
+
+/// Rasterized glyph at its position in pixels relative to the top left corner of the text box
+pub(crate) struct PlacedGlyph {
+    pub(crate) key: GlyphKey,
+    pub(crate) origin: cgmath::Point2<i32>,
+    pub(crate) glyph: Rc<RasterizedGlyph>,
+    pub(crate) color: Color,
+}
+
+/// Inline image with its top left corner relative to the top left corner of the text box
+pub(crate) struct PlacedImage {
+    pub(crate) origin: cgmath::Point2<i32>,
+    pub(crate) image: InlineImage,
+}
+
+/// Everything drawn for a text, relative to the top left corner of the text box
+#[derive(Default)]
+pub(crate) struct PlacedText {
+    pub(crate) glyphs: Vec<PlacedGlyph>,
+    pub(crate) images: Vec<PlacedImage>,
+    /// Underlines and strikethroughs
+    pub(crate) lines: Vec<(Rect, Color)>,
 }
 
 impl TextRasterizer {
     pub(crate) fn new() -> Self {
-        let default_font =
-            FontRef::try_from_slice(include_bytes!("../../../res/fonts/HoneyRoom.ttf")).unwrap();
+        let default_font = FontArc::try_from_slice(DEFAULT_FONT).unwrap();
 
-        Self { default_font }
+        Self {
+            default_font,
+            fonts: RefCell::new(HashMap::new()),
+            glyphs: RefCell::new(LruCache::new(GLYPH_CACHE_CAPACITY)),
+            layouts: RefCell::new(LruCache::new(LAYOUT_CACHE_CAPACITY)),
+        }
     }
 
-    pub(crate) fn get_rgba_from_text(
+    pub(crate) fn measure_text(
         &self,
         text: &TextParameters,
         width: u32,
         height: u32,
-    ) -> Vec<u8> {
-        if let FontParameters::Custom(data) = text.font {
-            if let Ok(font) = FontRef::try_from_slice(data) {
-                if let Some(px_scale) = self.default_font.pt_to_px_scale(text.scale) {
-                    let scaled_font = font.as_scaled(px_scale);
-                    return Self::get_data(&scaled_font, text, width, height);
+    ) -> TextMetrics {
+        let spans = Self::spans(text);
+        self.cached_layout(text, &spans, width, height)
+            .map(|(layout, _)| layout.metrics)
+            .unwrap_or_default()
+    }
+
+    /// Returns the rasterized glyphs, images and lines of the text laid out in a box of the given size
+    pub(crate) fn placed_text(&self, text: &TextParameters, width: u32, height: u32) -> PlacedText {
+        let spans = Self::spans(text);
+        let (layout, fonts) = match self.cached_layout(text, &spans, width, height) {
+            Some(layout) => layout,
+            None => return PlacedText::default(),
+        };
+        let color = |span: usize| spans[span].style.color.unwrap_or(text.color);
+
+        let glyphs = layout
+            .glyphs
+            .iter()
+            .map(|laid_out| {
+                // Glyphs start between pixels, the baseline is kept on the pixel grid
+                let glyph = &laid_out.glyph;
+                let steps = (glyph.position.x * SUBPIXEL_STEPS).round();
+                let x = (steps / SUBPIXEL_STEPS).floor();
+                let subpixel = (steps - x * SUBPIXEL_STEPS) as u8;
+
+                let (font_key, font, _) = &fonts[laid_out.span][laid_out.font];
+                let (key, rasterized) = self.rasterized_glyph(font, *font_key, glyph, subpixel);
+                PlacedGlyph {
+                    key,
+                    origin: cgmath::Point2::new(x as i32, glyph.position.y.round() as i32),
+                    glyph: rasterized,
+                    color: color(laid_out.span),
                 }
-            }
-        } else {
-            if let Some(px_scale) = self.default_font.pt_to_px_scale(text.scale) {
-                let scaled_font = self.default_font.as_scaled(px_scale);
-                return Self::get_data(&scaled_font, text, width, height);
-            }
+            })
+            .collect();
+
+        let images = layout
+            .images
+            .iter()
+            .filter_map(|laid_out| match &spans[laid_out.span].content {
+                SpanContent::Image(image) => Some(PlacedImage {
+                    origin: cgmath::Point2::new(
+                        laid_out.position.x.round() as i32,
+                        laid_out.position.y.round() as i32,
+                    ),
+                    image: image.clone(),
+                }),
+                SpanContent::Text(_) => None,
+            })
+            .collect();
+
+        let lines = layout
+            .decorations
+            .iter()
+            .map(|decoration| {
+                let x = decoration.position.x.round() as i32;
+                let y = decoration.position.y.round() as i32;
+                let right = (decoration.position.x + decoration.width).round() as i32;
+                let thickness = decoration.thickness.round().max(1.0) as u32;
+                let rect = Rect::new(x, y, (right - x).max(0) as u32, thickness);
+                (rect, color(decoration.span))
+            })
+            .collect();
+
+        PlacedText {
+            glyphs,
+            images,
+            lines,
         }
+    }
 
-        vec![0; width as usize * 4 * height as usize]
+    /// Returns the spans of the text, a text without spans is a single span of its `text`
+    fn spans(text: &TextParameters) -> Cow<'_, [TextSpan]> {
+        if text.spans.is_empty() {
+            Cow::Owned(vec![TextSpan::text(
+                text.text.clone(),
+                TextStyle::default(),
+            )])
+        } else {
+            Cow::Borrowed(&text.spans)
+        }
     }
 
-    fn get_data(
-        scaled_font: &PxScaleFont<&FontRef>,
+    /// Returns the layout of the spans with the fonts of each span,
+    /// or `None` if none of the fonts of a span can be used
+    fn cached_layout(
+        &self,
         text: &TextParameters,
+        spans: &[TextSpan],
         width: u32,
         height: u32,
-    ) -> Vec<u8> {
-        let glyphs = Self::layout_paragraph(&scaled_font, (0.0, 0.0).into(), width, &text.text);
-        Self::rasterize(&scaled_font, glyphs, width, height, &text.color)
+    ) -> Option<(Rc<TextLayout>, Vec<Vec<ResolvedFont>>)> {
+        let base_fonts = self.fonts(&text.font, text.scale, &text.fallback_fonts);
+        if base_fonts.is_empty() {
+            return None;
+        }
+        let span_fonts: Vec<Vec<ResolvedFont>> = spans
+            .iter()
+            .map(|span| match &span.content {
+                SpanContent::Text(_) => {
+                    let font = span.style.font.as_ref().unwrap_or(&text.font);
+                    let scale = span.style.scale.unwrap_or(text.scale);
+                    self.fonts(font, scale, &text.fallback_fonts)
+                }
+                SpanContent::Image(_) => vec![],
+            })
+            .collect();
+
+        let font_keys = |fonts: &[ResolvedFont]| fonts.iter().map(|(key, _, _)| *key).collect();
+        let key = LayoutKey {
+            spans: spans
+                .iter()
+                .zip(&span_fonts)
+                .map(|(span, fonts)| match &span.content {
+                    SpanContent::Text(text) => SpanKey::Text {
+                        text: text.clone(),
+                        fonts: font_keys(fonts),
+                        scale: fonts.first().map_or(0, |(_, _, scale)| scale.y.to_bits()),
+                        underline: span.style.underline,
+                        strikethrough: span.style.strikethrough,
+                    },
+                    SpanContent::Image(image) => SpanKey::Image(image.id()),
+                })
+                .collect(),
+            base_fonts: font_keys(&base_fonts),
+            horizontal_alignment: text.horizontal_alignment,
+            vertical_alignment: text.vertical_alignment,
+            line_spacing: text.line_spacing.to_bits(),
+            letter_spacing: text.letter_spacing.to_bits(),
+            width,
+            height,
+        };
+        if let Some(layout) = self.layouts.borrow_mut().get(&key) {
+            return Some((layout.clone(), span_fonts));
+        }
+
+        fn chain(fonts: &[ResolvedFont], letter_spacing: f32) -> FontChain<'_> {
+            FontChain {
+                fonts: fonts
+                    .iter()
+                    .map(|(_, font, scale)| font.as_scaled(*scale))
+                    .collect(),
+                keys: fonts.iter().map(|(key, _, _)| *key).collect(),
+                letter_spacing,
+            }
+        }
+        let mut styled_spans = Vec::with_capacity(spans.len());
+        for (span, fonts) in spans.iter().zip(&span_fonts) {
+            styled_spans.push(match &span.content {
+                SpanContent::Text(_) if fonts.is_empty() => return None,
+                SpanContent::Text(span_text) => StyledSpan::Text {
+                    text: span_text,
+                    fonts: chain(fonts, text.letter_spacing),
+                    underline: span.style.underline,
+                    strikethrough: span.style.strikethrough,
+                },
+                SpanContent::Image(image) => StyledSpan::Image {
+                    width: image.width() as f32,
+                    height: image.height() as f32,
+                },
+            });
+        }
+
+        let layout = Rc::new(layout::layout(
+            &styled_spans,
+            &chain(&base_fonts, text.letter_spacing),
+            text,
+            width,
+            height,
+        ));
+        self.layouts.borrow_mut().insert(key, layout.clone());
+        Some((layout, span_fonts))
     }
 
-    fn layout_paragraph(
-        scaled_font: &PxScaleFont<&FontRef>,
-        start_position: Point,
-        width: u32,
-        text: &str,
-    ) -> Vec<Glyph> {
-        let mut target: Vec<Glyph> = vec![];
-
-        let v_advance = scaled_font.height() + scaled_font.line_gap();
-        let max_x_position = start_position.x + width as f32;
-
-        let mut caret = start_position + point(0.0, scaled_font.height());
-        for c in text.chars() {
-            if c.is_control() {
-                if c == '\n' {
-                    caret = point(start_position.x, caret.y + v_advance);
+    /// Returns the font followed by the fallback fonts and the default font,
+    /// the fonts that can't be used are skipped
+    fn fonts(
+        &self,
+        font: &FontParameters,
+        scale: f32,
+        fallback_fonts: &[FontParameters],
+    ) -> Vec<ResolvedFont> {
+        let mut fonts: Vec<ResolvedFont> = vec![];
+        let default = FontParameters::Default;
+        let chain = std::iter::once(font)
+            .chain(fallback_fonts)
+            .chain(std::iter::once(&default));
+        for font in chain {
+            if let Some((key, font)) = self.font(font) {
+                if fonts.iter().any(|(other, _, _)| *other == key) {
+                    continue;
+                }
+                // Fonts differ in the size of their units, so each is scaled separately
+                if let Some(scale) = font.pt_to_px_scale(scale) {
+                    fonts.push((key, font, scale));
                 }
-                continue;
-            }
-            let mut glyph = scaled_font.scaled_glyph(c);
-            glyph.position = caret;
-            caret.x += scaled_font.h_advance(glyph.id);
-
-            if !c.is_whitespace() && caret.x > max_x_position {
-                caret = point(start_position.x, caret.y + v_advance);
-                glyph.position = caret;
-                caret.x += scaled_font.h_advance(glyph.id);
             }
-
-            target.push(glyph);
         }
-
-        target
+        fonts
     }
 
-    fn rasterize(
-        scaled_font: &PxScaleFont<&FontRef>,
-        glyphs: Vec<Glyph>,
-        width: u32,
-        height: u32,
-        color: &Color,
-    ) -> Vec<u8> {
-        let width = width as usize;
-        let height = height as usize;
+    /// Custom fonts are only parsed the first time they are used,
+    /// invalid ones are reported once and skipped
+    fn font(&self, font: &FontParameters) -> Option<(FontKey, FontArc)> {
+        match font {
+            FontParameters::Default => Some((
+                FontKey::Static(DEFAULT_FONT.as_ptr() as usize),
+                self.default_font.clone(),
+            )),
+            FontParameters::Custom(data) => {
+                let address = data.as_ptr() as usize;
+                let font = self
+                    .fonts
+                    .borrow_mut()
+                    .entry(address)
+                    .or_insert_with(|| match FontArc::try_from_slice(data) {
+                        Ok(font) => Some(font),
+                        Err(error) => {
+                            warn!("Failed to parse custom font: {}", error);
+                            None
+                        }
+                    })
+                    .clone()?;
+                Some((FontKey::Static(address), font))
+            }
+            FontParameters::Loaded(handle) => Some((handle.key(), handle.font().clone())),
+        }
+    }
 
-        let mut data = vec![0; width * 4 * height];
+    /// Returns the coverage of the glyph rasterized with its origin `subpixel` steps
+    /// to the right of a pixel corner
+    fn rasterized_glyph(
+        &self,
+        font: &FontArc,
+        font_key: FontKey,
+        glyph: &Glyph,
+        subpixel: u8,
+    ) -> (GlyphKey, Rc<RasterizedGlyph>) {
+        let key = GlyphKey {
+            font: font_key,
+            scale: (glyph.scale.x.to_bits(), glyph.scale.y.to_bits()),
+            id: glyph.id.0,
+            subpixel,
+        };
+        if let Some(rasterized) = self.glyphs.borrow_mut().get(&key) {
+            return (key, rasterized.clone());
+        }
 
-        for glyph in glyphs {
-            if let Some(outline) = scaled_font.outline_glyph(glyph) {
+        let origin = glyph
+            .id
+            .with_scale_and_position(glyph.scale, point(subpixel as f32 / SUBPIXEL_STEPS, 0.0));
+        let rasterized = match font.outline_glyph(origin) {
+            Some(outline) => {
                 let bounds = outline.px_bounds();
+                let width = bounds.width() as u32;
+                let height = bounds.height() as u32;
+                let mut coverage = vec![0; width as usize * height as usize];
                 outline.draw(|x, y, c| {
-                    let y = bounds.min.y as usize + y as usize;
-                    let x = bounds.min.x as usize + x as usize;
-                    let index = (y * width + x) * 4;
-                    if index + 3 < data.len() {
-                        data[index] = (c * color.r as f32 * 255.0) as u8;
-                        data[index + 1] = (c * color.g as f32 * 255.0) as u8;
-                        data[index + 2] = (c * color.b as f32 * 255.0) as u8;
-                        data[index + 3] = (c * color.a as f32 * 255.0) as u8;
+                    if let Some(pixel) = coverage.get_mut((y * width + x) as usize) {
+                        *pixel = (c * 255.0).round().clamp(0.0, 255.0) as u8;
                     }
-                })
+                });
+                RasterizedGlyph {
+                    left: bounds.min.x as i32,
+                    top: bounds.min.y as i32,
+                    width,
+                    height,
+                    coverage,
+                }
             }
-        }
+            None => RasterizedGlyph {
+                left: 0,
+                top: 0,
+                width: 0,
+                height: 0,
+                coverage: vec![],
+            },
+        };
 
-        data
+        let rasterized = Rc::new(rasterized);
+        self.glyphs.borrow_mut().insert(key, rasterized.clone());
+        (key, rasterized)
     }
 }
 
+#[derive(Clone, Default)]
 pub enum FontParameters {
+    #[default]
     Default,
+    /// Data of a TrueType or OpenType font built into the program
     Custom(&'static [u8]),
+    /// Font loaded at runtime by a `FontRegistry`
+    Loaded(FontHandle),
+}
+
+/// Horizontal position of each line inside of the text box
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum HorizontalAlignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Spaces are stretched so that the words fill the width of the box,
+    /// except on the last line of each paragraph, which is aligned left
+    Justified,
+}
+
+/// Vertical position of the lines inside of the text box
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum VerticalAlignment {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
 }
 
 pub struct TextParameters {
@@ -128,4 +430,46 @@ pub struct TextParameters {
     /// Stays the same if the window can be resized freely
     pub scale: f32,
     pub font: FontParameters,
+    /// Fonts the glyphs missing in `font` are taken from in order,
+    /// the default font is always the last fallback
+    pub fallback_fonts: Vec<FontParameters>,
+    pub horizontal_alignment: HorizontalAlignment,
+    pub vertical_alignment: VerticalAlignment,
+    /// Multiplier of the distance between the baselines of the lines given by the font
+    pub line_spacing: f32,
+    /// Extra space in pixels between characters, negative values move them closer
+    pub letter_spacing: f32,
+    /// Differently styled parts of the text drawn instead of `text` when there are any,
+    /// see `parse_markup` for creating them from markup
+    pub spans: Vec<TextSpan>,
+}
+
+impl Default for TextParameters {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            color: Color::BLACK,
+            scale: 16.0,
+            font: FontParameters::Default,
+            fallback_fonts: vec![],
+            horizontal_alignment: HorizontalAlignment::Left,
+            vertical_alignment: VerticalAlignment::Top,
+            line_spacing: 1.0,
+            letter_spacing: 0.0,
+            spans: vec![],
+        }
+    }
+}
+
+/// Area taken by text laid out inside of a box, relative to the top left corner of the box
+///
+/// The area is made of the lines of the text, so it includes the space above and below
+/// the glyphs given by the font
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct TextMetrics {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub line_count: usize,
 }
@@ -0,0 +1,133 @@
+use super::FontKey;
+use ab_glyph::{Font, FontArc, FontVec};
+use anyhow::Context;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_FONT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Font loaded at runtime, clones of the handle share the font data
+#[derive(Clone)]
+pub struct FontHandle {
+    id: usize,
+    name: String,
+    font: FontArc,
+}
+
+impl FontHandle {
+    fn new(name: &str, font: FontArc) -> Self {
+        Self {
+            id: NEXT_FONT_ID.fetch_add(1, Ordering::Relaxed),
+            name: name.to_string(),
+            font,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns true if the font has a glyph for the character
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.font.glyph_id(c).0 != 0
+    }
+
+    pub(crate) fn key(&self) -> FontKey {
+        FontKey::Loaded(self.id)
+    }
+
+    pub(crate) fn font(&self) -> &FontArc {
+        &self.font
+    }
+}
+
+/// Fonts loaded from files or bytes by name
+#[derive(Default)]
+pub struct FontRegistry {
+    fonts: HashMap<String, FontHandle>,
+}
+
+impl FontRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a TrueType or OpenType font file, replacing the font with the same name
+    pub fn load_file(&mut self, name: &str, path: impl AsRef<Path>) -> anyhow::Result<FontHandle> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)
+            .with_context(|| format!("Failed to read font {}", path.display()))?;
+        self.load_bytes(name, data)
+            .with_context(|| format!("Failed to load font {}", path.display()))
+    }
+
+    /// Loads a font from the contents of a TrueType or OpenType file,
+    /// replacing the font with the same name
+    pub fn load_bytes(&mut self, name: &str, data: Vec<u8>) -> anyhow::Result<FontHandle> {
+        let font = FontVec::try_from_vec(data)
+            .with_context(|| format!("Failed to parse font {}", name))?;
+        let handle = FontHandle::new(name, FontArc::new(font));
+        self.fonts.insert(name.to_string(), handle.clone());
+        Ok(handle)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FontHandle> {
+        self.fonts.get(name)
+    }
+
+    /// Removes the font from the registry, text using its handles still draws with it
+    pub fn remove(&mut self, name: &str) -> Option<FontHandle> {
+        self.fonts.remove(name)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.fonts.keys().map(String::as_str).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::DEFAULT_FONT;
+    use super::*;
+
+    fn error(result: anyhow::Result<FontHandle>) -> String {
+        match result {
+            Ok(handle) => panic!("font {} was loaded", handle.name()),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn invalid_fonts_are_rejected() {
+        let mut registry = FontRegistry::new();
+        assert_eq!(
+            error(registry.load_bytes("broken", vec![0, 1, 2, 3])),
+            "Failed to parse font broken"
+        );
+        assert!(registry.get("broken").is_none());
+
+        assert_eq!(
+            error(registry.load_file("missing", "no/such/font.ttf")),
+            "Failed to read font no/such/font.ttf"
+        );
+        assert!(registry.names().is_empty());
+    }
+
+    #[test]
+    fn font_with_same_name_replaces_old_one() {
+        let mut registry = FontRegistry::new();
+        let old = registry.load_bytes("ui", DEFAULT_FONT.to_vec()).unwrap();
+        let new = registry.load_bytes("ui", DEFAULT_FONT.to_vec()).unwrap();
+        assert_eq!(registry.names(), ["ui"]);
+        assert!(old.key() != new.key());
+        assert!(registry.get("ui").unwrap().key() == new.key());
+        assert!(new.has_glyph('a'));
+
+        // Handles of removed fonts keep working
+        let removed = registry.remove("ui").unwrap();
+        assert!(removed.key() == new.key());
+        assert!(registry.get("ui").is_none());
+        assert!(old.has_glyph('a'));
+    }
+}
@@ -0,0 +1,154 @@
+use super::{GlyphKey, InlineImage, RasterizedGlyph};
+use std::collections::HashMap;
+
+/// Glyphs are packed with a gap, so linear filtering doesn't pick up their neighbours
+const PADDING: u32 = 1;
+const MAX_SIZE: u32 = 4096;
+/// Size of the white block lines are drawn with, only its center pixel is sampled
+const SOLID_SIZE: u32 = 3;
+
+/// Identifies an area of the atlas
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum AtlasKey {
+    Glyph(GlyphKey),
+    /// Id of an `InlineImage`
+    Image(usize),
+    /// White block
+    Solid,
+}
+
+/// RGBA image the glyphs and inline images are packed into row by row,
+/// glyphs are white with their coverage stored in the alpha
+pub(crate) struct GlyphAtlas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    positions: HashMap<AtlasKey, (u32, u32)>,
+    cursor: (u32, u32),
+    row_height: u32,
+    dirty: bool,
+}
+
+impl GlyphAtlas {
+    pub(crate) fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: Self::blank_pixels(width, height),
+            positions: HashMap::new(),
+            cursor: (PADDING, PADDING),
+            row_height: 0,
+            dirty: true,
+        }
+    }
+
+    pub(crate) fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub(crate) fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Returns the position of the top left pixel of the glyph in the atlas,
+    /// packing the glyph if it isn't in the atlas yet, or `None` if it doesn't fit anymore
+    pub(crate) fn glyph_position(
+        &mut self,
+        key: GlyphKey,
+        glyph: &RasterizedGlyph,
+    ) -> Option<(u32, u32)> {
+        self.position(
+            AtlasKey::Glyph(key),
+            glyph.width,
+            glyph.height,
+            |column, row| {
+                let coverage = glyph.coverage[(row * glyph.width + column) as usize];
+                [255, 255, 255, coverage]
+            },
+        )
+    }
+
+    pub(crate) fn image_position(&mut self, image: &InlineImage) -> Option<(u32, u32)> {
+        let pixels = image.image();
+        self.position(
+            AtlasKey::Image(image.id()),
+            pixels.width(),
+            pixels.height(),
+            |column, row| pixels.get_pixel(column, row).0,
+        )
+    }
+
+    /// Returns the position of the center pixel of an opaque white block
+    pub(crate) fn solid_position(&mut self) -> Option<(u32, u32)> {
+        self.position(AtlasKey::Solid, SOLID_SIZE, SOLID_SIZE, |_, _| [255; 4])
+            .map(|(x, y)| (x + SOLID_SIZE / 2, y + SOLID_SIZE / 2))
+    }
+
+    /// Packs an area of the given size if the key isn't in the atlas yet,
+    /// the pixels are given by the function from their position inside of the area
+    fn position(
+        &mut self,
+        key: AtlasKey,
+        width: u32,
+        height: u32,
+        pixel: impl Fn(u32, u32) -> [u8; 4],
+    ) -> Option<(u32, u32)> {
+        if let Some(position) = self.positions.get(&key) {
+            return Some(*position);
+        }
+
+        if self.cursor.0 + width + PADDING > self.width {
+            self.cursor = (PADDING, self.cursor.1 + self.row_height + PADDING);
+            self.row_height = 0;
+        }
+        if self.cursor.0 + width + PADDING > self.width
+            || self.cursor.1 + height + PADDING > self.height
+        {
+            return None;
+        }
+
+        let position = self.cursor;
+        for row in 0..height {
+            for column in 0..width {
+                let index = (((position.1 + row) * self.width + position.0 + column) * 4) as usize;
+                self.pixels[index..index + 4].copy_from_slice(&pixel(column, row));
+            }
+        }
+        self.cursor.0 += width + PADDING;
+        self.row_height = self.row_height.max(height);
+        self.positions.insert(key, position);
+        self.dirty = true;
+
+        Some(position)
+    }
+
+    /// Removes everything from the atlas
+    pub(crate) fn clear(&mut self) {
+        self.pixels = Self::blank_pixels(self.width, self.height);
+        self.positions.clear();
+        self.cursor = (PADDING, PADDING);
+        self.row_height = 0;
+        self.dirty = true;
+    }
+
+    /// Doubles the size of the atlas and removes everything from it,
+    /// returns false if the atlas is already as large as it can be
+    pub(crate) fn grow(&mut self) -> bool {
+        if self.width >= MAX_SIZE && self.height >= MAX_SIZE {
+            return false;
+        }
+        self.width = (self.width * 2).min(MAX_SIZE);
+        self.height = (self.height * 2).min(MAX_SIZE);
+        self.clear();
+        true
+    }
+
+    /// Returns true if anything was packed or removed since the last call
+    pub(crate) fn take_dirty(&mut self) -> bool {
+        std::mem::replace(&mut self.dirty, false)
+    }
+
+    fn blank_pixels(width: u32, height: u32) -> Vec<u8> {
+        [255, 255, 255, 0].repeat(width as usize * height as usize)
+    }
+}
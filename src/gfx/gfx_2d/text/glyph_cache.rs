@@ -0,0 +1,170 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// Identifies a font in the caches
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum FontKey {
+    /// Address of font data built into the program, which is the same for every use of the font
+    Static(usize),
+    /// Id of a `FontHandle`
+    Loaded(usize),
+}
+
+/// Identifies the rasterized image of a glyph of a font at a size
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct GlyphKey {
+    pub(crate) font: FontKey,
+    /// Bits of the horizontal and vertical scale in pixels
+    pub(crate) scale: (u32, u32),
+    pub(crate) id: u16,
+    /// Steps between pixels the origin of the glyph is moved to the right by
+    pub(crate) subpixel: u8,
+}
+
+/// Coverage of the pixels of a glyph from 0 to 255, one byte per pixel
+#[derive(Debug)]
+pub(crate) struct RasterizedGlyph {
+    /// Position of the top left pixel relative to the origin of the glyph on the baseline
+    pub(crate) left: i32,
+    pub(crate) top: i32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) coverage: Vec<u8>,
+}
+
+impl RasterizedGlyph {
+    /// Calls the function with the position relative to the glyph origin
+    /// and the coverage of every pixel the glyph touches
+    pub(crate) fn for_each_pixel(&self, mut function: impl FnMut(i32, i32, u8)) {
+        for (index, &coverage) in self.coverage.iter().enumerate() {
+            if coverage > 0 {
+                let x = self.left + (index as u32 % self.width) as i32;
+                let y = self.top + (index as u32 / self.width) as i32;
+                function(x, y, coverage);
+            }
+        }
+    }
+}
+
+/// Map which evicts the least recently used entries once it holds `capacity` of them
+pub(crate) struct LruCache<K, V> {
+    entries: HashMap<K, (V, u64)>,
+    /// Keys by the time they were used last, the first one is evicted next
+    usage: BTreeMap<u64, K>,
+    capacity: usize,
+    time: u64,
+}
+
+impl<K: Eq + Hash + Clone, V> LruCache<K, V> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            usage: BTreeMap::new(),
+            capacity: capacity.max(1),
+            time: 0,
+        }
+    }
+
+    pub(crate) fn get(&mut self, key: &K) -> Option<&V> {
+        self.time += 1;
+        let time = self.time;
+        let (value, used) = self.entries.get_mut(key)?;
+        if let Some(key) = self.usage.remove(used) {
+            self.usage.insert(time, key);
+        }
+        *used = time;
+        Some(&*value)
+    }
+
+    pub(crate) fn insert(&mut self, key: K, value: V) {
+        match self.entries.get(&key) {
+            Some((_, used)) => {
+                self.usage.remove(used);
+            }
+            None if self.entries.len() >= self.capacity => self.evict_oldest(),
+            None => {}
+        }
+        self.time += 1;
+        self.usage.insert(self.time, key.clone());
+        self.entries.insert(key, (value, self.time));
+    }
+
+    fn evict_oldest(&mut self) {
+        if let Some((_, oldest)) = self.usage.pop_first() {
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn least_recently_inserted_entry_is_evicted() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("c", 3);
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.get(&"b"), Some(&2));
+        assert_eq!(cache.get(&"c"), Some(&3));
+    }
+
+    #[test]
+    fn reading_entry_keeps_it_in_cache() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        cache.insert("c", 3);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.get(&"c"), Some(&3));
+    }
+
+    #[test]
+    fn replacing_entry_does_not_evict_others() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        cache.insert("a", 10);
+        assert_eq!(cache.entries.len(), 2);
+        assert_eq!(cache.usage.len(), 2);
+        assert_eq!(cache.get(&"a"), Some(&10));
+        assert_eq!(cache.get(&"b"), Some(&2));
+
+        // The replaced entry counts as used, so the other one goes first
+        cache.insert("a", 11);
+        cache.insert("c", 3);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(&11));
+    }
+
+    #[test]
+    fn cache_holds_at_least_one_entry() {
+        let mut cache = LruCache::new(0);
+        cache.insert(1, "one");
+        assert_eq!(cache.get(&1), Some(&"one"));
+        cache.insert(2, "two");
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&2), Some(&"two"));
+    }
+
+    #[test]
+    fn many_entries_are_evicted_in_order_of_use() {
+        let mut cache = LruCache::new(100);
+        for key in 0..100 {
+            cache.insert(key, key);
+        }
+        for key in (0..100).step_by(2) {
+            cache.get(&key);
+        }
+        for key in 100..150 {
+            cache.insert(key, key);
+        }
+        assert!((1..100).step_by(2).all(|key| cache.get(&key).is_none()));
+        assert!((0..100).step_by(2).all(|key| cache.get(&key).is_some()));
+        assert_eq!(cache.entries.len(), cache.usage.len());
+    }
+}
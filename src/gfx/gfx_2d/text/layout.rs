@@ -0,0 +1,916 @@
+use super::{FontKey, HorizontalAlignment, TextMetrics, TextParameters, VerticalAlignment};
+use ab_glyph::*;
+
+/// Fonts of a span scaled to its size, in the order glyphs are looked up in
+pub(super) struct FontChain<'a> {
+    pub(super) fonts: Vec<PxScaleFont<&'a FontArc>>,
+    /// Keys of the fonts, glyphs of different spans are kerned when their fonts are the same
+    pub(super) keys: Vec<FontKey>,
+    /// Extra space in pixels between characters
+    pub(super) letter_spacing: f32,
+}
+
+/// Character a run follows on its line, the first character of the run is spaced
+/// and kerned against it
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Preceding {
+    /// The run starts the line or follows an image
+    Nothing,
+    /// The run follows a character it isn't kerned with
+    Character,
+    /// The run continues a word ending with the glyph of the font
+    Glyph(FontKey, PxScale, GlyphId),
+}
+
+impl Preceding {
+    /// Keeps the spacing but not the kerning, for the boundaries between words and whitespace
+    fn unkerned(self) -> Self {
+        match self {
+            Preceding::Glyph(..) => Preceding::Character,
+            preceding => preceding,
+        }
+    }
+}
+
+impl<'a> FontChain<'a> {
+    /// Font the line height and the baseline are taken from
+    fn primary(&self) -> &PxScaleFont<&'a FontArc> {
+        &self.fonts[0]
+    }
+
+    /// Returns the index of the first font with a glyph for the character and the glyph,
+    /// the missing glyph of the primary font if none of the fonts has it
+    fn glyph(&self, c: char) -> (usize, GlyphId) {
+        self.fonts
+            .iter()
+            .enumerate()
+            .map(|(index, font)| (index, font.glyph_id(c)))
+            .find(|(_, id)| id.0 != 0)
+            .unwrap_or((0, GlyphId(0)))
+    }
+
+    /// Calls the function with the byte index, the character, the index of the font, the glyph
+    /// and its offset from the start of the text for each character,
+    /// returns the advance of the text and the character the next run follows
+    ///
+    /// Letter spacing is added before each character following another one
+    /// and pairs of glyphs from the same font are kerned
+    fn walk(
+        &self,
+        text: &str,
+        preceding: Preceding,
+        mut function: impl FnMut(usize, char, usize, GlyphId, f32),
+    ) -> (f32, Preceding) {
+        let mut caret = 0.0;
+        let mut preceding = preceding;
+        for (index, c) in text.char_indices().filter(|(_, c)| !c.is_control()) {
+            let (font, id) = self.glyph(c);
+            let scaled = &self.fonts[font];
+            match preceding {
+                Preceding::Nothing => {}
+                Preceding::Character => caret += self.letter_spacing,
+                Preceding::Glyph(key, scale, previous) => {
+                    caret += self.letter_spacing;
+                    if key == self.keys[font] && scale == scaled.scale {
+                        caret += scaled.kern(previous, id);
+                    }
+                }
+            }
+            function(index, c, font, id, caret);
+            caret += scaled.h_advance(id);
+            preceding = Preceding::Glyph(self.keys[font], scaled.scale, id);
+        }
+        (caret, preceding)
+    }
+
+    fn advance(&self, text: &str, preceding: Preceding) -> f32 {
+        self.walk(text, preceding, |_, _, _, _, _| {}).0
+    }
+
+    /// Returns the length in bytes of the start of the text fitting into the width
+    fn fitting_length(&self, text: &str, preceding: Preceding, width: f32) -> usize {
+        let mut length = text.len();
+        self.walk(text, preceding, |index, _, font, id, x| {
+            if length == text.len() && x + self.fonts[font].h_advance(id) > width {
+                length = index;
+            }
+        });
+        length
+    }
+}
+
+/// Span of the text with its style resolved against the text parameters
+pub(super) enum StyledSpan<'a> {
+    Text {
+        text: &'a str,
+        fonts: FontChain<'a>,
+        underline: bool,
+        strikethrough: bool,
+    },
+    Image {
+        width: f32,
+        height: f32,
+    },
+}
+
+pub(super) struct LaidOutGlyph {
+    pub(super) span: usize,
+    /// Index of the font in the chain of the span
+    pub(super) font: usize,
+    pub(super) glyph: Glyph,
+}
+
+pub(super) struct LaidOutImage {
+    pub(super) span: usize,
+    /// Top left corner of the image
+    pub(super) position: Point,
+}
+
+/// Underline or strikethrough
+pub(super) struct Decoration {
+    pub(super) span: usize,
+    pub(super) position: Point,
+    pub(super) width: f32,
+    pub(super) thickness: f32,
+}
+
+/// Contents of the text positioned inside of the text box
+#[derive(Default)]
+pub(super) struct TextLayout {
+    pub(super) glyphs: Vec<LaidOutGlyph>,
+    pub(super) images: Vec<LaidOutImage>,
+    pub(super) decorations: Vec<Decoration>,
+    pub(super) metrics: TextMetrics,
+}
+
+/// Part of a word or the whitespace between words, images are words of their own
+#[derive(Copy, Clone)]
+struct Piece<'a> {
+    span: usize,
+    text: &'a str,
+    whitespace: bool,
+}
+
+/// Piece placed on a line
+struct Run<'a> {
+    span: usize,
+    text: &'a str,
+    x: f32,
+    width: f32,
+    whitespace: bool,
+    preceding: Preceding,
+}
+
+/// Line of runs without the whitespace at its end
+struct Line<'a> {
+    runs: Vec<Run<'a>>,
+    width: f32,
+    /// The last line of a paragraph is not stretched when the text is justified
+    ends_paragraph: bool,
+}
+
+impl<'a> Line<'a> {
+    fn new() -> Self {
+        Self {
+            runs: vec![],
+            width: 0.0,
+            ends_paragraph: false,
+        }
+    }
+
+    fn has_word(&self) -> bool {
+        self.runs.iter().any(|run| !run.whitespace)
+    }
+
+    fn finish(mut self, ends_paragraph: bool) -> Self {
+        while self.runs.last().is_some_and(|run| run.whitespace) {
+            self.runs.pop();
+        }
+        self.ends_paragraph = ends_paragraph;
+        self
+    }
+}
+
+/// Height above and below the baseline and the gap to the next line
+#[derive(Copy, Clone)]
+struct LineMetrics {
+    ascent: f32,
+    descent: f32,
+    line_gap: f32,
+}
+
+/// Breaks the spans into lines fitting into the box and positions them inside of it
+///
+/// Lines are as high as their tallest span, empty ones take the height of the `base` fonts
+pub(super) fn layout(
+    spans: &[StyledSpan],
+    base: &FontChain,
+    text: &TextParameters,
+    width: u32,
+    height: u32,
+) -> TextLayout {
+    let max_width = width as f32;
+    let paragraphs = split_paragraphs(spans);
+    if paragraphs.len() == 1 && paragraphs[0].is_empty() {
+        return TextLayout::default();
+    }
+    let lines: Vec<Line> = paragraphs
+        .iter()
+        .flat_map(|pieces| wrap_paragraph(spans, pieces, max_width))
+        .collect();
+
+    let line_metrics: Vec<LineMetrics> = lines
+        .iter()
+        .map(|line| measure_line(spans, base, line))
+        .collect();
+    let mut baselines = Vec::with_capacity(lines.len());
+    let mut baseline = line_metrics[0].ascent;
+    for (index, metrics) in line_metrics.iter().enumerate() {
+        if index > 0 {
+            let previous = line_metrics[index - 1];
+            let gap = previous.line_gap.max(metrics.line_gap);
+            baseline += (previous.descent + gap + metrics.ascent) * text.line_spacing;
+        }
+        baselines.push(baseline);
+    }
+    let text_height = baseline + line_metrics[lines.len() - 1].descent;
+    let top = match text.vertical_alignment {
+        VerticalAlignment::Top => 0.0,
+        VerticalAlignment::Middle => (height as f32 - text_height) / 2.0,
+        VerticalAlignment::Bottom => height as f32 - text_height,
+    };
+
+    let mut layout = TextLayout::default();
+    let mut left = f32::INFINITY;
+    let mut right = f32::NEG_INFINITY;
+    for (line, baseline) in lines.iter().zip(baselines) {
+        let baseline = top + baseline;
+        let free_width = max_width - line.width;
+        let gaps = line
+            .runs
+            .iter()
+            .skip_while(|run| run.whitespace)
+            .filter(|run| run.whitespace)
+            .count();
+        let (offset, gap_width) = match text.horizontal_alignment {
+            HorizontalAlignment::Left => (0.0, 0.0),
+            HorizontalAlignment::Center => (free_width / 2.0, 0.0),
+            HorizontalAlignment::Right => (free_width, 0.0),
+            HorizontalAlignment::Justified => {
+                if !line.ends_paragraph && gaps > 0 && free_width > 0.0 {
+                    (0.0, free_width / gaps as f32)
+                } else {
+                    (0.0, 0.0)
+                }
+            }
+        };
+
+        let mut stretch = 0.0;
+        let mut after_word = false;
+        for run in &line.runs {
+            let start = offset + run.x + stretch;
+            let mut run_width = run.width;
+            if run.whitespace && after_word {
+                stretch += gap_width;
+                run_width += gap_width;
+            }
+            after_word |= !run.whitespace;
+
+            match &spans[run.span] {
+                StyledSpan::Text {
+                    fonts,
+                    underline,
+                    strikethrough,
+                    ..
+                } => {
+                    fonts.walk(run.text, run.preceding, |_, c, font, id, x| {
+                        if !c.is_whitespace() {
+                            let position = point(start + x, baseline);
+                            layout.glyphs.push(LaidOutGlyph {
+                                span: run.span,
+                                font,
+                                glyph: id
+                                    .with_scale_and_position(fonts.fonts[font].scale(), position),
+                            });
+                        }
+                    });
+
+                    // Fonts don't give the position of the lines, so it is derived from the size
+                    let size = fonts.primary().scale().y;
+                    let thickness = (size * 0.06).max(1.0);
+                    let mut decorate = |y: f32| {
+                        layout.decorations.push(Decoration {
+                            span: run.span,
+                            position: point(start, y - thickness / 2.0),
+                            width: run_width,
+                            thickness,
+                        })
+                    };
+                    if *underline {
+                        decorate(baseline + size * 0.1);
+                    }
+                    if *strikethrough {
+                        decorate(baseline - size * 0.3);
+                    }
+                }
+                StyledSpan::Image { height, .. } => layout.images.push(LaidOutImage {
+                    span: run.span,
+                    position: point(start, baseline - height),
+                }),
+            }
+        }
+
+        left = left.min(offset);
+        right = right.max(offset + line.width + stretch);
+    }
+
+    layout.metrics = TextMetrics {
+        x: left,
+        y: top,
+        width: right - left,
+        height: text_height,
+        line_count: lines.len(),
+    };
+    layout
+}
+
+/// Splits the spans at newlines into paragraphs of words and whitespace
+fn split_paragraphs<'a>(spans: &[StyledSpan<'a>]) -> Vec<Vec<Piece<'a>>> {
+    let mut paragraphs = vec![vec![]];
+    for (index, span) in spans.iter().enumerate() {
+        match span {
+            StyledSpan::Text { text, .. } => {
+                for (line, part) in text.split('\n').enumerate() {
+                    if line > 0 {
+                        paragraphs.push(vec![]);
+                    }
+                    let paragraph = paragraphs.last_mut().unwrap();
+                    for (text, whitespace) in split_runs(part) {
+                        paragraph.push(Piece {
+                            span: index,
+                            text,
+                            whitespace,
+                        });
+                    }
+                }
+            }
+            StyledSpan::Image { .. } => paragraphs.last_mut().unwrap().push(Piece {
+                span: index,
+                text: "",
+                whitespace: false,
+            }),
+        }
+    }
+    paragraphs
+}
+
+/// Breaks a paragraph into lines no wider than `max_width` between words,
+/// words wider than a whole line are broken between characters
+fn wrap_paragraph<'a>(spans: &[StyledSpan], pieces: &[Piece<'a>], max_width: f32) -> Vec<Line<'a>> {
+    let is_image = |piece: &Piece| matches!(spans[piece.span], StyledSpan::Image { .. });
+    // Returns the width of the piece and the character the next piece follows
+    let piece_width = |piece: &Piece, preceding: Preceding| match &spans[piece.span] {
+        StyledSpan::Text { fonts, .. } => fonts.walk(piece.text, preceding, |_, _, _, _, _| {}),
+        StyledSpan::Image { width, .. } => (*width, Preceding::Nothing),
+    };
+
+    let mut lines = vec![];
+    let mut line = Line::new();
+    let mut caret = 0.0;
+    let mut preceding = Preceding::Nothing;
+    let mut index = 0;
+    while index < pieces.len() {
+        let piece = pieces[index];
+        if piece.whitespace {
+            let (width, next) = piece_width(&piece, preceding.unkerned());
+            line.runs.push(Run {
+                span: piece.span,
+                text: piece.text,
+                x: caret,
+                width,
+                whitespace: true,
+                preceding: preceding.unkerned(),
+            });
+            caret += width;
+            preceding = next.unkerned();
+            index += 1;
+            continue;
+        }
+
+        // A word is made of the pieces of text up to the next whitespace
+        let end = if is_image(&piece) {
+            index + 1
+        } else {
+            pieces[index..]
+                .iter()
+                .position(|piece| piece.whitespace || is_image(piece))
+                .map_or(pieces.len(), |length| index + length)
+        };
+        let word = &pieces[index..end];
+        let mut word_width = 0.0;
+        let mut word_preceding = preceding;
+        for piece in word {
+            let (width, next) = piece_width(piece, word_preceding);
+            word_width += width;
+            word_preceding = next;
+        }
+        if caret + word_width > max_width && line.has_word() {
+            lines.push(std::mem::replace(&mut line, Line::new()).finish(false));
+            caret = 0.0;
+            preceding = Preceding::Nothing;
+        }
+
+        for piece in word {
+            let fonts = match &spans[piece.span] {
+                StyledSpan::Text { fonts, .. } => fonts,
+                StyledSpan::Image { width, .. } => {
+                    if caret + width > max_width && line.has_word() {
+                        lines.push(std::mem::replace(&mut line, Line::new()).finish(false));
+                        caret = 0.0;
+                    }
+                    line.runs.push(Run {
+                        span: piece.span,
+                        text: "",
+                        x: caret,
+                        width: *width,
+                        whitespace: false,
+                        preceding: Preceding::Nothing,
+                    });
+                    caret += width;
+                    line.width = caret;
+                    preceding = Preceding::Nothing;
+                    continue;
+                }
+            };
+
+            let mut rest = piece.text;
+            while !rest.is_empty() {
+                let width = fonts.advance(rest, preceding);
+                let end = if caret + width <= max_width {
+                    rest.len()
+                } else {
+                    match fonts.fitting_length(rest, preceding, max_width - caret) {
+                        0 if line.has_word() => {
+                            lines.push(std::mem::replace(&mut line, Line::new()).finish(false));
+                            caret = 0.0;
+                            preceding = Preceding::Nothing;
+                            continue;
+                        }
+                        // At least one character is put on each line, so the loop always ends
+                        0 => rest.chars().next().map_or(rest.len(), char::len_utf8),
+                        end => end,
+                    }
+                };
+
+                let (head, tail) = rest.split_at(end);
+                let (width, next) = fonts.walk(head, preceding, |_, _, _, _, _| {});
+                line.runs.push(Run {
+                    span: piece.span,
+                    text: head,
+                    x: caret,
+                    width,
+                    whitespace: false,
+                    preceding,
+                });
+                caret += width;
+                line.width = caret;
+                preceding = next;
+                rest = tail;
+                if !rest.is_empty() {
+                    lines.push(std::mem::replace(&mut line, Line::new()).finish(false));
+                    caret = 0.0;
+                    preceding = Preceding::Nothing;
+                }
+            }
+        }
+        index = end;
+    }
+
+    lines.push(line.finish(true));
+    lines
+}
+
+fn measure_line(spans: &[StyledSpan], base: &FontChain, line: &Line) -> LineMetrics {
+    let font_metrics = |fonts: &FontChain| {
+        let font = fonts.primary();
+        LineMetrics {
+            ascent: font.ascent(),
+            descent: -font.descent(),
+            line_gap: font.line_gap(),
+        }
+    };
+    if line.runs.is_empty() {
+        return font_metrics(base);
+    }
+
+    line.runs
+        .iter()
+        .map(|run| match &spans[run.span] {
+            StyledSpan::Text { fonts, .. } => font_metrics(fonts),
+            StyledSpan::Image { height, .. } => LineMetrics {
+                ascent: *height,
+                descent: 0.0,
+                line_gap: 0.0,
+            },
+        })
+        .reduce(|a, b| LineMetrics {
+            ascent: a.ascent.max(b.ascent),
+            descent: a.descent.max(b.descent),
+            line_gap: a.line_gap.max(b.line_gap),
+        })
+        .unwrap()
+}
+
+/// Splits the text into runs of word and whitespace characters,
+/// no-break spaces are part of the words
+fn split_runs(text: &str) -> Vec<(&str, bool)> {
+    let is_break = |c: char| c.is_whitespace() && c != '\u{a0}';
+
+    let mut runs = vec![];
+    let mut start = 0;
+    let mut current = None;
+    for (index, c) in text.char_indices() {
+        let whitespace = is_break(c);
+        if let Some(previous) = current {
+            if previous != whitespace {
+                runs.push((&text[start..index], previous));
+                start = index;
+            }
+        }
+        current = Some(whitespace);
+    }
+    if let Some(whitespace) = current {
+        runs.push((&text[start..], whitespace));
+    }
+
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::DEFAULT_FONT;
+    use super::*;
+
+    fn font() -> FontArc {
+        FontArc::try_from_slice(DEFAULT_FONT).unwrap()
+    }
+
+    fn chain(font: &FontArc, letter_spacing: f32) -> FontChain<'_> {
+        FontChain {
+            fonts: vec![font.as_scaled(PxScale::from(20.0))],
+            keys: vec![FontKey::Static(0)],
+            letter_spacing,
+        }
+    }
+
+    fn span<'a>(text: &'a str, fonts: FontChain<'a>) -> StyledSpan<'a> {
+        StyledSpan::Text {
+            text,
+            fonts,
+            underline: false,
+            strikethrough: false,
+        }
+    }
+
+    /// Returns the text of each line the spans are wrapped into
+    fn wrap(spans: &[StyledSpan], max_width: f32) -> Vec<String> {
+        split_paragraphs(spans)
+            .iter()
+            .flat_map(|pieces| wrap_paragraph(spans, pieces, max_width))
+            .map(|line| line.runs.iter().map(|run| run.text).collect())
+            .collect()
+    }
+
+    fn parameters(horizontal_alignment: HorizontalAlignment) -> TextParameters {
+        TextParameters {
+            horizontal_alignment,
+            ..Default::default()
+        }
+    }
+
+    fn glyph_positions(layout: &TextLayout) -> Vec<Point> {
+        layout.glyphs.iter().map(|g| g.glyph.position).collect()
+    }
+
+    #[test]
+    fn text_is_split_into_words_and_whitespace() {
+        assert_eq!(
+            split_runs("ab  c\u{a0}d\t"),
+            [
+                ("ab", false),
+                ("  ", true),
+                ("c\u{a0}d", false),
+                ("\t", true)
+            ]
+        );
+        assert_eq!(split_runs(" a"), [(" ", true), ("a", false)]);
+        assert!(split_runs("").is_empty());
+    }
+
+    #[test]
+    fn paragraphs_are_wrapped_between_words() {
+        let font = font();
+        let fonts = chain(&font, 0.0);
+        let max_width = (fonts.advance("aaa bbb", Preceding::Nothing)
+            + fonts.advance("aaa bbb ccc", Preceding::Nothing))
+            / 2.0;
+        let spans = [span("aaa bbb ccc\n\nddd", chain(&font, 0.0))];
+        assert_eq!(wrap(&spans, max_width), ["aaa bbb", "ccc", "", "ddd"]);
+    }
+
+    #[test]
+    fn words_wider_than_line_are_broken_between_characters() {
+        let font = font();
+        let fonts = chain(&font, 0.0);
+        let max_width = fonts.advance("aaa", Preceding::Nothing) + 1.0;
+        let spans = [span("b aaaaaaa", chain(&font, 0.0))];
+        assert_eq!(wrap(&spans, max_width), ["b", "aaa", "aaa", "a"]);
+    }
+
+    #[test]
+    fn lines_are_aligned_inside_of_box() {
+        let font = font();
+        let base = chain(&font, 0.0);
+        let spans = [span("aaa", chain(&font, 0.0))];
+        let text_width = base.advance("aaa", Preceding::Nothing);
+
+        let left = layout(
+            &spans,
+            &base,
+            &parameters(HorizontalAlignment::Left),
+            200,
+            100,
+        );
+        assert_eq!(left.metrics.x, 0.0);
+        assert!((left.metrics.width - text_width).abs() < 1e-3);
+        assert_eq!(left.metrics.line_count, 1);
+
+        let right = layout(
+            &spans,
+            &base,
+            &parameters(HorizontalAlignment::Right),
+            200,
+            100,
+        );
+        assert!((right.metrics.x + right.metrics.width - 200.0).abs() < 1e-3);
+
+        let center = layout(
+            &spans,
+            &base,
+            &parameters(HorizontalAlignment::Center),
+            200,
+            100,
+        );
+        assert!((center.metrics.x - (200.0 - text_width) / 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn lines_are_aligned_vertically_and_spaced() {
+        let font = font();
+        let base = chain(&font, 0.0);
+        let scaled = base.primary();
+        let line_distance = scaled.ascent() - scaled.descent() + scaled.line_gap();
+        let spans = [span("a\nb", chain(&font, 0.0))];
+        let lay_out = |vertical_alignment, line_spacing| {
+            let text = TextParameters {
+                vertical_alignment,
+                line_spacing,
+                ..Default::default()
+            };
+            layout(&spans, &base, &text, 200, 300)
+        };
+        let baselines = |layout: &TextLayout| -> Vec<f32> {
+            glyph_positions(layout).iter().map(|p| p.y).collect()
+        };
+
+        let top = lay_out(VerticalAlignment::Top, 1.0);
+        let text_height = line_distance + scaled.ascent() - scaled.descent();
+        assert_eq!(top.metrics.y, 0.0);
+        assert!((top.metrics.height - text_height).abs() < 1e-3);
+        let [first, second] = baselines(&top)[..] else {
+            panic!("two glyphs are laid out")
+        };
+        assert!((first - scaled.ascent()).abs() < 1e-3);
+        assert!((second - first - line_distance).abs() < 1e-3);
+
+        let middle = lay_out(VerticalAlignment::Middle, 1.0);
+        assert!((middle.metrics.y - (300.0 - text_height) / 2.0).abs() < 1e-3);
+        assert!((baselines(&middle)[0] - first - middle.metrics.y).abs() < 1e-3);
+
+        let bottom = lay_out(VerticalAlignment::Bottom, 1.0);
+        assert!((bottom.metrics.y + bottom.metrics.height - 300.0).abs() < 1e-3);
+
+        // The spacing stretches the distance between baselines, not the first line
+        let spaced = lay_out(VerticalAlignment::Top, 2.0);
+        let spaced_baselines = baselines(&spaced);
+        assert!((spaced_baselines[0] - first).abs() < 1e-3);
+        assert!((spaced_baselines[1] - spaced_baselines[0] - 2.0 * line_distance).abs() < 1e-3);
+        assert!((spaced.metrics.height - text_height - line_distance).abs() < 1e-3);
+    }
+
+    #[test]
+    fn justified_lines_are_stretched_except_last_one_of_paragraph() {
+        let font = font();
+        let base = chain(&font, 0.0);
+        let max_width = (base.advance("aaa bbb", Preceding::Nothing)
+            + base.advance("aaa bbb ccc", Preceding::Nothing))
+            / 2.0;
+        let justified = parameters(HorizontalAlignment::Justified);
+
+        let spans = [span("aaa bbb ccc", chain(&font, 0.0))];
+        let wrapped = layout(&spans, &base, &justified, max_width as u32, 100);
+        assert_eq!(wrapped.metrics.line_count, 2);
+        assert!((wrapped.metrics.width - max_width.floor()).abs() < 1e-3);
+        // The first glyph of the last word of the first line is pushed to the right
+        let last_word = &wrapped.glyphs[3];
+        let left = layout(
+            &spans,
+            &base,
+            &parameters(HorizontalAlignment::Left),
+            1000,
+            100,
+        );
+        assert!(last_word.glyph.position.x > left.glyphs[3].glyph.position.x);
+
+        let spans = [span("aaa bbb", chain(&font, 0.0))];
+        let single = layout(&spans, &base, &justified, 1000, 100);
+        assert!(single.metrics.width < 500.0);
+    }
+
+    #[test]
+    fn letter_spacing_is_only_added_between_characters() {
+        let font = font();
+        let plain = chain(&font, 0.0);
+        let spaced = chain(&font, 3.0);
+        assert_eq!(
+            spaced.advance("a", Preceding::Nothing),
+            plain.advance("a", Preceding::Nothing)
+        );
+        let difference =
+            spaced.advance("abc", Preceding::Nothing) - plain.advance("abc", Preceding::Nothing);
+        assert!((difference - 6.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn spans_of_same_font_are_spaced_and_kerned_like_one_span() {
+        let font = font();
+        let base = chain(&font, 2.0);
+        let parameters = parameters(HorizontalAlignment::Right);
+
+        let whole = [span("AVAV To", chain(&font, 2.0))];
+        let split = [
+            span("A", chain(&font, 2.0)),
+            span("VA", chain(&font, 2.0)),
+            span("V T", chain(&font, 2.0)),
+            span("o", chain(&font, 2.0)),
+        ];
+        let whole = layout(&whole, &base, &parameters, 300, 100);
+        let split = layout(&split, &base, &parameters, 300, 100);
+        assert_eq!(glyph_positions(&whole), glyph_positions(&split));
+        assert_eq!(whole.metrics, split.metrics);
+    }
+
+    #[test]
+    fn broken_word_starts_next_line_without_spacing() {
+        let font = font();
+        let base = chain(&font, 2.0);
+        let max_width = base.advance("aaa", Preceding::Nothing) + 1.0;
+        let spans = [span("aaaaaa", chain(&font, 2.0))];
+        let layout = layout(
+            &spans,
+            &base,
+            &parameters(HorizontalAlignment::Left),
+            max_width as u32,
+            100,
+        );
+        assert_eq!(layout.metrics.line_count, 2);
+        assert_eq!(layout.glyphs[0].glyph.position.x, 0.0);
+        assert_eq!(layout.glyphs[3].glyph.position.x, 0.0);
+    }
+
+    #[test]
+    fn images_are_words_of_their_own() {
+        let font = font();
+        let base = chain(&font, 0.0);
+        let spans = [
+            span("aaa", chain(&font, 0.0)),
+            StyledSpan::Image {
+                width: 30.0,
+                height: 40.0,
+            },
+        ];
+        let text_width = base.advance("aaa", Preceding::Nothing);
+        let layout = layout(
+            &spans,
+            &base,
+            &parameters(HorizontalAlignment::Left),
+            200,
+            100,
+        );
+        assert_eq!(layout.images.len(), 1);
+        assert!((layout.images[0].position.x - text_width).abs() < 1e-3);
+        assert!((layout.metrics.width - (text_width + 30.0)).abs() < 1e-3);
+        assert!(layout.metrics.height >= 40.0);
+    }
+
+    /// Font without the glyph of one character, for chains falling back to the next font
+    struct Missing(FontArc, char);
+
+    impl Font for Missing {
+        fn units_per_em(&self) -> Option<f32> {
+            self.0.units_per_em()
+        }
+
+        fn ascent_unscaled(&self) -> f32 {
+            self.0.ascent_unscaled()
+        }
+
+        fn descent_unscaled(&self) -> f32 {
+            self.0.descent_unscaled()
+        }
+
+        fn line_gap_unscaled(&self) -> f32 {
+            self.0.line_gap_unscaled()
+        }
+
+        fn glyph_id(&self, c: char) -> GlyphId {
+            if c == self.1 {
+                GlyphId(0)
+            } else {
+                self.0.glyph_id(c)
+            }
+        }
+
+        fn h_advance_unscaled(&self, id: GlyphId) -> f32 {
+            self.0.h_advance_unscaled(id)
+        }
+
+        fn h_side_bearing_unscaled(&self, id: GlyphId) -> f32 {
+            self.0.h_side_bearing_unscaled(id)
+        }
+
+        fn v_advance_unscaled(&self, id: GlyphId) -> f32 {
+            self.0.v_advance_unscaled(id)
+        }
+
+        fn v_side_bearing_unscaled(&self, id: GlyphId) -> f32 {
+            self.0.v_side_bearing_unscaled(id)
+        }
+
+        fn kern_unscaled(&self, first: GlyphId, second: GlyphId) -> f32 {
+            self.0.kern_unscaled(first, second)
+        }
+
+        fn outline(&self, id: GlyphId) -> Option<Outline> {
+            self.0.outline(id)
+        }
+
+        fn glyph_count(&self) -> usize {
+            self.0.glyph_count()
+        }
+
+        fn codepoint_ids(&self) -> CodepointIdIter<'_> {
+            self.0.codepoint_ids()
+        }
+
+        fn glyph_raster_image2(&self, id: GlyphId, size: u16) -> Option<v2::GlyphImage<'_>> {
+            self.0.glyph_raster_image2(id, size)
+        }
+    }
+
+    #[test]
+    fn missing_characters_are_taken_from_next_font() {
+        let font = font();
+        let without_b = FontArc::new(Missing(font.clone(), 'b'));
+        let fonts = FontChain {
+            fonts: vec![
+                without_b.as_scaled(PxScale::from(20.0)),
+                font.as_scaled(PxScale::from(40.0)),
+            ],
+            keys: vec![FontKey::Static(1), FontKey::Static(0)],
+            letter_spacing: 0.0,
+        };
+        assert_eq!(fonts.glyph('a'), (0, font.glyph_id('a')));
+        assert_eq!(fonts.glyph('b'), (1, font.glyph_id('b')));
+        // Characters none of the fonts have are drawn with the missing glyph of the first one
+        assert_eq!(font.glyph_id('\u{e000}'), GlyphId(0));
+        assert_eq!(fonts.glyph('\u{e000}'), (0, GlyphId(0)));
+
+        let spans = [span("ab", fonts)];
+        let base = chain(&font, 0.0);
+        let layout = layout(
+            &spans,
+            &base,
+            &parameters(HorizontalAlignment::Left),
+            200,
+            100,
+        );
+        let fallback = &layout.glyphs[1];
+        assert_eq!((layout.glyphs[0].font, fallback.font), (0, 1));
+        assert_eq!(fallback.glyph.scale, PxScale::from(40.0));
+        let a_advance = without_b
+            .as_scaled(PxScale::from(20.0))
+            .h_advance(font.glyph_id('a'));
+        assert!((fallback.glyph.position.x - a_advance).abs() < 1e-3);
+    }
+}
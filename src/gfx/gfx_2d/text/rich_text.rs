@@ -0,0 +1,360 @@
+use super::{FontParameters, FontRegistry};
+use crate::gfx::gfx_2d::Sprite;
+use crate::gfx::texture::Color;
+use anyhow::{anyhow, bail};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_IMAGE_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Image drawn inside of text like a glyph, standing on the baseline,
+/// clones of it share the pixels
+#[derive(Clone)]
+pub struct InlineImage {
+    id: usize,
+    image: Rc<image::RgbaImage>,
+}
+
+impl InlineImage {
+    /// Creates the image from pixels with straight alpha
+    pub fn new(image: image::RgbaImage) -> Self {
+        Self {
+            id: NEXT_IMAGE_ID.fetch_add(1, Ordering::Relaxed),
+            image: Rc::new(image),
+        }
+    }
+
+    /// Copies the pixels of the sprite as straight colors like those of `Sprite::from_image`,
+    /// semi-transparent pixels drawn with `put_pixel` are premultiplied and come out darker
+    pub fn from_sprite(sprite: &Sprite) -> Self {
+        Self::new(sprite.image().to_rgba8())
+    }
+
+    pub fn width(&self) -> u32 {
+        self.image.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.image.height()
+    }
+
+    pub(crate) fn id(&self) -> usize {
+        self.id
+    }
+
+    pub(crate) fn image(&self) -> &image::RgbaImage {
+        &self.image
+    }
+}
+
+/// Style of a span, parts that aren't set are taken from the `TextParameters`
+#[derive(Clone, Default)]
+pub struct TextStyle {
+    pub color: Option<Color>,
+    /// Text scale in points
+    pub scale: Option<f32>,
+    pub font: Option<FontParameters>,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+#[derive(Clone)]
+pub enum SpanContent {
+    Text(String),
+    Image(InlineImage),
+}
+
+/// Part of rich text with its own style
+#[derive(Clone)]
+pub struct TextSpan {
+    pub content: SpanContent,
+    pub style: TextStyle,
+}
+
+impl TextSpan {
+    pub fn text(text: impl Into<String>, style: TextStyle) -> Self {
+        Self {
+            content: SpanContent::Text(text.into()),
+            style,
+        }
+    }
+
+    pub fn image(image: InlineImage) -> Self {
+        Self {
+            content: SpanContent::Image(image),
+            style: TextStyle::default(),
+        }
+    }
+}
+
+/// Fonts and images markup refers to by name
+#[derive(Default)]
+pub struct MarkupResources<'a> {
+    pub fonts: Option<&'a FontRegistry>,
+    pub images: HashMap<String, InlineImage>,
+}
+
+/// Parses markup into spans of rich text
+///
+/// Styles are set by tags closed in the reverse order they were opened in:
+/// `[color=#ff0]`, `[color=#ffcc00]` or `[color=#ffcc0080]`, `[size=24]` in points,
+/// `[font=name]` with a font of the registry, `[u]` for underline and `[s]` for strikethrough.
+/// `[img=name]` inserts an image and isn't closed, `[[` is a literal `[`
+pub fn parse_markup(markup: &str, resources: &MarkupResources) -> anyhow::Result<Vec<TextSpan>> {
+    let mut spans = vec![];
+    let mut open_tags: Vec<(&str, TextStyle)> = vec![];
+    let mut style = TextStyle::default();
+    let mut text = String::new();
+
+    let mut rest = markup;
+    while let Some(start) = rest.find('[') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(escaped) = rest.strip_prefix("[[") {
+            text.push('[');
+            rest = escaped;
+            continue;
+        }
+
+        let end = rest
+            .find(']')
+            .ok_or_else(|| anyhow!("Unterminated tag {}", rest))?;
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        if !text.is_empty() {
+            spans.push(TextSpan::text(std::mem::take(&mut text), style.clone()));
+        }
+
+        if let Some(name) = tag.strip_prefix('/') {
+            match open_tags.pop() {
+                Some((open, previous)) if open == name => style = previous,
+                Some((open, _)) => bail!("Expected [/{}], found [/{}]", open, name),
+                None => bail!("Closing tag [/{}] wasn't opened", name),
+            }
+            continue;
+        }
+
+        let (name, value) = match tag.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (tag, None),
+        };
+        let previous = style.clone();
+        match (name, value) {
+            ("img", Some(value)) => {
+                let image = resources
+                    .images
+                    .get(value)
+                    .ok_or_else(|| anyhow!("Unknown image {}", value))?;
+                spans.push(TextSpan::image(image.clone()));
+                continue;
+            }
+            ("color", Some(value)) => style.color = Some(parse_color(value)?),
+            ("size", Some(value)) => {
+                let scale = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|scale| *scale > 0.0)
+                    .ok_or_else(|| anyhow!("Invalid size {}", value))?;
+                style.scale = Some(scale);
+            }
+            ("font", Some(value)) => {
+                let font = resources
+                    .fonts
+                    .and_then(|fonts| fonts.get(value))
+                    .ok_or_else(|| anyhow!("Unknown font {}", value))?;
+                style.font = Some(FontParameters::Loaded(font.clone()));
+            }
+            ("u", None) => style.underline = true,
+            ("s", None) => style.strikethrough = true,
+            _ => bail!("Unknown tag [{}]", tag),
+        }
+        open_tags.push((name, previous));
+    }
+
+    text.push_str(rest);
+    if !text.is_empty() {
+        spans.push(TextSpan::text(text, style));
+    }
+    if let Some((open, _)) = open_tags.last() {
+        bail!("Tag [{}] isn't closed", open);
+    }
+
+    Ok(spans)
+}
+
+/// Parses `#rgb`, `#rrggbb` or `#rrggbbaa`
+fn parse_color(value: &str) -> anyhow::Result<Color> {
+    let invalid = || anyhow!("Invalid color {}", value);
+    let hex = value.strip_prefix('#').ok_or_else(invalid)?;
+    let channel = |index: usize, length: usize| {
+        let digits = hex
+            .get(index * length..(index + 1) * length)
+            .ok_or_else(invalid)?;
+        let value = u8::from_str_radix(digits, 16).map_err(|_| invalid())?;
+        Ok::<u8, anyhow::Error>(if length == 1 { value * 17 } else { value })
+    };
+
+    match hex.len() {
+        3 => Ok(Color::new(
+            channel(0, 1)?,
+            channel(1, 1)?,
+            channel(2, 1)?,
+            255,
+        )),
+        6 => Ok(Color::new(
+            channel(0, 2)?,
+            channel(1, 2)?,
+            channel(2, 2)?,
+            255,
+        )),
+        8 => Ok(Color::new(
+            channel(0, 2)?,
+            channel(1, 2)?,
+            channel(2, 2)?,
+            channel(3, 2)?,
+        )),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::DEFAULT_FONT;
+    use super::*;
+
+    fn rgba(color: Color) -> (u8, u8, u8, u8) {
+        (color.r, color.g, color.b, color.a)
+    }
+
+    fn texts(spans: &[TextSpan]) -> Vec<&str> {
+        spans
+            .iter()
+            .map(|span| match &span.content {
+                SpanContent::Text(text) => text.as_str(),
+                SpanContent::Image(_) => "<img>",
+            })
+            .collect()
+    }
+
+    fn error(markup: &str) -> String {
+        let resources = MarkupResources::default();
+        match parse_markup(markup, &resources) {
+            Ok(_) => panic!("{} was parsed", markup),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn nested_tags_style_spans() {
+        let spans = parse_markup(
+            "plain [color=#f00]red [u]underlined[/u][/color] [s][size=24]big[/size][/s]",
+            &MarkupResources::default(),
+        )
+        .unwrap();
+        assert_eq!(texts(&spans), ["plain ", "red ", "underlined", " ", "big"]);
+
+        assert!(spans[0].style.color.is_none());
+        assert_eq!(spans[1].style.color.map(rgba), Some((255, 0, 0, 255)));
+        assert!(!spans[1].style.underline);
+        assert_eq!(spans[2].style.color.map(rgba), Some((255, 0, 0, 255)));
+        assert!(spans[2].style.underline);
+        assert!(spans[3].style.color.is_none() && !spans[3].style.underline);
+        assert!(spans[4].style.strikethrough);
+        assert_eq!(spans[4].style.scale, Some(24.0));
+    }
+
+    #[test]
+    fn escaped_bracket_is_text() {
+        let spans = parse_markup("[[not a tag] [[", &MarkupResources::default()).unwrap();
+        assert_eq!(texts(&spans), ["[not a tag] ["]);
+        assert!(parse_markup("", &MarkupResources::default())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn images_and_fonts_are_taken_from_resources() {
+        let mut fonts = FontRegistry::new();
+        fonts.load_bytes("title", DEFAULT_FONT.to_vec()).unwrap();
+        let mut resources = MarkupResources {
+            fonts: Some(&fonts),
+            ..Default::default()
+        };
+        resources.images.insert(
+            "coin".to_string(),
+            InlineImage::new(image::RgbaImage::new(8, 8)),
+        );
+
+        let spans = parse_markup("[font=title]x [img=coin][/font]", &resources).unwrap();
+        assert_eq!(texts(&spans), ["x ", "<img>"]);
+        assert!(matches!(
+            &spans[0].style.font,
+            Some(FontParameters::Loaded(font)) if font.name() == "title"
+        ));
+
+        assert_eq!(
+            parse_markup("[img=gem]", &resources)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Unknown image gem"
+        );
+        assert_eq!(
+            parse_markup("[font=body]a[/font]", &resources)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Unknown font body"
+        );
+    }
+
+    #[test]
+    fn malformed_markup_is_rejected() {
+        assert_eq!(error("a [color=#fff"), "Unterminated tag [color=#fff");
+        assert_eq!(error("[u]a[/s]"), "Expected [/u], found [/s]");
+        assert_eq!(error("a[/u]"), "Closing tag [/u] wasn't opened");
+        assert_eq!(error("[u]a"), "Tag [u] isn't closed");
+        assert_eq!(error("[b]a[/b]"), "Unknown tag [b]");
+        assert_eq!(error("[u=1]a[/u]"), "Unknown tag [u=1]");
+        assert_eq!(error("[size=0]a[/size]"), "Invalid size 0");
+        assert_eq!(error("[size=big]a[/size]"), "Invalid size big");
+        assert_eq!(error("[font=title]a[/font]"), "Unknown font title");
+        assert_eq!(error("[color=red]a[/color]"), "Invalid color red");
+    }
+
+    #[test]
+    fn colors_are_parsed_in_short_and_long_forms() {
+        assert_eq!(rgba(parse_color("#fc0").unwrap()), (255, 204, 0, 255));
+        assert_eq!(rgba(parse_color("#ffcc00").unwrap()), (255, 204, 0, 255));
+        assert_eq!(rgba(parse_color("#ffcc0080").unwrap()), (255, 204, 0, 128));
+    }
+
+    #[test]
+    fn invalid_colors_are_rejected() {
+        for value in [
+            "fc0",
+            "#",
+            "#ff",
+            "#ffcc0",
+            "#ffcc008",
+            "#ggcc00",
+            "#ffcc0080ff",
+            "#éé",
+        ] {
+            assert_eq!(
+                parse_color(value).err().unwrap().to_string(),
+                format!("Invalid color {}", value)
+            );
+        }
+    }
+
+    #[test]
+    fn images_from_sprites_keep_straight_colors() {
+        let image = image::RgbaImage::from_pixel(2, 1, image::Rgba([200, 100, 50, 128]));
+        let inline = InlineImage::from_sprite(&Sprite::from_image(image.clone()));
+        assert_eq!((inline.width(), inline.height()), (2, 1));
+        assert_eq!(*inline.image(), image);
+    }
+}
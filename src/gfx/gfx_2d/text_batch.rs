@@ -0,0 +1,233 @@
+use super::{
+    GlyphAtlas, GlyphKey, InlineImage, RasterizedGlyph, Rect, SpriteBatch, SpriteInstance,
+    TextParameters, TextRasterizer,
+};
+use crate::gfx::texture::{Color, Texture};
+use log::warn;
+use std::rc::Rc;
+
+const ATLAS_SIZE: u32 = 512;
+
+/// What a queued quad shows
+enum QuadSource {
+    Glyph(GlyphKey, Rc<RasterizedGlyph>),
+    Image(InlineImage),
+    /// Single pixel of solid color stretched over the quad, used for lines
+    Solid,
+}
+
+/// Quad of queued text showing the part of its source inside of the text box
+struct QueuedQuad {
+    source: QuadSource,
+    /// Top left corner of the quad in world coordinates
+    position: cgmath::Point2<f32>,
+    size: cgmath::Vector2<f32>,
+    /// Part of the source shown in pixels of the source
+    source_rect: Rect,
+    tint: Color,
+}
+
+impl QueuedQuad {
+    /// Returns the position of the source in the atlas, packing it if needed
+    fn atlas_position(&self, atlas: &mut GlyphAtlas) -> Option<(u32, u32)> {
+        match &self.source {
+            QuadSource::Glyph(key, glyph) => atlas.glyph_position(*key, glyph),
+            QuadSource::Image(image) => atlas.image_position(image),
+            QuadSource::Solid => atlas.solid_position(),
+        }
+    }
+
+    fn instance(&self, atlas_position: (u32, u32), atlas_size: (u32, u32)) -> SpriteInstance {
+        let source = &self.source_rect;
+        let min_u = (atlas_position.0 as i32 + source.x) as f32;
+        let min_v = (atlas_position.1 as i32 + source.y) as f32;
+        let (atlas_width, atlas_height) = (atlas_size.0 as f32, atlas_size.1 as f32);
+        SpriteInstance {
+            position: self.position + self.size / 2.0,
+            scale: cgmath::Vector2::new(
+                self.size.x / source.width as f32,
+                self.size.y / source.height as f32,
+            ),
+            tint: self.tint,
+            uv_rect: [
+                min_u / atlas_width,
+                min_v / atlas_height,
+                (min_u + source.width as f32) / atlas_width,
+                (min_v + source.height as f32) / atlas_height,
+            ],
+            ..Default::default()
+        }
+    }
+}
+
+/// Text drawn by the GPU as a sprite batch of glyphs packed into an atlas texture
+pub(super) struct TextBatch {
+    rasterizer: TextRasterizer,
+    atlas: GlyphAtlas,
+    atlas_texture: Texture,
+    sprite_batch: SpriteBatch,
+    quads: Vec<QueuedQuad>,
+    changed: bool,
+}
+
+impl TextBatch {
+    pub(super) fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let mut atlas = GlyphAtlas::new(ATLAS_SIZE, ATLAS_SIZE);
+        atlas.take_dirty();
+        let atlas_texture = Self::atlas_texture(device, queue, &atlas);
+        let sprite_batch = SpriteBatch::from_texture(device, "text", &atlas_texture);
+
+        Self {
+            rasterizer: TextRasterizer::new(),
+            atlas,
+            atlas_texture,
+            sprite_batch,
+            quads: vec![],
+            changed: false,
+        }
+    }
+
+    pub(super) fn queue_text(
+        &mut self,
+        text: &TextParameters,
+        position: cgmath::Point2<f32>,
+        width: u32,
+        height: u32,
+    ) {
+        let area = Rect::new(0, 0, width, height);
+        let placed = self.rasterizer.placed_text(text, width, height);
+
+        let mut queue = |source: QuadSource, bounds: Rect, tint: Color| {
+            // Aligned text can overflow the box, those parts are cut off
+            if let Some(visible) = bounds.intersection(&area) {
+                // Lines are stretched from a single pixel, the others are shown in their pixels
+                let source_rect = match source {
+                    QuadSource::Solid => Rect::new(0, 0, 1, 1),
+                    _ => Rect::new(
+                        visible.x - bounds.x,
+                        visible.y - bounds.y,
+                        visible.width,
+                        visible.height,
+                    ),
+                };
+                self.quads.push(QueuedQuad {
+                    source,
+                    position: position + cgmath::Vector2::new(visible.x as f32, visible.y as f32),
+                    size: cgmath::Vector2::new(visible.width as f32, visible.height as f32),
+                    source_rect,
+                    tint,
+                });
+            }
+        };
+
+        for glyph in placed.glyphs {
+            let bounds = Rect::new(
+                glyph.origin.x + glyph.glyph.left,
+                glyph.origin.y + glyph.glyph.top,
+                glyph.glyph.width,
+                glyph.glyph.height,
+            );
+            queue(
+                QuadSource::Glyph(glyph.key, glyph.glyph),
+                bounds,
+                glyph.color,
+            );
+        }
+        for image in placed.images {
+            let bounds = Rect::new(
+                image.origin.x,
+                image.origin.y,
+                image.image.width(),
+                image.image.height(),
+            );
+            queue(QuadSource::Image(image.image), bounds, Color::WHITE);
+        }
+        for (bounds, color) in placed.lines {
+            queue(QuadSource::Solid, bounds, color);
+        }
+        self.changed = true;
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.quads.clear();
+        self.changed = true;
+    }
+
+    /// Packs newly queued text into the atlas and uploads it with the instances
+    pub(super) fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.changed {
+            self.pack_glyphs();
+        }
+
+        if self.atlas.take_dirty() {
+            let (width, height) = self.atlas.size();
+            let texture_size = self.atlas_texture.size;
+            if texture_size.width != width || texture_size.height != height {
+                self.atlas_texture = Self::atlas_texture(device, queue, &self.atlas);
+                self.sprite_batch.set_texture(device, &self.atlas_texture);
+            } else {
+                self.atlas_texture.write_region(
+                    queue,
+                    self.atlas.pixels(),
+                    width,
+                    (0, 0, width, height),
+                );
+            }
+        }
+
+        if self.changed {
+            self.changed = false;
+            self.sprite_batch.update_buffer(device, queue);
+        }
+    }
+
+    /// Expects the quad's vertex and index buffers to be set
+    pub(super) fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        self.sprite_batch.render(render_pass);
+    }
+
+    /// Creates an instance for every queued quad, the atlas is cleared of glyphs and images
+    /// that aren't drawn anymore or grown when the queued ones don't fit into it
+    fn pack_glyphs(&mut self) {
+        let mut cleared = false;
+        loop {
+            let mut instances = Vec::with_capacity(self.quads.len());
+            let mut full = false;
+            for queued in &self.quads {
+                match queued.atlas_position(&mut self.atlas) {
+                    Some(position) => instances.push(queued.instance(position, self.atlas.size())),
+                    None => {
+                        full = true;
+                        break;
+                    }
+                }
+            }
+
+            if !full {
+                self.sprite_batch.instances = instances;
+                return;
+            }
+            if !cleared {
+                self.atlas.clear();
+                cleared = true;
+            } else if !self.atlas.grow() {
+                warn!("Glyph atlas is full, some of the queued text isn't drawn");
+                self.sprite_batch.instances = instances;
+                return;
+            }
+        }
+    }
+
+    fn atlas_texture(device: &wgpu::Device, queue: &wgpu::Queue, atlas: &GlyphAtlas) -> Texture {
+        let (width, height) = atlas.size();
+        Texture::from_bytes_rgba(
+            device,
+            queue,
+            atlas.pixels(),
+            width,
+            height,
+            false,
+            Some("glyph atlas texture"),
+        )
+    }
+}
@@ -0,0 +1,557 @@
+use crate::gfx::gfx_2d::animation::AnimationFrame;
+use crate::gfx::gfx_2d::sprite::{DrawSpriteParameters, Rect, Sprite};
+use crate::gfx::gfx_2d::sprite_batch::SpriteInstance;
+use crate::gfx::texture::Color;
+use std::collections::HashMap;
+
+mod tiled;
+
+/// Value of a custom property of a map, a layer, an object or a tile
+#[derive(Debug, Clone)]
+pub enum PropertyValue {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Color(Color),
+    /// Path of a file relative to the map
+    File(String),
+    /// Id of an object of the map
+    Object(u32),
+    /// Properties grouped by a custom class
+    Class(Properties),
+}
+
+pub type Properties = HashMap<String, PropertyValue>;
+
+/// Tile of a tile layer given by its global id with its flips
+///
+/// Flips are applied in the order Tiled applies them: the diagonal one first,
+/// which swaps the x and y axes of the tile, then the horizontal and the vertical one
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Tile {
+    /// Id of the tile in the tileset plus the first global id of its tileset
+    pub id: u32,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    pub flip_diagonal: bool,
+}
+
+impl Tile {
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            flip_horizontal: false,
+            flip_vertical: false,
+            flip_diagonal: false,
+        }
+    }
+}
+
+/// Tile images of the same size cut from one sprite in a grid
+pub struct Tileset {
+    pub name: String,
+    /// Image of the tiles with straight colors like sprites created by `Sprite::from_image`
+    pub sprite: Sprite,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Pixels around the grid
+    pub margin: u32,
+    /// Pixels between neighbouring tiles
+    pub spacing: u32,
+    pub columns: u32,
+    pub tile_count: u32,
+    pub properties: Properties,
+    /// Frames of animated tiles by their ids in the tileset, indices of the frames are ids too
+    animations: HashMap<u32, Vec<AnimationFrame>>,
+    tile_properties: HashMap<u32, Properties>,
+}
+
+impl Tileset {
+    /// Creates a tileset using all whole tiles of the sprite
+    pub fn new(
+        name: &str,
+        sprite: Sprite,
+        tile_width: u32,
+        tile_height: u32,
+        margin: u32,
+        spacing: u32,
+    ) -> Self {
+        let count = |size: u32, tile: u32| {
+            (size.saturating_sub(2 * margin) + spacing) / (tile + spacing).max(1)
+        };
+        let columns = count(sprite.width(), tile_width);
+        let rows = count(sprite.height(), tile_height);
+
+        Self {
+            name: name.to_string(),
+            sprite,
+            tile_width,
+            tile_height,
+            margin,
+            spacing,
+            columns,
+            tile_count: columns * rows,
+            properties: Properties::new(),
+            animations: HashMap::new(),
+            tile_properties: HashMap::new(),
+        }
+    }
+
+    /// Returns the part of the sprite with the tile given by its id in the tileset
+    pub fn tile_rect(&self, id: u32) -> Rect {
+        let columns = self.columns.max(1);
+        let (column, row) = (id % columns, id / columns);
+        Rect::new(
+            (self.margin + column * (self.tile_width + self.spacing)) as i32,
+            (self.margin + row * (self.tile_height + self.spacing)) as i32,
+            self.tile_width,
+            self.tile_height,
+        )
+    }
+
+    /// Returns the part of the sprite with the tile in texture coordinates
+    pub(super) fn tile_uv_rect(&self, id: u32) -> [f32; 4] {
+        let rect = self.tile_rect(id);
+        let (width, height) = (self.sprite.width() as f32, self.sprite.height() as f32);
+        [
+            rect.x as f32 / width,
+            rect.y as f32 / height,
+            (rect.x as f32 + rect.width as f32) / width,
+            (rect.y as f32 + rect.height as f32) / height,
+        ]
+    }
+
+    /// Makes the tile cycle through the frames, whose indices are ids of tiles in the tileset
+    pub fn set_animation(&mut self, id: u32, frames: Vec<AnimationFrame>) {
+        if frames.is_empty() {
+            self.animations.remove(&id);
+        } else {
+            self.animations.insert(id, frames);
+        }
+    }
+
+    pub fn animation(&self, id: u32) -> Option<&[AnimationFrame]> {
+        self.animations.get(&id).map(|frames| frames.as_slice())
+    }
+
+    pub fn tile_properties(&self, id: u32) -> Option<&Properties> {
+        self.tile_properties.get(&id)
+    }
+
+    pub fn set_tile_properties(&mut self, id: u32, properties: Properties) {
+        self.tile_properties.insert(id, properties);
+    }
+
+    /// Returns the id of the tile shown `time` seconds after the start of its animation
+    pub(super) fn animated_tile(&self, id: u32, time: f32) -> u32 {
+        let frames = match self.animations.get(&id) {
+            Some(frames) => frames,
+            None => return id,
+        };
+        let length: f32 = frames.iter().map(|frame| frame.duration).sum();
+        if length <= 0.0 {
+            return id;
+        }
+
+        let mut time = time % length;
+        for frame in frames {
+            if time < frame.duration {
+                return frame.index as u32;
+            }
+            time -= frame.duration;
+        }
+        id
+    }
+}
+
+/// Grid of tiles
+#[derive(Debug, Clone)]
+pub struct TileLayer {
+    pub name: String,
+    pub visible: bool,
+    /// Multiplier of the alpha of the tiles
+    pub opacity: f32,
+    /// Offset of the layer in pixels
+    pub offset: cgmath::Vector2<f32>,
+    pub properties: Properties,
+    width: u32,
+    height: u32,
+    tiles: Vec<Option<Tile>>,
+}
+
+impl TileLayer {
+    /// Creates an empty layer of given size in tiles
+    pub fn new(name: &str, width: u32, height: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            visible: true,
+            opacity: 1.0,
+            offset: cgmath::Vector2::new(0.0, 0.0),
+            properties: Properties::new(),
+            width,
+            height,
+            tiles: vec![None; (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the tile at the column `x` and the row `y`, `None` for empty and outside cells
+    pub fn tile(&self, x: u32, y: u32) -> Option<Tile> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.tiles[(y * self.width + x) as usize]
+    }
+
+    /// Sets the tile at the column `x` and the row `y`, cells outside of the layer are ignored
+    pub fn set_tile(&mut self, x: u32, y: u32, tile: Option<Tile>) {
+        if x < self.width && y < self.height {
+            self.tiles[(y * self.width + x) as usize] = tile;
+        }
+    }
+}
+
+/// Shape of an object on an object layer
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    /// Points relative to the position of the object
+    Polygon(Vec<cgmath::Point2<f32>>),
+    /// Points relative to the position of the object
+    Polyline(Vec<cgmath::Point2<f32>>),
+    /// Tile drawn with its bottom left corner at the position of the object
+    Tile(Tile),
+    Text(String),
+}
+
+/// Object placed on the map, e.g. a spawn point or a trigger area
+#[derive(Debug, Clone)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    /// Class of the object, called type in older versions of Tiled
+    pub class: String,
+    /// Top left corner in pixels, bottom left one for tile objects
+    pub position: cgmath::Point2<f32>,
+    pub size: cgmath::Vector2<f32>,
+    /// Clockwise rotation around the position
+    pub rotation: cgmath::Rad<f32>,
+    pub visible: bool,
+    pub shape: ObjectShape,
+    pub properties: Properties,
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub visible: bool,
+    pub opacity: f32,
+    pub offset: cgmath::Vector2<f32>,
+    pub objects: Vec<MapObject>,
+    pub properties: Properties,
+}
+
+impl ObjectLayer {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            visible: true,
+            opacity: 1.0,
+            offset: cgmath::Vector2::new(0.0, 0.0),
+            objects: vec![],
+            properties: Properties::new(),
+        }
+    }
+
+    /// Returns the first object with the given name
+    pub fn object(&self, name: &str) -> Option<&MapObject> {
+        self.objects.iter().find(|object| object.name == name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MapLayer {
+    Tiles(TileLayer),
+    Objects(ObjectLayer),
+}
+
+impl MapLayer {
+    pub fn name(&self) -> &str {
+        match self {
+            MapLayer::Tiles(layer) => &layer.name,
+            MapLayer::Objects(layer) => &layer.name,
+        }
+    }
+}
+
+/// Level made of layers of tiles from tilesets and layers of objects
+///
+/// Tiles are referenced by global ids, each tileset takes the ids from its first global id
+/// up to the first global id of the next tileset. Tiles larger than the grid are drawn
+/// with their bottom left corner at the bottom left corner of their cell
+pub struct Tilemap {
+    /// Width of the map in tiles
+    pub width: u32,
+    /// Height of the map in tiles
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub layers: Vec<MapLayer>,
+    pub properties: Properties,
+    /// Tilesets with their first global ids sorted by them
+    tilesets: Vec<(u32, Tileset)>,
+    time: f32,
+}
+
+impl Tilemap {
+    /// Creates a map without layers and tilesets, sizes of the map are in tiles
+    pub fn new(width: u32, height: u32, tile_width: u32, tile_height: u32) -> Self {
+        Self {
+            width,
+            height,
+            tile_width,
+            tile_height,
+            layers: vec![],
+            properties: Properties::new(),
+            tilesets: vec![],
+            time: 0.0,
+        }
+    }
+
+    /// Adds the tileset after the already added ones and returns its first global id
+    pub fn add_tileset(&mut self, tileset: Tileset) -> u32 {
+        let first_id = match self.tilesets.last() {
+            Some((first_id, last)) => first_id + last.tile_count.max(1),
+            None => 1,
+        };
+        self.tilesets.push((first_id, tileset));
+        first_id
+    }
+
+    /// Returns the tilesets with their first global ids
+    pub fn tilesets(&self) -> &[(u32, Tileset)] {
+        &self.tilesets
+    }
+
+    /// Returns the tileset of the tile given by its global id and the id in the tileset
+    pub fn tileset_of(&self, id: u32) -> Option<(&Tileset, u32)> {
+        self.tileset_index(id)
+            .map(|(index, id)| (&self.tilesets[index].1, id))
+    }
+
+    /// Returns the index of the tileset of the tile given by its global id and the id in the tileset
+    pub(super) fn tileset_index(&self, id: u32) -> Option<(usize, u32)> {
+        if id == 0 {
+            return None;
+        }
+        self.tilesets
+            .iter()
+            .rposition(|(first_id, _)| *first_id <= id)
+            .map(|index| (index, id - self.tilesets[index].0))
+    }
+
+    /// Returns the custom properties of the tile given by its global id
+    pub fn tile_properties(&self, id: u32) -> Option<&Properties> {
+        let (tileset, id) = self.tileset_of(id)?;
+        tileset.tile_properties(id)
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&MapLayer> {
+        self.layers.iter().find(|layer| layer.name() == name)
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut MapLayer> {
+        self.layers.iter_mut().find(|layer| layer.name() == name)
+    }
+
+    pub fn tile_layer(&self, name: &str) -> Option<&TileLayer> {
+        match self.layer(name) {
+            Some(MapLayer::Tiles(layer)) => Some(layer),
+            _ => None,
+        }
+    }
+
+    pub fn tile_layer_mut(&mut self, name: &str) -> Option<&mut TileLayer> {
+        match self.layer_mut(name) {
+            Some(MapLayer::Tiles(layer)) => Some(layer),
+            _ => None,
+        }
+    }
+
+    pub fn object_layer(&self, name: &str) -> Option<&ObjectLayer> {
+        match self.layer(name) {
+            Some(MapLayer::Objects(layer)) => Some(layer),
+            _ => None,
+        }
+    }
+
+    /// Advances animations of the tiles
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    /// Returns the time in seconds the animations of the tiles have advanced by
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Draws all visible tile layers with the top left corner of the map at `position`
+    ///
+    /// Every tile is drawn pixel by pixel, `Renderer2D::add_tilemap` draws the map
+    /// with the GPU instead, which is much cheaper for maps drawn every frame
+    pub fn draw(&self, target: &mut Sprite, position: cgmath::Point2<f32>) {
+        for layer in &self.layers {
+            if let MapLayer::Tiles(layer) = layer {
+                self.draw_tile_layer(target, layer, position);
+            }
+        }
+    }
+
+    /// Draws the tile layer with the given name if it is visible,
+    /// so that other drawing can be placed between the layers
+    pub fn draw_layer(&self, target: &mut Sprite, name: &str, position: cgmath::Point2<f32>) {
+        if let Some(layer) = self.tile_layer(name) {
+            self.draw_tile_layer(target, layer, position);
+        }
+    }
+
+    /// Draws only the tiles inside of the area of the target that can be drawn on,
+    /// so large maps cost as much as the part of them on the screen
+    fn draw_tile_layer(
+        &self,
+        target: &mut Sprite,
+        layer: &TileLayer,
+        position: cgmath::Point2<f32>,
+    ) {
+        if !layer.visible || layer.opacity <= 0.0 || self.tile_width == 0 || self.tile_height == 0 {
+            return;
+        }
+
+        let origin = position + layer.offset;
+        let (min, max) = target.local_bounds();
+
+        // Tiles larger than the grid reach up and to the right out of their cells
+        let overflow_x = self.max_tile_size().0.saturating_sub(self.tile_width) as f32;
+        let overflow_y = self.max_tile_size().1.saturating_sub(self.tile_height) as f32;
+        let (tile_width, tile_height) = (self.tile_width as f32, self.tile_height as f32);
+        let first =
+            |min: f32, origin: f32, size: f32| ((min - origin) / size).floor().max(0.0) as u32;
+        let last = |max: f32, origin: f32, size: f32, cells: u32| {
+            (((max - origin) / size).floor().max(-1.0) as i64 + 1).min(cells as i64) as u32
+        };
+        let (x0, x1) = (
+            first(min.x - overflow_x, origin.x, tile_width),
+            last(max.x, origin.x, tile_width, layer.width),
+        );
+        let (y0, y1) = (
+            first(min.y, origin.y, tile_height),
+            last(max.y + overflow_y, origin.y, tile_height, layer.height),
+        );
+
+        let alpha = (layer.opacity.min(1.0) * 255.0).round() as u8;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                if let Some(tile) = layer.tile(x, y) {
+                    let cell = cgmath::Point2::new(
+                        origin.x + x as f32 * tile_width,
+                        origin.y + (y + 1) as f32 * tile_height,
+                    );
+                    self.draw_tile(target, tile, cell, alpha);
+                }
+            }
+        }
+    }
+
+    /// Draws the tile with its bottom left corner at `corner`
+    pub fn draw_tile(
+        &self,
+        target: &mut Sprite,
+        tile: Tile,
+        corner: cgmath::Point2<f32>,
+        alpha: u8,
+    ) {
+        let (tileset, id) = match self.tileset_of(tile.id) {
+            Some(found) => found,
+            None => return,
+        };
+        let id = tileset.animated_tile(id, self.time);
+        let (width, height) = (tileset.tile_width as f32, tileset.tile_height as f32);
+        let (rotation, flip_horizontal, flip_vertical) = Self::tile_orientation(tile);
+        let center = cgmath::Point2::new(corner.x + width / 2.0, corner.y - height / 2.0);
+
+        target.draw_sprite_ex(
+            &tileset.sprite,
+            center,
+            &DrawSpriteParameters {
+                source: Some(tileset.tile_rect(id)),
+                rotation,
+                pivot: cgmath::Point2::new(width / 2.0, height / 2.0),
+                flip_horizontal,
+                flip_vertical,
+                tint: Color::new(255, 255, 255, alpha),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Returns the index of the tileset, the id of the tile in it and the instance
+    /// drawing the tile with its bottom left corner at `corner` the same way `draw_tile` does
+    pub(super) fn tile_instance(
+        &self,
+        tile: Tile,
+        corner: cgmath::Point2<f32>,
+        alpha: u8,
+    ) -> Option<(usize, u32, SpriteInstance)> {
+        let (index, id) = self.tileset_index(tile.id)?;
+        let tileset = &self.tilesets[index].1;
+        let (width, height) = (tileset.tile_width as f32, tileset.tile_height as f32);
+        let (rotation, flip_horizontal, flip_vertical) = Self::tile_orientation(tile);
+        let sign = |flip: bool| if flip { -1.0 } else { 1.0 };
+
+        let instance = SpriteInstance {
+            position: cgmath::Point2::new(corner.x + width / 2.0, corner.y - height / 2.0),
+            scale: cgmath::Vector2::new(sign(flip_horizontal), sign(flip_vertical)),
+            rotation,
+            tint: Color::new(255, 255, 255, alpha),
+            uv_rect: tileset.tile_uv_rect(tileset.animated_tile(id, self.time)),
+            ..Default::default()
+        };
+        Some((index, id, instance))
+    }
+
+    /// Returns the rotation and the flips of the source the tile is drawn with
+    fn tile_orientation(tile: Tile) -> (cgmath::Rad<f32>, bool, bool) {
+        // A diagonal flip is a rotation by a quarter turn counterclockwise with flips
+        // of the source, the tile is rotated around its center
+        if tile.flip_diagonal {
+            (
+                cgmath::Rad(-std::f32::consts::FRAC_PI_2),
+                !tile.flip_vertical,
+                tile.flip_horizontal,
+            )
+        } else {
+            (cgmath::Rad(0.0), tile.flip_horizontal, tile.flip_vertical)
+        }
+    }
+
+    fn max_tile_size(&self) -> (u32, u32) {
+        self.tilesets
+            .iter()
+            .fold((0, 0), |(width, height), (_, tileset)| {
+                (
+                    width.max(tileset.tile_width),
+                    height.max(tileset.tile_height),
+                )
+            })
+    }
+}
@@ -0,0 +1,1248 @@
+//! Import of maps made in the [Tiled](https://www.mapeditor.org/) editor
+//!
+//! Both formats are read into the same raw structures first, which are then turned into a `Tilemap`
+
+use super::{
+    MapLayer, MapObject, ObjectLayer, ObjectShape, Properties, PropertyValue, Tile, TileLayer,
+    Tilemap, Tileset,
+};
+use crate::gfx::gfx_2d::animation::AnimationFrame;
+use crate::gfx::gfx_2d::sprite::Sprite;
+use crate::gfx::texture::Color;
+use anyhow::{anyhow, bail, Context};
+use base64::Engine;
+use serde::Deserialize;
+use std::io::Read;
+use std::path::Path;
+
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+/// Rotation of hexagonal tiles, which is ignored
+const ROTATED_HEXAGONAL: u32 = 0x1000_0000;
+
+/// Methods related to importing Tiled maps
+///
+/// Only finite orthogonal maps are supported. Tilesets must be made of a single image,
+/// external tilesets and images are loaded relative to the file referencing them.
+/// Image layers are skipped and layers of groups are added with the group's offset,
+/// visibility and opacity applied
+impl Tilemap {
+    /// Loads a `.tmj` or `.json` map in the JSON format or a `.tmx` map in the XML format
+    pub fn from_tiled_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read map {}", path.display()))?;
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+        let map = match path.extension().and_then(|e| e.to_str()) {
+            Some("tmx") => Self::from_tmx(&contents, directory),
+            _ => Self::from_tmj(&contents, directory),
+        };
+        map.with_context(|| format!("Failed to import map {}", path.display()))
+    }
+
+    /// Imports a map in the JSON format, files it references are loaded relative to `directory`
+    pub fn from_tmj(json: &str, directory: &Path) -> anyhow::Result<Self> {
+        let map: JsonMap = serde_json::from_str(json)?;
+        build_map(map.into_raw()?, directory)
+    }
+
+    /// Imports a map in the XML format, files it references are loaded relative to `directory`
+    pub fn from_tmx(xml: &str, directory: &Path) -> anyhow::Result<Self> {
+        let document = roxmltree::Document::parse(xml)?;
+        build_map(xml_map(document.root_element())?, directory)
+    }
+}
+
+struct RawMap {
+    orientation: String,
+    infinite: bool,
+    width: u32,
+    height: u32,
+    tile_width: u32,
+    tile_height: u32,
+    tilesets: Vec<RawTilesetReference>,
+    layers: Vec<RawLayer>,
+    properties: Properties,
+}
+
+enum RawTilesetReference {
+    External { first_id: u32, source: String },
+    Embedded { first_id: u32, tileset: RawTileset },
+}
+
+struct RawTileset {
+    name: String,
+    tile_width: u32,
+    tile_height: u32,
+    margin: u32,
+    spacing: u32,
+    tile_count: u32,
+    columns: u32,
+    image: Option<String>,
+    transparent_color: Option<Color>,
+    tiles: Vec<RawTile>,
+    properties: Properties,
+}
+
+struct RawTile {
+    id: u32,
+    /// Ids of the tiles with durations in milliseconds
+    animation: Vec<(u32, u32)>,
+    properties: Properties,
+}
+
+struct RawLayerInfo {
+    name: String,
+    visible: bool,
+    opacity: f32,
+    offset: cgmath::Vector2<f32>,
+    properties: Properties,
+}
+
+enum RawLayer {
+    Tiles {
+        info: RawLayerInfo,
+        width: u32,
+        height: u32,
+        data: Vec<u32>,
+    },
+    Objects {
+        info: RawLayerInfo,
+        objects: Vec<MapObject>,
+    },
+    Group {
+        info: RawLayerInfo,
+        layers: Vec<RawLayer>,
+    },
+    Image,
+}
+
+fn build_map(raw: RawMap, directory: &Path) -> anyhow::Result<Tilemap> {
+    if raw.orientation != "orthogonal" {
+        bail!("{} maps are not supported", raw.orientation);
+    }
+    if raw.infinite {
+        bail!("Infinite maps are not supported");
+    }
+
+    let mut map = Tilemap::new(raw.width, raw.height, raw.tile_width, raw.tile_height);
+    map.properties = raw.properties;
+
+    for reference in raw.tilesets {
+        let (first_id, tileset) = match reference {
+            RawTilesetReference::Embedded { first_id, tileset } => {
+                (first_id, build_tileset(tileset, directory)?)
+            }
+            RawTilesetReference::External { first_id, source } => {
+                let path = directory.join(&source);
+                let tileset = load_tileset(&path)
+                    .with_context(|| format!("Failed to import tileset {}", path.display()))?;
+                (first_id, tileset)
+            }
+        };
+        map.tilesets.push((first_id, tileset));
+    }
+    map.tilesets.sort_by_key(|(first_id, _)| *first_id);
+
+    let root = RawLayerInfo {
+        name: String::new(),
+        visible: true,
+        opacity: 1.0,
+        offset: cgmath::Vector2::new(0.0, 0.0),
+        properties: Properties::new(),
+    };
+    add_layers(&mut map.layers, raw.layers, &root)?;
+    Ok(map)
+}
+
+/// Adds the layers with the group's offset, visibility and opacity applied, groups are flattened
+fn add_layers(
+    layers: &mut Vec<MapLayer>,
+    raw_layers: Vec<RawLayer>,
+    group: &RawLayerInfo,
+) -> anyhow::Result<()> {
+    let apply_group = |mut info: RawLayerInfo| {
+        info.visible &= group.visible;
+        info.opacity *= group.opacity;
+        info.offset += group.offset;
+        info
+    };
+
+    for raw_layer in raw_layers {
+        match raw_layer {
+            RawLayer::Tiles {
+                info,
+                width,
+                height,
+                data,
+            } => {
+                if data.len() != (width * height) as usize {
+                    bail!(
+                        "Layer {} has {} tiles instead of {}",
+                        info.name,
+                        data.len(),
+                        width * height
+                    );
+                }
+                let info = apply_group(info);
+                let mut layer = TileLayer::new(&info.name, width, height);
+                layer.visible = info.visible;
+                layer.opacity = info.opacity;
+                layer.offset = info.offset;
+                layer.properties = info.properties;
+                layer.tiles = data.into_iter().map(tile_from_id).collect();
+                layers.push(MapLayer::Tiles(layer));
+            }
+            RawLayer::Objects { info, objects } => {
+                let info = apply_group(info);
+                let mut layer = ObjectLayer::new(&info.name);
+                layer.visible = info.visible;
+                layer.opacity = info.opacity;
+                layer.offset = info.offset;
+                layer.properties = info.properties;
+                layer.objects = objects;
+                layers.push(MapLayer::Objects(layer));
+            }
+            RawLayer::Group {
+                info,
+                layers: children,
+            } => {
+                add_layers(layers, children, &apply_group(info))?;
+            }
+            RawLayer::Image => {}
+        }
+    }
+    Ok(())
+}
+
+/// Splits the global id with flip flags into a tile, 0 is an empty cell
+fn tile_from_id(id: u32) -> Option<Tile> {
+    let flags = FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL;
+    let tile_id = id & !flags;
+    if tile_id == 0 {
+        return None;
+    }
+    Some(Tile {
+        id: tile_id,
+        flip_horizontal: id & FLIPPED_HORIZONTALLY != 0,
+        flip_vertical: id & FLIPPED_VERTICALLY != 0,
+        flip_diagonal: id & FLIPPED_DIAGONALLY != 0,
+    })
+}
+
+fn load_tileset(path: &Path) -> anyhow::Result<Tileset> {
+    let contents = std::fs::read_to_string(path)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let raw = match path.extension().and_then(|e| e.to_str()) {
+        Some("tsx") => {
+            let document = roxmltree::Document::parse(&contents)?;
+            xml_tileset(document.root_element())?
+        }
+        _ => serde_json::from_str::<JsonTileset>(&contents)?.into_raw()?,
+    };
+    build_tileset(raw, directory)
+}
+
+fn build_tileset(raw: RawTileset, directory: &Path) -> anyhow::Result<Tileset> {
+    let image = match raw.image {
+        Some(image) => image,
+        None => bail!(
+            "Tileset {} is an image collection, which is not supported",
+            raw.name
+        ),
+    };
+    let path = directory.join(&image);
+    let mut image = image::open(&path)
+        .with_context(|| format!("Failed to load image {}", path.display()))?
+        .to_rgba8();
+    if let Some(key) = raw.transparent_color {
+        for pixel in image.pixels_mut() {
+            if pixel[0] == key.r && pixel[1] == key.g && pixel[2] == key.b {
+                *pixel = image::Rgba([0, 0, 0, 0]);
+            }
+        }
+    }
+
+    let mut tileset = Tileset::new(
+        &raw.name,
+        Sprite::from_image(image),
+        raw.tile_width,
+        raw.tile_height,
+        raw.margin,
+        raw.spacing,
+    );
+    if raw.columns > 0 {
+        tileset.columns = raw.columns;
+    }
+    if raw.tile_count > 0 {
+        tileset.tile_count = raw.tile_count;
+    }
+    tileset.properties = raw.properties;
+
+    for tile in raw.tiles {
+        let frames = tile
+            .animation
+            .iter()
+            .map(|&(id, duration)| AnimationFrame {
+                index: id as usize,
+                duration: duration as f32 / 1000.0,
+            })
+            .collect();
+        tileset.set_animation(tile.id, frames);
+        if !tile.properties.is_empty() {
+            tileset.set_tile_properties(tile.id, tile.properties);
+        }
+    }
+    Ok(tileset)
+}
+
+/// Parses `#RRGGBB` or `#AARRGGBB` with an optional `#`
+fn parse_color(value: &str) -> anyhow::Result<Color> {
+    let hex = value.trim_start_matches('#');
+    let channel = |i: usize| {
+        u8::from_str_radix(hex.get(i..i + 2).unwrap_or(""), 16)
+            .map_err(|_| anyhow!("Invalid color {}", value))
+    };
+    match hex.len() {
+        6 => Ok(Color::new(channel(0)?, channel(2)?, channel(4)?, 255)),
+        8 => Ok(Color::new(
+            channel(2)?,
+            channel(4)?,
+            channel(6)?,
+            channel(0)?,
+        )),
+        _ if hex.is_empty() => Ok(Color::TRANSPARENT),
+        _ => bail!("Invalid color {}", value),
+    }
+}
+
+/// Decodes tile layer data in base64, optionally compressed, into global ids
+fn decode_tile_data(data: &str, compression: Option<&str>) -> anyhow::Result<Vec<u32>> {
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data.trim())?;
+    let bytes = match compression {
+        None | Some("") => bytes,
+        Some("zlib") => {
+            let mut decoded = vec![];
+            flate2::read::ZlibDecoder::new(bytes.as_slice()).read_to_end(&mut decoded)?;
+            decoded
+        }
+        Some("gzip") => {
+            let mut decoded = vec![];
+            flate2::read::GzDecoder::new(bytes.as_slice()).read_to_end(&mut decoded)?;
+            decoded
+        }
+        Some(compression) => bail!("{} compression is not supported", compression),
+    };
+
+    Ok(bytes
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+fn parse_csv(data: &str) -> anyhow::Result<Vec<u32>> {
+    data.split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| Ok(value.parse::<u32>()?))
+        .collect()
+}
+
+fn object_shape(
+    gid: Option<u32>,
+    ellipse: bool,
+    point: bool,
+    polygon: Option<Vec<cgmath::Point2<f32>>>,
+    polyline: Option<Vec<cgmath::Point2<f32>>>,
+    text: Option<String>,
+) -> ObjectShape {
+    if let Some(tile) = gid.and_then(tile_from_id) {
+        ObjectShape::Tile(tile)
+    } else if let Some(points) = polygon {
+        ObjectShape::Polygon(points)
+    } else if let Some(points) = polyline {
+        ObjectShape::Polyline(points)
+    } else if let Some(text) = text {
+        ObjectShape::Text(text)
+    } else if ellipse {
+        ObjectShape::Ellipse
+    } else if point {
+        ObjectShape::Point
+    } else {
+        ObjectShape::Rectangle
+    }
+}
+
+// JSON format
+
+#[derive(Deserialize)]
+struct JsonMap {
+    #[serde(default = "default_orientation")]
+    orientation: String,
+    #[serde(default)]
+    infinite: bool,
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default)]
+    tilesets: Vec<JsonTilesetReference>,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Deserialize)]
+struct JsonTilesetReference {
+    firstgid: u32,
+    source: Option<String>,
+    #[serde(flatten)]
+    tileset: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct JsonTileset {
+    #[serde(default)]
+    name: String,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default)]
+    margin: u32,
+    #[serde(default)]
+    spacing: u32,
+    #[serde(default)]
+    tilecount: u32,
+    #[serde(default)]
+    columns: u32,
+    image: Option<String>,
+    transparentcolor: Option<String>,
+    #[serde(default)]
+    tiles: Vec<JsonTile>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Deserialize)]
+struct JsonTile {
+    id: u32,
+    #[serde(default)]
+    animation: Vec<JsonFrame>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Deserialize)]
+struct JsonFrame {
+    tileid: u32,
+    duration: u32,
+}
+
+#[derive(Deserialize)]
+struct JsonLayer {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(default = "default_opacity")]
+    opacity: f32,
+    #[serde(default)]
+    offsetx: f32,
+    #[serde(default)]
+    offsety: f32,
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+    data: Option<serde_json::Value>,
+    encoding: Option<String>,
+    compression: Option<String>,
+    #[serde(default)]
+    objects: Vec<JsonObject>,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Deserialize)]
+struct JsonObject {
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type")]
+    kind: String,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default = "default_true")]
+    visible: bool,
+    gid: Option<u32>,
+    #[serde(default)]
+    ellipse: bool,
+    #[serde(default)]
+    point: bool,
+    polygon: Option<Vec<JsonPoint>>,
+    polyline: Option<Vec<JsonPoint>>,
+    text: Option<JsonText>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Deserialize)]
+struct JsonPoint {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct JsonText {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct JsonProperty {
+    name: String,
+    #[serde(default = "default_property_type", rename = "type")]
+    kind: String,
+    value: serde_json::Value,
+}
+
+fn default_orientation() -> String {
+    "orthogonal".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+fn default_property_type() -> String {
+    "string".to_string()
+}
+
+impl JsonMap {
+    fn into_raw(self) -> anyhow::Result<RawMap> {
+        let tilesets = self
+            .tilesets
+            .into_iter()
+            .map(|reference| {
+                Ok(match reference.source {
+                    Some(source) => RawTilesetReference::External {
+                        first_id: reference.firstgid,
+                        source,
+                    },
+                    None => RawTilesetReference::Embedded {
+                        first_id: reference.firstgid,
+                        tileset: serde_json::from_value::<JsonTileset>(reference.tileset)?
+                            .into_raw()?,
+                    },
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(RawMap {
+            orientation: self.orientation,
+            infinite: self.infinite,
+            width: self.width,
+            height: self.height,
+            tile_width: self.tilewidth,
+            tile_height: self.tileheight,
+            tilesets,
+            layers: self
+                .layers
+                .into_iter()
+                .map(JsonLayer::into_raw)
+                .collect::<anyhow::Result<_>>()?,
+            properties: json_properties(self.properties)?,
+        })
+    }
+}
+
+impl JsonTileset {
+    fn into_raw(self) -> anyhow::Result<RawTileset> {
+        Ok(RawTileset {
+            name: self.name,
+            tile_width: self.tilewidth,
+            tile_height: self.tileheight,
+            margin: self.margin,
+            spacing: self.spacing,
+            tile_count: self.tilecount,
+            columns: self.columns,
+            image: self.image,
+            transparent_color: self
+                .transparentcolor
+                .as_deref()
+                .map(parse_color)
+                .transpose()?,
+            tiles: self
+                .tiles
+                .into_iter()
+                .map(|tile| {
+                    Ok(RawTile {
+                        id: tile.id,
+                        animation: tile
+                            .animation
+                            .iter()
+                            .map(|frame| (frame.tileid, frame.duration))
+                            .collect(),
+                        properties: json_properties(tile.properties)?,
+                    })
+                })
+                .collect::<anyhow::Result<_>>()?,
+            properties: json_properties(self.properties)?,
+        })
+    }
+}
+
+impl JsonLayer {
+    fn into_raw(self) -> anyhow::Result<RawLayer> {
+        let info = RawLayerInfo {
+            name: self.name,
+            visible: self.visible,
+            opacity: self.opacity,
+            offset: cgmath::Vector2::new(self.offsetx, self.offsety),
+            properties: json_properties(self.properties)?,
+        };
+
+        Ok(match self.kind.as_str() {
+            "tilelayer" => {
+                let data = match (self.data, self.encoding.as_deref()) {
+                    (Some(serde_json::Value::String(data)), Some("base64")) => {
+                        decode_tile_data(&data, self.compression.as_deref())?
+                    }
+                    (Some(data), _) => serde_json::from_value(data)?,
+                    (None, _) => bail!("Layer {} has no data", info.name),
+                };
+                RawLayer::Tiles {
+                    info,
+                    width: self.width,
+                    height: self.height,
+                    data,
+                }
+            }
+            "objectgroup" => RawLayer::Objects {
+                info,
+                objects: self
+                    .objects
+                    .into_iter()
+                    .map(JsonObject::into_object)
+                    .collect::<anyhow::Result<_>>()?,
+            },
+            "group" => RawLayer::Group {
+                info,
+                layers: self
+                    .layers
+                    .into_iter()
+                    .map(JsonLayer::into_raw)
+                    .collect::<anyhow::Result<_>>()?,
+            },
+            _ => RawLayer::Image,
+        })
+    }
+}
+
+impl JsonObject {
+    fn into_object(self) -> anyhow::Result<MapObject> {
+        let points = |points: Option<Vec<JsonPoint>>| {
+            points.map(|points| {
+                points
+                    .iter()
+                    .map(|p| cgmath::Point2::new(p.x, p.y))
+                    .collect()
+            })
+        };
+
+        Ok(MapObject {
+            id: self.id,
+            name: self.name,
+            class: if self.class.is_empty() {
+                self.kind
+            } else {
+                self.class
+            },
+            position: cgmath::Point2::new(self.x, self.y),
+            size: cgmath::Vector2::new(self.width, self.height),
+            rotation: cgmath::Deg(self.rotation).into(),
+            visible: self.visible,
+            shape: object_shape(
+                self.gid,
+                self.ellipse,
+                self.point,
+                points(self.polygon),
+                points(self.polyline),
+                self.text.map(|text| text.text),
+            ),
+            properties: json_properties(self.properties)?,
+        })
+    }
+}
+
+fn json_properties(properties: Vec<JsonProperty>) -> anyhow::Result<Properties> {
+    properties
+        .into_iter()
+        .map(|property| {
+            let value = json_property_value(&property.kind, property.value)
+                .with_context(|| format!("Invalid property {}", property.name))?;
+            Ok((property.name, value))
+        })
+        .collect()
+}
+
+fn json_property_value(kind: &str, value: serde_json::Value) -> anyhow::Result<PropertyValue> {
+    use serde_json::Value;
+    Ok(match (kind, value) {
+        ("int", value) => PropertyValue::Int(
+            value
+                .as_i64()
+                .or_else(|| value.as_f64().map(|v| v as i64))
+                .ok_or_else(|| anyhow!("Expected an integer"))?,
+        ),
+        ("float", value) => {
+            PropertyValue::Float(value.as_f64().ok_or_else(|| anyhow!("Expected a number"))?)
+        }
+        ("bool", Value::Bool(value)) => PropertyValue::Bool(value),
+        ("color", Value::String(value)) => PropertyValue::Color(parse_color(&value)?),
+        ("file", Value::String(value)) => PropertyValue::File(value),
+        ("object", value) => PropertyValue::Object(
+            value
+                .as_u64()
+                .ok_or_else(|| anyhow!("Expected an object id"))? as u32,
+        ),
+        // Members of classes are stored without their types
+        ("class", Value::Object(members)) => PropertyValue::Class(
+            members
+                .into_iter()
+                .map(|(name, value)| {
+                    let kind = match &value {
+                        Value::Bool(_) => "bool",
+                        Value::Number(number) if number.is_i64() => "int",
+                        Value::Number(_) => "float",
+                        Value::Object(_) => "class",
+                        _ => "string",
+                    };
+                    Ok((name, json_property_value(kind, value)?))
+                })
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        (_, Value::String(value)) => PropertyValue::String(value),
+        (_, value) => PropertyValue::String(value.to_string()),
+    })
+}
+
+// XML format
+
+fn attribute<T: std::str::FromStr>(
+    node: roxmltree::Node,
+    name: &str,
+    default: T,
+) -> anyhow::Result<T> {
+    match node.attribute(name) {
+        Some(value) => value.trim().parse().map_err(|_| {
+            anyhow!(
+                "Invalid value {} of attribute {} of {}",
+                value,
+                name,
+                node.tag_name().name()
+            )
+        }),
+        None => Ok(default),
+    }
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn xml_map(node: roxmltree::Node) -> anyhow::Result<RawMap> {
+    if !node.has_tag_name("map") {
+        bail!("Expected a map element");
+    }
+
+    let tilesets = node
+        .children()
+        .filter(|child| child.has_tag_name("tileset"))
+        .map(|tileset| {
+            let first_id = attribute(tileset, "firstgid", 1)?;
+            Ok(match tileset.attribute("source") {
+                Some(source) => RawTilesetReference::External {
+                    first_id,
+                    source: source.to_string(),
+                },
+                None => RawTilesetReference::Embedded {
+                    first_id,
+                    tileset: xml_tileset(tileset)?,
+                },
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(RawMap {
+        orientation: attribute(node, "orientation", default_orientation())?,
+        infinite: attribute(node, "infinite", 0)? != 0,
+        width: attribute(node, "width", 0)?,
+        height: attribute(node, "height", 0)?,
+        tile_width: attribute(node, "tilewidth", 0)?,
+        tile_height: attribute(node, "tileheight", 0)?,
+        tilesets,
+        layers: xml_layers(node)?,
+        properties: xml_properties(node)?,
+    })
+}
+
+fn xml_tileset(node: roxmltree::Node) -> anyhow::Result<RawTileset> {
+    let image = child(node, "image");
+    let tiles = node
+        .children()
+        .filter(|child| child.has_tag_name("tile"))
+        .map(|tile| {
+            let animation = match child(tile, "animation") {
+                Some(animation) => animation
+                    .children()
+                    .filter(|frame| frame.has_tag_name("frame"))
+                    .map(|frame| {
+                        Ok((
+                            attribute(frame, "tileid", 0)?,
+                            attribute(frame, "duration", 0)?,
+                        ))
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+                None => vec![],
+            };
+            Ok(RawTile {
+                id: attribute(tile, "id", 0)?,
+                animation,
+                properties: xml_properties(tile)?,
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(RawTileset {
+        name: node.attribute("name").unwrap_or_default().to_string(),
+        tile_width: attribute(node, "tilewidth", 0)?,
+        tile_height: attribute(node, "tileheight", 0)?,
+        margin: attribute(node, "margin", 0)?,
+        spacing: attribute(node, "spacing", 0)?,
+        tile_count: attribute(node, "tilecount", 0)?,
+        columns: attribute(node, "columns", 0)?,
+        image: image
+            .and_then(|image| image.attribute("source"))
+            .map(str::to_string),
+        transparent_color: image
+            .and_then(|image| image.attribute("trans"))
+            .map(parse_color)
+            .transpose()?,
+        tiles,
+        properties: xml_properties(node)?,
+    })
+}
+
+fn xml_layers(node: roxmltree::Node) -> anyhow::Result<Vec<RawLayer>> {
+    node.children()
+        .filter(|child| child.is_element())
+        .filter_map(|child| {
+            let info = || -> anyhow::Result<RawLayerInfo> {
+                Ok(RawLayerInfo {
+                    name: child.attribute("name").unwrap_or_default().to_string(),
+                    visible: attribute(child, "visible", 1)? != 0,
+                    opacity: attribute(child, "opacity", 1.0)?,
+                    offset: cgmath::Vector2::new(
+                        attribute(child, "offsetx", 0.0)?,
+                        attribute(child, "offsety", 0.0)?,
+                    ),
+                    properties: xml_properties(child)?,
+                })
+            };
+            let layer = match child.tag_name().name() {
+                "layer" => (|| {
+                    Ok(RawLayer::Tiles {
+                        info: info()?,
+                        width: attribute(child, "width", 0)?,
+                        height: attribute(child, "height", 0)?,
+                        data: xml_tile_data(child)?,
+                    })
+                })(),
+                "objectgroup" => (|| {
+                    Ok(RawLayer::Objects {
+                        info: info()?,
+                        objects: child
+                            .children()
+                            .filter(|object| object.has_tag_name("object"))
+                            .map(xml_object)
+                            .collect::<anyhow::Result<_>>()?,
+                    })
+                })(),
+                "group" => (|| {
+                    Ok(RawLayer::Group {
+                        info: info()?,
+                        layers: xml_layers(child)?,
+                    })
+                })(),
+                "imagelayer" => Ok(RawLayer::Image),
+                _ => return None,
+            };
+            Some(layer)
+        })
+        .collect()
+}
+
+fn xml_tile_data(layer: roxmltree::Node) -> anyhow::Result<Vec<u32>> {
+    let data = child(layer, "data").ok_or_else(|| anyhow!("Layer has no data"))?;
+    if child(data, "chunk").is_some() {
+        bail!("Infinite maps are not supported");
+    }
+
+    let text = data.text().unwrap_or_default();
+    match data.attribute("encoding") {
+        Some("csv") => parse_csv(text),
+        Some("base64") => decode_tile_data(text, data.attribute("compression")),
+        Some(encoding) => bail!("{} encoding is not supported", encoding),
+        None => data
+            .children()
+            .filter(|tile| tile.has_tag_name("tile"))
+            .map(|tile| attribute(tile, "gid", 0))
+            .collect(),
+    }
+}
+
+fn xml_object(node: roxmltree::Node) -> anyhow::Result<MapObject> {
+    let points = |name: &str| -> anyhow::Result<Option<Vec<cgmath::Point2<f32>>>> {
+        let shape = match child(node, name) {
+            Some(shape) => shape,
+            None => return Ok(None),
+        };
+        shape
+            .attribute("points")
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|point| {
+                let (x, y) = point
+                    .split_once(',')
+                    .ok_or_else(|| anyhow!("Invalid point {}", point))?;
+                Ok(cgmath::Point2::new(x.parse()?, y.parse()?))
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(Some)
+    };
+
+    let class = node
+        .attribute("class")
+        .or_else(|| node.attribute("type"))
+        .unwrap_or_default();
+    let gid = node.attribute("gid").map(str::parse::<u32>).transpose()?;
+
+    Ok(MapObject {
+        id: attribute(node, "id", 0)?,
+        name: node.attribute("name").unwrap_or_default().to_string(),
+        class: class.to_string(),
+        position: cgmath::Point2::new(attribute(node, "x", 0.0)?, attribute(node, "y", 0.0)?),
+        size: cgmath::Vector2::new(
+            attribute(node, "width", 0.0)?,
+            attribute(node, "height", 0.0)?,
+        ),
+        rotation: cgmath::Deg(attribute(node, "rotation", 0.0_f32)?).into(),
+        visible: attribute(node, "visible", 1)? != 0,
+        shape: object_shape(
+            gid,
+            child(node, "ellipse").is_some(),
+            child(node, "point").is_some(),
+            points("polygon")?,
+            points("polyline")?,
+            child(node, "text").map(|text| text.text().unwrap_or_default().to_string()),
+        ),
+        properties: xml_properties(node)?,
+    })
+}
+
+fn xml_properties(node: roxmltree::Node) -> anyhow::Result<Properties> {
+    let properties = match child(node, "properties") {
+        Some(properties) => properties,
+        None => return Ok(Properties::new()),
+    };
+
+    properties
+        .children()
+        .filter(|property| property.has_tag_name("property"))
+        .map(|property| {
+            let name = property.attribute("name").unwrap_or_default().to_string();
+            // Multiline strings are stored as the text of the element
+            let value = property
+                .attribute("value")
+                .or_else(|| property.text())
+                .unwrap_or_default();
+            let invalid = || anyhow!("Invalid value {} of property {}", value, name);
+
+            let value = match property.attribute("type").unwrap_or("string") {
+                "int" => PropertyValue::Int(value.parse().map_err(|_| invalid())?),
+                "float" => PropertyValue::Float(value.parse().map_err(|_| invalid())?),
+                "bool" => PropertyValue::Bool(value.parse().map_err(|_| invalid())?),
+                "color" => PropertyValue::Color(parse_color(value)?),
+                "file" => PropertyValue::File(value.to_string()),
+                "object" => PropertyValue::Object(value.parse().map_err(|_| invalid())?),
+                "class" => PropertyValue::Class(xml_properties(property)?),
+                _ => PropertyValue::String(value.to_string()),
+            };
+            Ok((name, value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    /// Creates an empty directory with a 64x32 tileset image `tiles.png`
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("tiled_test_{}", name));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        image::RgbaImage::from_pixel(64, 32, image::Rgba([255, 0, 255, 255]))
+            .save(directory.join("tiles.png"))
+            .unwrap();
+        directory
+    }
+
+    fn tiles(layer: &TileLayer) -> Vec<Option<u32>> {
+        (0..layer.height())
+            .flat_map(|y| (0..layer.width()).map(move |x| layer.tile(x, y).map(|t| t.id)))
+            .collect()
+    }
+
+    const TMJ: &str = r##"{
+        "width": 2, "height": 2, "tilewidth": 16, "tileheight": 16,
+        "properties": [{"name": "music", "type": "file", "value": "level.ogg"}],
+        "tilesets": [{
+            "firstgid": 1, "name": "terrain", "tilewidth": 16, "tileheight": 16,
+            "image": "tiles.png", "transparentcolor": "#ff00ff",
+            "tiles": [{"id": 1, "animation": [
+                {"tileid": 1, "duration": 100}, {"tileid": 2, "duration": 100}
+            ]}]
+        }],
+        "layers": [
+            {"type": "tilelayer", "name": "ground", "width": 2, "height": 2,
+             "data": [1, 0, 2147483650, 3]},
+            {"type": "group", "name": "decor", "offsetx": 4, "opacity": 0.5, "layers": [
+                {"type": "tilelayer", "name": "grass", "width": 2, "height": 2, "offsetx": 1,
+                 "opacity": 0.5, "data": [0, 0, 0, 4]},
+                {"type": "imagelayer", "name": "sky"}
+            ]},
+            {"type": "objectgroup", "name": "objects", "objects": [
+                {"id": 7, "name": "spawn", "type": "player", "x": 8, "y": 24, "point": true,
+                 "properties": [{"name": "lives", "type": "int", "value": 3}]},
+                {"id": 8, "name": "area", "x": 0, "y": 0, "rotation": 90,
+                 "polygon": [{"x": 0, "y": 0}, {"x": 16, "y": 0}, {"x": 0, "y": 16}]}
+            ]}
+        ]
+    }"##;
+
+    #[test]
+    fn json_map_is_imported() {
+        let directory = directory("json");
+        let map = Tilemap::from_tmj(TMJ, &directory).unwrap();
+        assert_eq!((map.width, map.height, map.tile_width), (2, 2, 16));
+        assert!(matches!(
+            map.properties.get("music"),
+            Some(PropertyValue::File(file)) if file == "level.ogg"
+        ));
+
+        let (first_id, tileset) = &map.tilesets()[0];
+        assert_eq!(*first_id, 1);
+        assert_eq!((tileset.columns, tileset.tile_count), (4, 8));
+        assert_eq!(tileset.animation(1).map(|frames| frames.len()), Some(2));
+        // The transparent color is keyed out
+        assert_eq!(tileset.sprite.pixel(0, 0).a, 0);
+
+        // Image layers are skipped and groups are flattened
+        let names: Vec<&str> = map.layers.iter().map(MapLayer::name).collect();
+        assert_eq!(names, ["ground", "grass", "objects"]);
+
+        let ground = map.tile_layer("ground").unwrap();
+        assert_eq!(tiles(ground), [Some(1), None, Some(2), Some(3)]);
+        let flipped = ground.tile(0, 1).unwrap();
+        assert!(flipped.flip_horizontal && !flipped.flip_vertical && !flipped.flip_diagonal);
+
+        let grass = map.tile_layer("grass").unwrap();
+        assert_eq!(grass.offset, cgmath::Vector2::new(5.0, 0.0));
+        assert_eq!(grass.opacity, 0.25);
+
+        let objects = map.object_layer("objects").unwrap();
+        let spawn = objects.object("spawn").unwrap();
+        assert_eq!((spawn.id, spawn.class.as_str()), (7, "player"));
+        assert_eq!(spawn.position, cgmath::Point2::new(8.0, 24.0));
+        assert_eq!(spawn.shape, ObjectShape::Point);
+        assert!(matches!(
+            spawn.properties.get("lives"),
+            Some(PropertyValue::Int(3))
+        ));
+        let area = objects.object("area").unwrap();
+        assert!(matches!(&area.shape, ObjectShape::Polygon(points) if points.len() == 3));
+        assert!((area.rotation.0 - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn xml_map_with_external_tileset_is_imported() {
+        let directory = directory("xml");
+        std::fs::write(
+            directory.join("terrain.tsx"),
+            r#"<tileset name="terrain" tilewidth="16" tileheight="16" tilecount="8" columns="4">
+                <image source="tiles.png" width="64" height="32"/>
+                <tile id="2">
+                    <properties><property name="solid" type="bool" value="true"/></properties>
+                </tile>
+            </tileset>"#,
+        )
+        .unwrap();
+
+        let mut encoder = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+        for id in [5_u32, 0, 0, 6] {
+            encoder.write_all(&id.to_le_bytes()).unwrap();
+        }
+        let compressed =
+            base64::engine::general_purpose::STANDARD.encode(encoder.finish().unwrap());
+
+        let tmx = format!(
+            r#"<map orientation="orthogonal" width="2" height="2" tilewidth="16" tileheight="16">
+                <tileset firstgid="5" source="terrain.tsx"/>
+                <layer name="csv" width="2" height="2">
+                    <data encoding="csv">5,6,
+                    0,7</data>
+                </layer>
+                <layer name="zlib" width="2" height="2" visible="0">
+                    <data encoding="base64" compression="zlib">{}</data>
+                </layer>
+                <layer name="plain" width="2" height="1">
+                    <data><tile gid="8"/><tile/></data>
+                </layer>
+                <objectgroup name="objects">
+                    <object id="1" name="sign" x="4" y="8"><text>Hello</text></object>
+                    <object id="2" name="crate" gid="6" x="0" y="16" width="16" height="16"/>
+                </objectgroup>
+            </map>"#,
+            compressed
+        );
+        let map = Tilemap::from_tmx(&tmx, &directory).unwrap();
+
+        assert_eq!(map.tilesets()[0].0, 5);
+        let solid = map.tile_properties(7).and_then(|p| p.get("solid"));
+        assert!(matches!(solid, Some(PropertyValue::Bool(true))));
+
+        let csv = map.tile_layer("csv").unwrap();
+        assert_eq!(tiles(csv), [Some(5), Some(6), None, Some(7)]);
+        let zlib = map.tile_layer("zlib").unwrap();
+        assert_eq!(tiles(zlib), [Some(5), None, None, Some(6)]);
+        assert!(!zlib.visible);
+        let plain = map.tile_layer("plain").unwrap();
+        assert_eq!(tiles(plain), [Some(8), None]);
+
+        let objects = map.object_layer("objects").unwrap();
+        assert_eq!(
+            objects.object("sign").unwrap().shape,
+            ObjectShape::Text("Hello".to_string())
+        );
+        assert_eq!(
+            objects.object("crate").unwrap().shape,
+            ObjectShape::Tile(Tile::new(6))
+        );
+    }
+
+    #[test]
+    fn file_is_imported_by_its_extension() {
+        let directory = directory("file");
+        let path = directory.join("level.tmj");
+        std::fs::write(&path, TMJ).unwrap();
+        assert!(Tilemap::from_tiled_file(&path).is_ok());
+
+        let missing = Tilemap::from_tiled_file(directory.join("missing.tmx"));
+        let error = format!("{:#}", missing.err().unwrap());
+        assert!(error.starts_with("Failed to read map"), "{}", error);
+    }
+
+    #[test]
+    fn unsupported_maps_are_rejected() {
+        let directory = Path::new("");
+        let error = |json: &str| format!("{:#}", Tilemap::from_tmj(json, directory).err().unwrap());
+
+        assert!(error(
+            r#"{"orientation": "isometric", "width": 1, "height": 1,
+                "tilewidth": 16, "tileheight": 16}"#
+        )
+        .contains("isometric maps are not supported"));
+        assert!(error(
+            r#"{"infinite": true, "width": 1, "height": 1, "tilewidth": 16, "tileheight": 16}"#
+        )
+        .contains("Infinite maps are not supported"));
+        assert!(error(
+            r#"{"width": 1, "height": 1, "tilewidth": 16, "tileheight": 16, "tilesets": [
+                {"firstgid": 1, "name": "props", "tilewidth": 16, "tileheight": 16}
+            ]}"#
+        )
+        .contains("image collection"));
+        assert!(error(
+            r#"{"width": 2, "height": 1, "tilewidth": 16, "tileheight": 16, "layers": [
+                {"type": "tilelayer", "name": "ground", "width": 2, "height": 1, "data": [1]}
+            ]}"#
+        )
+        .contains("Layer ground has 1 tiles instead of 2"));
+
+        let chunked = r#"<map width="1" height="1" tilewidth="16" tileheight="16">
+            <layer name="ground" width="1" height="1">
+                <data encoding="csv"><chunk x="0" y="0" width="1" height="1">1</chunk></data>
+            </layer>
+        </map>"#;
+        let error = format!("{:#}", Tilemap::from_tmx(chunked, directory).err().unwrap());
+        assert!(
+            error.contains("Infinite maps are not supported"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn global_ids_are_split_into_tiles_and_flips() {
+        assert_eq!(tile_from_id(0), None);
+        assert_eq!(tile_from_id(FLIPPED_HORIZONTALLY), None);
+        let tile = tile_from_id(3 | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL);
+        assert_eq!(
+            tile,
+            Some(Tile {
+                id: 3,
+                flip_horizontal: false,
+                flip_vertical: true,
+                flip_diagonal: true,
+            })
+        );
+    }
+
+    #[test]
+    fn colors_are_parsed_with_optional_alpha() {
+        let rgba = |color: Color| (color.r, color.g, color.b, color.a);
+        assert_eq!(rgba(parse_color("#ff8000").unwrap()), (255, 128, 0, 255));
+        assert_eq!(rgba(parse_color("80ff8000").unwrap()), (255, 128, 0, 128));
+        assert_eq!(rgba(parse_color("").unwrap()), (0, 0, 0, 0));
+        assert!(parse_color("#ff80").is_err());
+        assert!(parse_color("#gg8000").is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use super::{CameraBounds, MapLayer, Sprite, SpriteBatch, SpriteInstance, Tilemap};
+use crate::gfx::texture::Texture;
+use std::collections::BTreeMap;
+
+/// Width and height in tiles of the parts of a tile layer drawn by one sprite batch
+const CHUNK_SIZE: u32 = 32;
+
+/// Instances of a chunk collected while the layers are walked
+#[derive(Default)]
+struct ChunkTiles {
+    instances: Vec<SpriteInstance>,
+    animated: Vec<(usize, u32)>,
+}
+
+/// Tiles of one tileset in a square part of a tile layer
+struct TileChunk {
+    sprite_batch: SpriteBatch,
+    tileset: usize,
+    /// Area of the world the tiles cover
+    bounds: CameraBounds,
+    /// Instances showing animated tiles with the ids of the tiles in the tileset
+    animated: Vec<(usize, u32)>,
+    changed: bool,
+}
+
+/// Tile layers of a `Tilemap` drawn by the GPU as sprite batches
+///
+/// The layers are split into chunks, so only the chunks in the view of the camera are drawn
+/// and only the instances of animated tiles are changed after the batches are built
+pub(super) struct TilemapBatch {
+    pub(super) name: String,
+    /// Kept alive for the bind groups of the chunks
+    _textures: Vec<Texture>,
+    chunks: Vec<TileChunk>,
+}
+
+impl TilemapBatch {
+    /// Builds the batches of the visible tile layers of the map
+    /// with its top left corner at `position` in world coordinates
+    pub(super) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: &str,
+        tilemap: &Tilemap,
+        position: cgmath::Point2<f32>,
+    ) -> Self {
+        let textures: Vec<Texture> = tilemap
+            .tilesets()
+            .iter()
+            .map(|(_, tileset)| {
+                Texture::from_bytes_rgba(
+                    device,
+                    queue,
+                    &Self::tileset_bytes(&tileset.sprite),
+                    tileset.sprite.width(),
+                    tileset.sprite.height(),
+                    true,
+                    Some(&format!("{} tileset texture", tileset.name)),
+                )
+            })
+            .collect();
+
+        let mut chunks = vec![];
+        let (tile_width, tile_height) = (tilemap.tile_width as f32, tilemap.tile_height as f32);
+        for layer in &tilemap.layers {
+            let layer = match layer {
+                MapLayer::Tiles(layer) if layer.visible && layer.opacity > 0.0 => layer,
+                _ => continue,
+            };
+            let origin = position + layer.offset;
+            let alpha = (layer.opacity.min(1.0) * 255.0).round() as u8;
+
+            // Tiles by the row and the column of the chunk and the tileset, in the order of drawing
+            let mut layer_chunks: BTreeMap<(u32, u32, usize), ChunkTiles> = BTreeMap::new();
+            for y in 0..layer.height() {
+                for x in 0..layer.width() {
+                    let tile = match layer.tile(x, y) {
+                        Some(tile) => tile,
+                        None => continue,
+                    };
+                    let corner = cgmath::Point2::new(
+                        origin.x + x as f32 * tile_width,
+                        origin.y + (y + 1) as f32 * tile_height,
+                    );
+                    if let Some((tileset, id, instance)) =
+                        tilemap.tile_instance(tile, corner, alpha)
+                    {
+                        let chunk = layer_chunks
+                            .entry((y / CHUNK_SIZE, x / CHUNK_SIZE, tileset))
+                            .or_default();
+                        if tilemap.tilesets()[tileset].1.animation(id).is_some() {
+                            chunk.animated.push((chunk.instances.len(), id));
+                        }
+                        chunk.instances.push(instance);
+                    }
+                }
+            }
+
+            for ((_, _, tileset), tiles) in layer_chunks {
+                let mut sprite_batch = SpriteBatch::from_texture(
+                    device,
+                    &format!("{} tiles", name),
+                    &textures[tileset],
+                );
+                let bounds = Self::bounds(&tiles.instances, sprite_batch.texture_size());
+                sprite_batch.instances = tiles.instances;
+                chunks.push(TileChunk {
+                    sprite_batch,
+                    tileset,
+                    bounds,
+                    animated: tiles.animated,
+                    changed: true,
+                });
+            }
+        }
+
+        Self {
+            name: name.to_string(),
+            _textures: textures,
+            chunks,
+        }
+    }
+
+    /// Shows the frames of the animated tiles at the current time of the map
+    pub(super) fn update_animations(&mut self, tilemap: &Tilemap) {
+        for chunk in &mut self.chunks {
+            let tileset = match tilemap.tilesets().get(chunk.tileset) {
+                Some((_, tileset)) => tileset,
+                None => continue,
+            };
+            for (index, id) in &chunk.animated {
+                let uv_rect = tileset.tile_uv_rect(tileset.animated_tile(*id, tilemap.time()));
+                let instance = &mut chunk.sprite_batch.instances[*index];
+                if instance.uv_rect != uv_rect {
+                    instance.uv_rect = uv_rect;
+                    chunk.changed = true;
+                }
+            }
+        }
+    }
+
+    /// Uploads the instances of the chunks changed since the last upload
+    pub(super) fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        for chunk in self.chunks.iter_mut().filter(|chunk| chunk.changed) {
+            chunk.sprite_batch.update_buffer(device, queue);
+            chunk.changed = false;
+        }
+    }
+
+    /// Draws the chunks overlapping the `visible_area` in world coordinates,
+    /// expects the pipeline and the quad's buffers to be set
+    pub(super) fn render<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        visible_area: &CameraBounds,
+    ) {
+        for chunk in &self.chunks {
+            let bounds = &chunk.bounds;
+            if bounds.min.x <= visible_area.max.x
+                && bounds.max.x >= visible_area.min.x
+                && bounds.min.y <= visible_area.max.y
+                && bounds.max.y >= visible_area.min.y
+            {
+                chunk.sprite_batch.render(render_pass);
+            }
+        }
+    }
+
+    /// Returns the area covering the instances however they are rotated
+    fn bounds(instances: &[SpriteInstance], texture_size: (u32, u32)) -> CameraBounds {
+        let mut min = cgmath::Point2::new(f32::INFINITY, f32::INFINITY);
+        let mut max = cgmath::Point2::new(f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for instance in instances {
+            let [min_u, min_v, max_u, max_v] = instance.uv_rect;
+            let extent = ((max_u - min_u) * texture_size.0 as f32)
+                .max((max_v - min_v) * texture_size.1 as f32)
+                / 2.0;
+            min.x = min.x.min(instance.position.x - extent);
+            min.y = min.y.min(instance.position.y - extent);
+            max.x = max.x.max(instance.position.x + extent);
+            max.y = max.y.max(instance.position.y + extent);
+        }
+        CameraBounds::new(min, max)
+    }
+
+    /// Returns the RGBA bytes of the tileset's sprite, which holds straight colors
+    /// like the images loaded by `Sprite::from_image` and the sprite batches expect
+    fn tileset_bytes(sprite: &Sprite) -> Vec<u8> {
+        sprite
+            .values
+            .iter()
+            .flat_map(|color| [color.r, color.g, color.b, color.a])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_transparent_tiles_keep_their_colors() {
+        let directory = std::env::temp_dir().join("tilemap_batch_test_alpha");
+        std::fs::create_dir_all(&directory).unwrap();
+        let mut image = image::RgbaImage::from_pixel(2, 1, image::Rgba([10, 20, 30, 255]));
+        image.put_pixel(1, 0, image::Rgba([200, 100, 50, 128]));
+        image.save(directory.join("tiles.png")).unwrap();
+
+        let tmj = r#"{
+            "width": 2, "height": 1, "tilewidth": 1, "tileheight": 1,
+            "tilesets": [{"firstgid": 1, "name": "glass", "tilewidth": 1, "tileheight": 1,
+                          "image": "tiles.png"}],
+            "layers": [{"type": "tilelayer", "name": "windows", "width": 2, "height": 1,
+                        "data": [1, 2]}]
+        }"#;
+        let map = Tilemap::from_tmj(tmj, &directory).unwrap();
+        let (_, tileset) = &map.tilesets()[0];
+        assert_eq!(
+            TilemapBatch::tileset_bytes(&tileset.sprite),
+            [10, 20, 30, 255, 200, 100, 50, 128]
+        );
+    }
+}
@@ -176,17 +176,9 @@ impl Renderer3D {
         });
 
         if self.window_settings.resize_mode == ResizeMode::KeepAspectRatio {
-            let aspect = self.window_settings.logical_width as f32
-                / self.window_settings.logical_height as f32;
             // set up scissors rect with constant aspect ratio that stays in the center
-            let (width, height): (f32, f32) = self.screen_size.to_logical::<f32>(1.0).into();
-            let (scissors_width, scissors_height) = if width > height * aspect {
-                (height * aspect, height)
-            } else {
-                (width, width / aspect)
-            };
-            let scissors_x = (width - scissors_width) / 2.0;
-            let scissors_y = (height - scissors_height) / 2.0;
+            let (scissors_x, scissors_y, scissors_width, scissors_height) =
+                self.window_settings.game_area(self.screen_size);
             render_pass.set_scissor_rect(
                 scissors_x as u32,
                 scissors_y as u32,
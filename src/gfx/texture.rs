@@ -142,7 +142,8 @@ pub(in crate::gfx) const DEPTH_TEXTURE_FORMAT: wgpu::TextureFormat =
     wgpu::TextureFormat::Depth32Float;
 
 pub struct Texture {
-    pub(crate) _texture: wgpu::Texture,
+    pub(crate) texture: wgpu::Texture,
+    pub(crate) size: wgpu::Extent3d,
     pub(crate) view: wgpu::TextureView,
     pub(crate) sampler: wgpu::Sampler,
 }
@@ -152,13 +153,14 @@ impl Texture {
         device: &wgpu::Device,
         surface_config: &wgpu::SurfaceConfiguration,
     ) -> Self {
+        let size = wgpu::Extent3d {
+            width: surface_config.width,
+            height: surface_config.height,
+            depth_or_array_layers: 1,
+        };
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("depth_texture"),
-            size: wgpu::Extent3d {
-                width: surface_config.width,
-                height: surface_config.height,
-                depth_or_array_layers: 1,
-            },
+            size,
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
@@ -186,7 +188,8 @@ impl Texture {
         });
 
         Self {
-            _texture: texture,
+            texture,
+            size,
             view,
             sampler,
         }
@@ -291,12 +294,43 @@ impl Texture {
         });
 
         Self {
-            _texture: texture,
+            texture,
+            size: texture_size,
             view,
             sampler,
         }
     }
 
+    /// Writes the rectangle at `x`, `y` of `width` by `height` pixels from `bytes`,
+    /// which hold RGBA pixels of the whole texture
+    pub(crate) fn write_region(
+        &self,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        texture_width: u32,
+        (x, y, width, height): (u32, u32, u32, u32),
+    ) {
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: Default::default(),
+            },
+            bytes,
+            wgpu::ImageDataLayout {
+                offset: ((y * texture_width + x) * 4) as wgpu::BufferAddress,
+                bytes_per_row: std::num::NonZeroU32::new(4 * texture_width),
+                rows_per_image: std::num::NonZeroU32::new(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
     pub(crate) fn texture_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("texture_bind_group_layout"),
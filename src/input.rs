@@ -1,10 +1,22 @@
-use log::info;
+use crate::{ResizeMode, WindowSettings};
+use log::{info, warn};
+use std::collections::HashMap;
 use winit::{
-    dpi::PhysicalPosition,
-    event::{ElementState, KeyboardInput, MouseScrollDelta, WindowEvent},
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{DeviceEvent, ElementState, KeyboardInput, MouseScrollDelta, Touch, WindowEvent},
+    window::Window,
 };
 
-pub use winit::event::{MouseButton, VirtualKeyCode};
+mod key_names;
+mod pointer;
+mod sequence;
+mod touch;
+pub use pointer::*;
+use sequence::{BufferedAction, InputHistory};
+pub use sequence::{InputSequence, SequenceStep};
+pub use touch::*;
+
+pub use winit::event::{MouseButton, ScanCode, VirtualKeyCode};
 
 /// Describes current direction of the scroll wheel
 #[derive(Debug, Copy, Clone)]
@@ -14,9 +26,12 @@ pub enum ScrollDirection {
     None,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-enum Pressable {
+/// Things that could be pressed
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Pressable {
     KeyboardKey(VirtualKeyCode),
+    /// Keyboard key identified by its position, which does not depend on the keyboard layout
+    PhysicalKey(ScanCode),
     MouseButton(MouseButton),
 }
 
@@ -39,6 +54,16 @@ impl PressableState {
         }
     }
 
+    /// Creates a new instance of the struct using physical keyboard key as an input
+    fn new_physical_key(scancode: &ScanCode) -> Self {
+        let button = Pressable::PhysicalKey(*scancode);
+        Self {
+            button,
+            current_state: ElementState::Released,
+            previous_state: ElementState::Released,
+        }
+    }
+
     /// Creates a new instance of the struct using mouse button as an input
     fn new_mouse_button(button: &MouseButton) -> Self {
         let button = Pressable::MouseButton(*button);
@@ -103,21 +128,79 @@ pub struct InputHandler {
     current_cursor_position: cgmath::Point2<f32>,
     previous_cursor_position: cgmath::Point2<f32>,
     cursor_delta: cgmath::Vector2<f32>,
+    mouse_motion: cgmath::Vector2<f32>,
+    cursor_grabbed: bool,
+    cursor_visible: bool,
+    cursor_state_changed: bool,
+    focused: bool,
     scroll_direction: ScrollDirection,
     scroll_delta: f32,
+    window_settings: Option<WindowSettings>,
+    frame_size: PhysicalSize<u32>,
+    scale_factor: f64,
+    pointer_tracker: PointerTracker,
+    touch_tracker: TouchTracker,
+    /// Virtual keys produced by the physical keys in the current keyboard layout
+    physical_key_codes: HashMap<ScanCode, VirtualKeyCode>,
+    history: InputHistory,
+    buffered_actions: HashMap<String, BufferedAction>,
+    /// Time in seconds since the creation, advances once per frame
+    time: f64,
+    frame: u64,
 }
 
 impl InputHandler {
     /// Creates a new instance
-    pub(crate) fn new() -> Self {
+    ///
+    /// Without window settings the game coordinates of the cursor match the window's pixels
+    pub fn new() -> Self {
         info!("Creating input handler");
         Self {
             active_keys: vec![],
             current_cursor_position: cgmath::Point2::new(0.0, 0.0),
             previous_cursor_position: cgmath::Point2::new(0.0, 0.0),
             cursor_delta: cgmath::Vector2::new(0.0, 0.0),
+            mouse_motion: cgmath::Vector2::new(0.0, 0.0),
+            cursor_grabbed: false,
+            cursor_visible: true,
+            cursor_state_changed: false,
+            focused: true,
             scroll_direction: ScrollDirection::None,
             scroll_delta: 0.0,
+            window_settings: None,
+            frame_size: PhysicalSize::new(0, 0),
+            scale_factor: 1.0,
+            pointer_tracker: PointerTracker::new(),
+            touch_tracker: TouchTracker::new(),
+            physical_key_codes: HashMap::new(),
+            history: InputHistory::new(),
+            buffered_actions: HashMap::new(),
+            time: 0.0,
+            frame: 0,
+        }
+    }
+
+    /// Creates a new instance with the size and the DPI scale factor of the `window`
+    ///
+    /// Instances created with `new` don't know the size of the window until it is resized,
+    /// use `simulate_resize` to set it without a window
+    pub fn with_window(window: &Window) -> Self {
+        Self {
+            frame_size: window.inner_size(),
+            scale_factor: window.scale_factor(),
+            ..Self::new()
+        }
+    }
+
+    /// Creates a new instance that maps the cursor onto the logical resolution of the game
+    pub fn with_window_settings(window_settings: WindowSettings) -> Self {
+        Self {
+            window_settings: Some(window_settings),
+            frame_size: PhysicalSize::new(
+                window_settings.logical_width,
+                window_settings.logical_height,
+            ),
+            ..Self::new()
         }
     }
 
@@ -130,12 +213,60 @@ impl InputHandler {
                 self.accept_mouse_button_input(state, button)
             }
             WindowEvent::CursorMoved { position, .. } => self.accept_cursor_input(position),
+            WindowEvent::CursorLeft { .. } => self.accept_cursor_left(),
+            WindowEvent::Resized(size) => self.accept_resize(*size, self.scale_factor),
+            WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size,
+            } => self.accept_resize(**new_inner_size, *scale_factor),
+            WindowEvent::Focused(focused) => self.accept_focus(*focused),
+            WindowEvent::Touch(touch) => self.accept_touch_input(touch),
             _ => {}
         }
     }
 
+    /// Accepts raw input event from the device
+    pub(crate) fn accept_device_input(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            self.accept_mouse_motion_input(delta);
+        }
+    }
+
+    /// Stores the size of the window and its DPI scale factor
+    pub(crate) fn accept_resize(&mut self, frame_size: PhysicalSize<u32>, scale_factor: f64) {
+        self.frame_size = frame_size;
+        self.scale_factor = scale_factor;
+    }
+
+    /// Stores whether the window has focus and releases the cursor when it is lost
+    fn accept_focus(&mut self, focused: bool) {
+        self.focused = focused;
+        if !focused {
+            self.unlock_cursor();
+            self.pointer_tracker
+                .cancel_presses(self.current_cursor_position);
+        }
+    }
+
     /// Handles all that can be pressed
     fn handle_pressable(&mut self, button: &mut PressableState, state: &ElementState) {
+        let was_held = self.held_inputs().contains(&button.button);
+        self.update_pressable(button, state);
+
+        let is_held = *state == ElementState::Pressed;
+        if was_held != is_held {
+            self.history
+                .record(self.held_inputs(), self.time, self.frame);
+            if is_held {
+                for action in self.buffered_actions.values_mut() {
+                    action.accept_press(&button.button, self.time);
+                }
+            }
+        }
+    }
+
+    /// Stores the new state of the pressable
+    fn update_pressable(&mut self, button: &mut PressableState, state: &ElementState) {
         match state {
             ElementState::Pressed => {
                 if !self.active_keys.contains(button) {
@@ -155,11 +286,36 @@ impl InputHandler {
         }
     }
 
-    /// Updates and resets values
-    pub(crate) fn update_input_state(&mut self) {
+    /// Returns all keys and mouse buttons that are currently pressed
+    fn held_inputs(&self) -> Vec<Pressable> {
+        self.active_keys
+            .iter()
+            .filter(|key| key.current_state == ElementState::Pressed)
+            .map(|key| key.button)
+            .collect()
+    }
+
+    /// Updates and resets values after the frame that took `dt` seconds
+    pub(crate) fn update_input_state(&mut self, dt: f32) {
+        self.time += dt as f64;
+        self.frame += 1;
+        self.history.trim(self.time);
+        for action in self.buffered_actions.values_mut() {
+            action.update(self.time);
+        }
         self.reset_scroll();
         self.reset_cursor_delta();
+        self.reset_mouse_motion();
         self.update_key_state();
+        self.pointer_tracker
+            .update(self.current_cursor_position, self.time);
+        self.touch_tracker.update(self.time);
+    }
+}
+
+impl Default for InputHandler {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -170,16 +326,39 @@ impl InputHandler {
 impl InputHandler {
     /// Handles processing and storage of keyboard's input
     fn accept_keyboard_input(&mut self, keyboard_input: &KeyboardInput) {
-        match keyboard_input {
-            KeyboardInput {
-                state,
-                virtual_keycode: Some(key_code),
-                ..
-            } => {
-                let mut button = PressableState::new_keyboard_key(key_code);
-                self.handle_pressable(&mut button, state);
+        self.accept_key(
+            &keyboard_input.scancode,
+            keyboard_input.virtual_keycode.as_ref(),
+            &keyboard_input.state,
+        );
+    }
+
+    /// Handles both the physical and the virtual key of a single key press or release
+    fn accept_key(
+        &mut self,
+        scancode: &ScanCode,
+        key_code: Option<&VirtualKeyCode>,
+        state: &ElementState,
+    ) {
+        let mut button = PressableState::new_physical_key(scancode);
+        self.handle_pressable(&mut button, state);
+
+        if let Some(key_code) = key_code {
+            let mut button = PressableState::new_keyboard_key(key_code);
+            self.handle_pressable(&mut button, state);
+
+            if *state == ElementState::Pressed {
+                self.physical_key_codes.insert(*scancode, *key_code);
             }
-            _ => {}
+        }
+    }
+
+    /// Returns readable name of the virtual key, e.g. `1` instead of `Key1`
+    fn key_code_name(key_code: &VirtualKeyCode) -> String {
+        let name = format!("{:?}", key_code);
+        match name.strip_prefix("Key") {
+            Some(digit) if !digit.is_empty() => digit.to_string(),
+            _ => name,
         }
     }
 
@@ -219,6 +398,67 @@ impl InputHandler {
         !self.active_keys.contains(&key)
     }
 
+    /// Returns true on the first frame when the physical keyboard key is pressed
+    pub fn is_physical_key_down(&self, scancode: &ScanCode) -> bool {
+        let key = PressableState::new_physical_key(scancode);
+        if let Some(index) = self.active_keys.iter().position(|el| el == &key) {
+            return self.active_keys[index].is_down();
+        }
+
+        false
+    }
+
+    /// Returns true while the physical keyboard key is held down
+    pub fn is_physical_key_held(&self, scancode: &ScanCode) -> bool {
+        let key = PressableState::new_physical_key(scancode);
+        if let Some(index) = self.active_keys.iter().position(|el| el == &key) {
+            return self.active_keys[index].is_held();
+        }
+
+        false
+    }
+
+    /// Returns true on the first frame when the physical keyboard key is released
+    pub fn is_physical_key_released(&self, scancode: &ScanCode) -> bool {
+        let key = PressableState::new_physical_key(scancode);
+        if let Some(index) = self.active_keys.iter().position(|el| el == &key) {
+            return self.active_keys[index].is_released();
+        }
+
+        false
+    }
+
+    /// Returns true while the physical keyboard key is not pressed
+    pub fn is_physical_key_up(&self, scancode: &ScanCode) -> bool {
+        let key = PressableState::new_physical_key(scancode);
+        !self.active_keys.contains(&key)
+    }
+
+    /// Returns physical keys pressed during the current frame, e.g. to rebind a control
+    pub fn physical_keys_down(&self) -> Vec<ScanCode> {
+        self.active_keys
+            .iter()
+            .filter(|key| key.is_down())
+            .filter_map(|key| match key.button {
+                Pressable::PhysicalKey(scancode) => Some(scancode),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the name of the physical key in the current keyboard layout, e.g. to label bindings
+    ///
+    /// The name is the one of the virtual key the key produces, e.g. `Q` for the key left of `W`
+    /// on QWERTY and `A` on AZERTY. Keys that haven't been pressed yet are named after
+    /// the virtual key at their position on the US QWERTY layout
+    pub fn physical_key_name(&self, scancode: &ScanCode) -> Option<String> {
+        self.physical_key_codes
+            .get(scancode)
+            .copied()
+            .or_else(|| key_names::default_key_code(*scancode))
+            .map(|key_code| Self::key_code_name(&key_code))
+    }
+
     /// Updates the state of all active keys and removes those which are no longer active
     fn update_key_state(&mut self) {
         self.active_keys = self
@@ -240,6 +480,18 @@ impl InputHandler {
 impl InputHandler {
     /// Handles processing and storage of mouse buttons' input
     fn accept_mouse_button_input(&mut self, state: &ElementState, button: &MouseButton) {
+        match state {
+            ElementState::Pressed => {
+                self.pointer_tracker
+                    .accept_press(*button, self.current_cursor_position, self.time)
+            }
+            ElementState::Released => self.pointer_tracker.accept_release(
+                *button,
+                self.current_cursor_position,
+                self.time,
+            ),
+        }
+
         let mut button = PressableState::new_mouse_button(button);
         self.handle_pressable(&mut button, state);
     }
@@ -279,6 +531,45 @@ impl InputHandler {
         let key = PressableState::new_mouse_button(key_code);
         !self.active_keys.contains(&key)
     }
+
+    /// Returns true on the frame when the mouse button is clicked
+    pub fn is_mouse_button_clicked(&self, button: &MouseButton) -> bool {
+        self.pointer_tracker
+            .events()
+            .iter()
+            .any(|e| matches!(e, PointerEvent::Click { button: b, .. } if b == button))
+    }
+
+    /// Returns true on the frame when the mouse button is double-clicked
+    pub fn is_mouse_button_double_clicked(&self, button: &MouseButton) -> bool {
+        self.pointer_tracker
+            .events()
+            .iter()
+            .any(|e| matches!(e, PointerEvent::DoubleClick { button: b, .. } if b == button))
+    }
+
+    /// Returns true on the frame when the mouse button has been held long enough for a long press
+    pub fn is_mouse_button_long_pressed(&self, button: &MouseButton) -> bool {
+        self.pointer_tracker
+            .events()
+            .iter()
+            .any(|e| matches!(e, PointerEvent::LongPress { button: b, .. } if b == button))
+    }
+
+    /// Returns the point where the drag started while the mouse button is being dragged
+    pub fn mouse_drag_origin(&self, button: &MouseButton) -> Option<cgmath::Point2<f32>> {
+        self.pointer_tracker.drag_origin(*button)
+    }
+
+    /// Returns clicks, long presses and drags of the mouse buttons during the current frame
+    pub fn pointer_events(&self) -> &[PointerEvent] {
+        self.pointer_tracker.events()
+    }
+
+    /// Returns thresholds used to recognize pointer events
+    pub fn pointer_settings(&mut self) -> &mut PointerSettings {
+        &mut self.pointer_tracker.settings
+    }
 }
 
 // o-----------------------------------o
@@ -291,6 +582,14 @@ impl InputHandler {
         self.previous_cursor_position = self.current_cursor_position;
         self.current_cursor_position = cgmath::Point2::new(position.x as f32, position.y as f32);
         self.cursor_delta = self.current_cursor_position - self.previous_cursor_position;
+        self.pointer_tracker
+            .accept_cursor_move(self.current_cursor_position, self.cursor_delta);
+    }
+
+    /// Ends drags, since the release of the button may happen outside of the window
+    fn accept_cursor_left(&mut self) {
+        self.pointer_tracker
+            .cancel_presses(self.current_cursor_position);
     }
 
     /// Returns current cursor position on the screen
@@ -298,6 +597,62 @@ impl InputHandler {
         self.current_cursor_position
     }
 
+    /// Returns current cursor position in logical pixels of the window,
+    /// i.e. divided by the DPI scale factor
+    pub fn cursor_logical_position(&self) -> cgmath::Point2<f32> {
+        self.current_cursor_position / self.scale_factor as f32
+    }
+
+    /// Returns current cursor position in game coordinates
+    ///
+    /// With `ResizeMode::KeepAspectRatio` the position is mapped from the letterboxed area
+    /// onto the logical resolution from `WindowSettings`, so it can be compared with
+    /// positions on the 2D surfaces regardless of the window's size and scale factor.
+    /// In other modes the game covers the whole window and the position is in its logical pixels
+    pub fn cursor_game_position(&self) -> cgmath::Point2<f32> {
+        self.game_position(self.current_cursor_position)
+    }
+
+    /// Converts `position` in window's pixels to game coordinates
+    /// the same way `cursor_game_position` does
+    pub fn game_position(&self, position: cgmath::Point2<f32>) -> cgmath::Point2<f32> {
+        match self.window_settings {
+            Some(
+                window_settings @ WindowSettings {
+                    resize_mode: ResizeMode::KeepAspectRatio,
+                    ..
+                },
+            ) => {
+                let (x, y, width, height) = window_settings.game_area(self.frame_size);
+                if width <= 0.0 || height <= 0.0 {
+                    return cgmath::Point2::new(0.0, 0.0);
+                }
+                cgmath::Point2::new(
+                    (position.x - x) / width * window_settings.logical_width as f32,
+                    (position.y - y) / height * window_settings.logical_height as f32,
+                )
+            }
+            _ => position / self.scale_factor as f32,
+        }
+    }
+
+    /// Returns true if the cursor is inside of the area of the window the game is drawn to
+    ///
+    /// With `ResizeMode::KeepAspectRatio` the black bars around the game are not a part of the area
+    pub fn is_cursor_in_game_area(&self) -> bool {
+        let (x, y, width, height) = match self.window_settings {
+            Some(window_settings) => window_settings.game_area(self.frame_size),
+            None => (
+                0.0,
+                0.0,
+                self.frame_size.width as f32,
+                self.frame_size.height as f32,
+            ),
+        };
+        let position = self.current_cursor_position;
+        position.x >= x && position.x < x + width && position.y >= y && position.y < y + height
+    }
+
     /// Returns the difference between cursor's position during the current frame
     /// and during the previous frame
     pub fn cursor_delta(&self) -> cgmath::Vector2<f32> {
@@ -310,6 +665,89 @@ impl InputHandler {
     }
 }
 
+// o-----------------------------------o
+// |          MOUSE MOTION             |
+// o-----------------------------------o
+/// Methods related to processing of the raw mouse motion and locking of the cursor
+impl InputHandler {
+    /// Handles processing and storage of raw mouse motion
+    fn accept_mouse_motion_input(&mut self, delta: &(f64, f64)) {
+        if self.focused {
+            self.mouse_motion += cgmath::Vector2::new(delta.0 as f32, delta.1 as f32);
+        }
+    }
+
+    /// Returns raw mouse motion accumulated during the current frame
+    ///
+    /// Unlike `cursor_delta` it does not stop at the edges of the window
+    /// and is not affected by the pointer acceleration of the OS
+    pub fn mouse_motion(&self) -> cgmath::Vector2<f32> {
+        self.mouse_motion
+    }
+
+    /// Resets mouse motion to zero between frames
+    fn reset_mouse_motion(&mut self) {
+        self.mouse_motion = cgmath::Vector2::new(0.0, 0.0);
+    }
+
+    /// Confines the cursor to the window and hides it, e.g. for first-person controls
+    ///
+    /// The cursor is unlocked automatically when the window loses focus
+    pub fn lock_cursor(&mut self) {
+        self.set_cursor_grabbed(true);
+        self.set_cursor_visible(false);
+    }
+
+    /// Releases the cursor and shows it again
+    pub fn unlock_cursor(&mut self) {
+        self.set_cursor_grabbed(false);
+        self.set_cursor_visible(true);
+    }
+
+    /// Returns true while the cursor is confined to the window and hidden
+    pub fn is_cursor_locked(&self) -> bool {
+        self.cursor_grabbed && !self.cursor_visible
+    }
+
+    /// Confines the cursor to the window or releases it
+    pub fn set_cursor_grabbed(&mut self, grabbed: bool) {
+        self.cursor_state_changed |= self.cursor_grabbed != grabbed;
+        self.cursor_grabbed = grabbed;
+    }
+
+    /// Returns true while the cursor is confined to the window
+    pub fn is_cursor_grabbed(&self) -> bool {
+        self.cursor_grabbed
+    }
+
+    /// Shows or hides the cursor while it is over the window
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_state_changed |= self.cursor_visible != visible;
+        self.cursor_visible = visible;
+    }
+
+    /// Returns true while the cursor is shown
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Applies requested grab and visibility of the cursor to the window
+    pub(crate) fn apply_cursor_state(&mut self, window: &Window) {
+        if !self.cursor_state_changed {
+            return;
+        }
+        self.cursor_state_changed = false;
+
+        if let Err(e) = window.set_cursor_grab(self.cursor_grabbed) {
+            warn!("Could not change cursor grab: {}", e);
+            // A hidden cursor that isn't confined could not be found by the user
+            self.cursor_grabbed = false;
+            self.cursor_visible = true;
+        }
+        window.set_cursor_visible(self.cursor_visible);
+    }
+}
+
 // o-----------------------------------o
 // |          SCROLL WHEEL             |
 // o-----------------------------------o
@@ -348,3 +786,319 @@ impl InputHandler {
         self.scroll_delta = 0.0;
     }
 }
+
+// o-----------------------------------o
+// |              TOUCH                |
+// o-----------------------------------o
+/// Methods related to processing of the touch screen's input
+impl InputHandler {
+    /// Handles processing and storage of touch screen's input
+    fn accept_touch_input(&mut self, touch: &Touch) {
+        let position = cgmath::Point2::new(touch.location.x as f32, touch.location.y as f32);
+        self.accept_touch(touch.id, touch.phase, position);
+    }
+
+    /// Maps the `position` in window's pixels to game coordinates, the same ones the cursor uses
+    fn accept_touch(&mut self, id: u64, phase: TouchPhase, position: cgmath::Point2<f32>) {
+        let position = self.game_position(position);
+        self.touch_tracker
+            .accept_touch(id, phase, position, self.time);
+    }
+
+    /// Returns all fingers touching the screen during the current frame,
+    /// including those lifted during it
+    pub fn touches(&self) -> impl Iterator<Item = &TouchPoint> {
+        self.touch_tracker.touches()
+    }
+
+    /// Returns the finger with the `id` if it touches the screen during the current frame
+    pub fn touch(&self, id: u64) -> Option<&TouchPoint> {
+        self.touch_tracker.touches().find(|t| t.id == id)
+    }
+
+    /// Returns gestures recognized during the current frame
+    pub fn gestures(&self) -> &[Gesture] {
+        self.touch_tracker.gestures()
+    }
+
+    /// Returns thresholds used to recognize gestures
+    pub fn gesture_settings(&mut self) -> &mut GestureSettings {
+        &mut self.touch_tracker.settings
+    }
+}
+
+// o-----------------------------------o
+// |     SEQUENCES AND BUFFERING       |
+// o-----------------------------------o
+/// Methods related to recognition of input sequences and buffering of presses
+impl InputHandler {
+    /// Returns true on the frame when the last step of the sequence is performed
+    /// and all previous steps were performed in order within their time windows
+    pub fn is_sequence_completed(&self, sequence: &InputSequence) -> bool {
+        self.history.is_completed(sequence, self.frame)
+    }
+
+    /// Starts remembering presses of any of the `inputs` for `duration` seconds
+    /// under the `name`, e.g. to buffer a jump pressed just before landing
+    pub fn add_buffered_action(&mut self, name: &str, inputs: &[Pressable], duration: f32) {
+        self.buffered_actions
+            .insert(name.to_string(), BufferedAction::new(inputs, duration));
+    }
+
+    /// Stops remembering presses of the action added under the `name`
+    pub fn remove_buffered_action(&mut self, name: &str) {
+        self.buffered_actions.remove(name);
+    }
+
+    /// Returns true if the action has been pressed within its duration and has not been consumed
+    pub fn is_action_buffered(&self, name: &str) -> bool {
+        self.buffered_actions
+            .get(name)
+            .is_some_and(|action| action.pressed_at.is_some())
+    }
+
+    /// Forgets the remembered press of the action
+    ///
+    /// Returns true if there was a press to consume
+    pub fn consume_buffered_action(&mut self, name: &str) -> bool {
+        self.buffered_actions
+            .get_mut(name)
+            .and_then(|action| action.pressed_at.take())
+            .is_some()
+    }
+}
+
+// o-----------------------------------o
+// |         SYNTHETIC INPUT           |
+// o-----------------------------------o
+/// Methods that inject input without a window, e.g. to drive `GameObject::update` from tests.
+/// Injected input goes through the same processing as the input coming from the system.
+impl InputHandler {
+    /// Simulates a press of the keyboard key
+    pub fn simulate_key_press(&mut self, key_code: &VirtualKeyCode) {
+        let mut button = PressableState::new_keyboard_key(key_code);
+        self.handle_pressable(&mut button, &ElementState::Pressed);
+    }
+
+    /// Simulates a release of the keyboard key
+    pub fn simulate_key_release(&mut self, key_code: &VirtualKeyCode) {
+        let mut button = PressableState::new_keyboard_key(key_code);
+        self.handle_pressable(&mut button, &ElementState::Released);
+    }
+
+    /// Simulates a press of the physical keyboard key,
+    /// which produces the virtual `key_code` in the current keyboard layout if there is one
+    pub fn simulate_physical_key_press(
+        &mut self,
+        scancode: &ScanCode,
+        key_code: Option<&VirtualKeyCode>,
+    ) {
+        self.accept_key(scancode, key_code, &ElementState::Pressed);
+    }
+
+    /// Simulates a release of the physical keyboard key
+    pub fn simulate_physical_key_release(
+        &mut self,
+        scancode: &ScanCode,
+        key_code: Option<&VirtualKeyCode>,
+    ) {
+        self.accept_key(scancode, key_code, &ElementState::Released);
+    }
+
+    /// Simulates a press of the mouse button
+    pub fn simulate_mouse_button_press(&mut self, button: &MouseButton) {
+        self.accept_mouse_button_input(&ElementState::Pressed, button);
+    }
+
+    /// Simulates a release of the mouse button
+    pub fn simulate_mouse_button_release(&mut self, button: &MouseButton) {
+        self.accept_mouse_button_input(&ElementState::Released, button);
+    }
+
+    /// Simulates movement of the cursor to the `position` in window's pixels
+    pub fn simulate_cursor_move(&mut self, position: cgmath::Point2<f32>) {
+        self.accept_cursor_input(&PhysicalPosition::new(position.x as f64, position.y as f64));
+    }
+
+    /// Simulates raw motion of the mouse by `delta`
+    pub fn simulate_mouse_motion(&mut self, delta: cgmath::Vector2<f32>) {
+        self.accept_mouse_motion_input(&(delta.x as f64, delta.y as f64));
+    }
+
+    /// Simulates the window gaining or losing focus
+    pub fn simulate_focus(&mut self, focused: bool) {
+        self.accept_focus(focused);
+    }
+
+    /// Simulates the cursor leaving the window
+    pub fn simulate_cursor_left(&mut self) {
+        self.accept_cursor_left();
+    }
+
+    /// Simulates scrolling of the scroll wheel by `delta` lines
+    ///
+    /// Positive values scroll up, negative values scroll down
+    pub fn simulate_scroll(&mut self, delta: f32) {
+        self.accept_scroll_wheel_input(&MouseScrollDelta::LineDelta(0.0, delta));
+    }
+
+    /// Simulates resizing of the window to `width` by `height` physical pixels
+    /// with the DPI `scale_factor`
+    pub fn simulate_resize(&mut self, width: u32, height: u32, scale_factor: f64) {
+        self.accept_resize(PhysicalSize::new(width, height), scale_factor);
+    }
+
+    /// Simulates a touch event of the finger with the `id` at `position` in window's pixels
+    pub fn simulate_touch(&mut self, id: u64, phase: TouchPhase, position: cgmath::Point2<f32>) {
+        self.accept_touch(id, phase, position);
+    }
+
+    /// Moves the input state to the next frame without any time passing
+    ///
+    /// Does the same thing `Game::run` does after all game objects have been updated,
+    /// so keys that were pressed during this frame are considered held during the next one
+    pub fn advance_frame(&mut self) {
+        self.advance_time(0.0);
+    }
+
+    /// Moves the input state to the next frame after the current one took `dt` seconds,
+    /// which lets timed events like long presses and double taps be tested
+    pub fn advance_time(&mut self, dt: f32) {
+        self.update_input_state(dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_goes_through_down_held_released_and_up() {
+        let mut input = InputHandler::new();
+        let key = VirtualKeyCode::Space;
+        assert!(input.is_key_up(&key));
+
+        input.simulate_key_press(&key);
+        assert!(input.is_key_down(&key));
+        assert!(!input.is_key_held(&key));
+
+        input.advance_frame();
+        assert!(!input.is_key_down(&key));
+        assert!(input.is_key_held(&key));
+
+        input.simulate_key_release(&key);
+        assert!(input.is_key_released(&key));
+        assert!(!input.is_key_held(&key));
+
+        input.advance_frame();
+        assert!(!input.is_key_released(&key));
+        assert!(input.is_key_up(&key));
+    }
+
+    #[test]
+    fn repeated_press_of_held_key_is_not_down_again() {
+        let mut input = InputHandler::new();
+        let key = VirtualKeyCode::A;
+        input.simulate_key_press(&key);
+        input.advance_frame();
+
+        input.simulate_key_press(&key);
+        assert!(!input.is_key_down(&key));
+        assert!(input.is_key_held(&key));
+    }
+
+    #[test]
+    fn mouse_button_goes_through_down_held_and_released() {
+        let mut input = InputHandler::new();
+        let button = MouseButton::Left;
+
+        input.simulate_mouse_button_press(&button);
+        assert!(input.is_mouse_button_down(&button));
+        input.advance_frame();
+        assert!(input.is_mouse_button_held(&button));
+        input.simulate_mouse_button_release(&button);
+        assert!(input.is_mouse_button_released(&button));
+        input.advance_frame();
+        assert!(input.is_mouse_button_up(&button));
+    }
+
+    #[test]
+    fn physical_key_is_named_after_its_virtual_key() {
+        let mut input = InputHandler::new();
+        // The key left of W on QWERTY
+        let scancode: ScanCode = 16;
+        assert_eq!(input.physical_key_name(&scancode), Some("Q".to_string()));
+
+        input.simulate_physical_key_press(&scancode, Some(&VirtualKeyCode::A));
+        assert!(input.is_physical_key_down(&scancode));
+        assert_eq!(input.physical_keys_down(), vec![scancode]);
+        assert_eq!(input.physical_key_name(&scancode), Some("A".to_string()));
+    }
+
+    #[test]
+    fn cursor_is_scaled_outside_of_kept_aspect_ratio() {
+        let mut input = InputHandler::new();
+        input.simulate_resize(1600, 1200, 2.0);
+        input.simulate_cursor_move(cgmath::Point2::new(200.0, 100.0));
+        assert_eq!(
+            input.cursor_game_position(),
+            cgmath::Point2::new(100.0, 50.0)
+        );
+    }
+
+    fn letterboxed() -> InputHandler {
+        InputHandler::with_window_settings(WindowSettings {
+            logical_width: 320,
+            logical_height: 240,
+            resize_mode: ResizeMode::KeepAspectRatio,
+        })
+    }
+
+    #[test]
+    fn cursor_is_mapped_into_pillarboxed_game_area() {
+        let mut input = letterboxed();
+        // The game takes 1200x900 pixels in the middle, with 200 pixel bars on the sides
+        input.simulate_resize(1600, 900, 2.0);
+
+        input.simulate_cursor_move(cgmath::Point2::new(800.0, 450.0));
+        assert_eq!(
+            input.cursor_game_position(),
+            cgmath::Point2::new(160.0, 120.0)
+        );
+        assert!(input.is_cursor_in_game_area());
+
+        input.simulate_cursor_move(cgmath::Point2::new(200.0, 0.0));
+        assert_eq!(input.cursor_game_position(), cgmath::Point2::new(0.0, 0.0));
+        assert!(input.is_cursor_in_game_area());
+
+        input.simulate_cursor_move(cgmath::Point2::new(100.0, 450.0));
+        assert!(!input.is_cursor_in_game_area());
+        input.simulate_cursor_move(cgmath::Point2::new(1450.0, 450.0));
+        assert!(!input.is_cursor_in_game_area());
+    }
+
+    #[test]
+    fn cursor_is_mapped_into_letterboxed_game_area() {
+        let mut input = letterboxed();
+        // The game takes 800x600 pixels in the middle, with 200 pixel bars above and below
+        input.simulate_resize(800, 1000, 1.0);
+
+        input.simulate_cursor_move(cgmath::Point2::new(400.0, 500.0));
+        assert_eq!(
+            input.cursor_game_position(),
+            cgmath::Point2::new(160.0, 120.0)
+        );
+        assert!(input.is_cursor_in_game_area());
+
+        input.simulate_cursor_move(cgmath::Point2::new(800.0, 200.0));
+        assert_eq!(
+            input.cursor_game_position(),
+            cgmath::Point2::new(320.0, 0.0)
+        );
+
+        input.simulate_cursor_move(cgmath::Point2::new(400.0, 100.0));
+        assert!(!input.is_cursor_in_game_area());
+        input.simulate_cursor_move(cgmath::Point2::new(400.0, 850.0));
+        assert!(!input.is_cursor_in_game_area());
+    }
+}
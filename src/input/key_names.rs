@@ -0,0 +1,80 @@
+use winit::event::{ScanCode, VirtualKeyCode};
+
+/// Returns the virtual key at the position of the physical key on the US QWERTY layout,
+/// scancodes are the PC ones used on Windows and Linux
+pub(super) fn default_key_code(scancode: ScanCode) -> Option<VirtualKeyCode> {
+    use VirtualKeyCode::*;
+    let key_code = match scancode {
+        1 => Escape,
+        2 => Key1,
+        3 => Key2,
+        4 => Key3,
+        5 => Key4,
+        6 => Key5,
+        7 => Key6,
+        8 => Key7,
+        9 => Key8,
+        10 => Key9,
+        11 => Key0,
+        12 => Minus,
+        13 => Equals,
+        14 => Back,
+        15 => Tab,
+        16 => Q,
+        17 => W,
+        18 => E,
+        19 => R,
+        20 => T,
+        21 => Y,
+        22 => U,
+        23 => I,
+        24 => O,
+        25 => P,
+        26 => LBracket,
+        27 => RBracket,
+        28 => Return,
+        29 => LControl,
+        30 => A,
+        31 => S,
+        32 => D,
+        33 => F,
+        34 => G,
+        35 => H,
+        36 => J,
+        37 => K,
+        38 => L,
+        39 => Semicolon,
+        40 => Apostrophe,
+        41 => Grave,
+        42 => LShift,
+        43 => Backslash,
+        44 => Z,
+        45 => X,
+        46 => C,
+        47 => V,
+        48 => B,
+        49 => N,
+        50 => M,
+        51 => Comma,
+        52 => Period,
+        53 => Slash,
+        54 => RShift,
+        56 => LAlt,
+        57 => Space,
+        58 => Capital,
+        59 => F1,
+        60 => F2,
+        61 => F3,
+        62 => F4,
+        63 => F5,
+        64 => F6,
+        65 => F7,
+        66 => F8,
+        67 => F9,
+        68 => F10,
+        87 => F11,
+        88 => F12,
+        _ => return None,
+    };
+    Some(key_code)
+}
@@ -0,0 +1,362 @@
+use cgmath::MetricSpace;
+use winit::event::MouseButton;
+
+/// Higher-level events derived from the mouse buttons and the cursor movement
+///
+/// Each event is reported only during one frame, positions are in window's pixels
+/// unlike the game coordinates of `Gesture`, `InputHandler::game_position` converts them
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PointerEvent {
+    /// The button was released without dragging or long pressing
+    Click {
+        button: MouseButton,
+        position: cgmath::Point2<f32>,
+    },
+    /// The second click of the button within the double-click interval,
+    /// reported in addition to the click
+    DoubleClick {
+        button: MouseButton,
+        position: cgmath::Point2<f32>,
+    },
+    /// Reported while the button is still held, no click is reported when it is released
+    LongPress {
+        button: MouseButton,
+        position: cgmath::Point2<f32>,
+    },
+    /// The cursor moved further than the drag threshold while the button was held
+    DragStart {
+        button: MouseButton,
+        origin: cgmath::Point2<f32>,
+        position: cgmath::Point2<f32>,
+    },
+    DragMove {
+        button: MouseButton,
+        origin: cgmath::Point2<f32>,
+        position: cgmath::Point2<f32>,
+        /// Movement of the cursor during the current frame
+        delta: cgmath::Vector2<f32>,
+    },
+    /// The button was released, or the cursor left the window or the window lost focus
+    /// while the button was dragged
+    DragEnd {
+        button: MouseButton,
+        origin: cgmath::Point2<f32>,
+        position: cgmath::Point2<f32>,
+    },
+}
+
+/// Thresholds used to tell pointer events apart
+#[derive(Debug, Copy, Clone)]
+pub struct PointerSettings {
+    /// Longest time in seconds between two clicks for a double-click
+    pub double_click_interval: f32,
+    /// Time in seconds a button has to be held for a long press
+    pub long_press_duration: f32,
+    /// Distance in pixels the cursor has to move while the button is held to start a drag
+    pub drag_threshold: f32,
+}
+
+impl Default for PointerSettings {
+    fn default() -> Self {
+        Self {
+            double_click_interval: 0.3,
+            long_press_duration: 0.5,
+            drag_threshold: 5.0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ButtonPress {
+    button: MouseButton,
+    origin: cgmath::Point2<f32>,
+    start_time: f64,
+    dragging: bool,
+    long_pressed: bool,
+}
+
+/// Tracks held mouse buttons and recognizes pointer events
+pub(super) struct PointerTracker {
+    presses: Vec<ButtonPress>,
+    events: Vec<PointerEvent>,
+    pub(super) settings: PointerSettings,
+    last_click: Option<(MouseButton, f64, cgmath::Point2<f32>)>,
+}
+
+impl PointerTracker {
+    pub(super) fn new() -> Self {
+        Self {
+            presses: vec![],
+            events: vec![],
+            settings: PointerSettings::default(),
+            last_click: None,
+        }
+    }
+
+    /// Starts tracking the `button` pressed at `position` at `time` seconds
+    pub(super) fn accept_press(
+        &mut self,
+        button: MouseButton,
+        position: cgmath::Point2<f32>,
+        time: f64,
+    ) {
+        if self.presses.iter().any(|p| p.button == button) {
+            return;
+        }
+
+        self.presses.push(ButtonPress {
+            button,
+            origin: position,
+            start_time: time,
+            dragging: false,
+            long_pressed: false,
+        });
+    }
+
+    /// Stops tracking the `button` released at `position` at `time` seconds
+    pub(super) fn accept_release(
+        &mut self,
+        button: MouseButton,
+        position: cgmath::Point2<f32>,
+        time: f64,
+    ) {
+        let press = match self.presses.iter().position(|p| p.button == button) {
+            Some(index) => self.presses.remove(index),
+            None => return,
+        };
+
+        if press.dragging {
+            self.events.push(PointerEvent::DragEnd {
+                button,
+                origin: press.origin,
+                position,
+            });
+            return;
+        }
+
+        if press.long_pressed {
+            return;
+        }
+
+        self.events.push(PointerEvent::Click { button, position });
+        match self.last_click {
+            Some((last_button, last_time, last_position))
+                if last_button == button
+                    && (time - last_time) as f32 <= self.settings.double_click_interval
+                    && last_position.distance(position) <= self.settings.drag_threshold =>
+            {
+                self.events
+                    .push(PointerEvent::DoubleClick { button, position });
+                self.last_click = None;
+            }
+            _ => self.last_click = Some((button, time, position)),
+        }
+    }
+
+    /// Stops tracking all held buttons when their releases may never arrive,
+    /// e.g. after the window has lost focus, active drags end at `position`
+    pub(super) fn cancel_presses(&mut self, position: cgmath::Point2<f32>) {
+        for press in self.presses.drain(..) {
+            if press.dragging {
+                self.events.push(PointerEvent::DragEnd {
+                    button: press.button,
+                    origin: press.origin,
+                    position,
+                });
+            }
+        }
+    }
+
+    /// Starts and continues drags of the held buttons when the cursor moves
+    pub(super) fn accept_cursor_move(
+        &mut self,
+        position: cgmath::Point2<f32>,
+        delta: cgmath::Vector2<f32>,
+    ) {
+        for press in self.presses.iter_mut() {
+            let button = press.button;
+            let origin = press.origin;
+
+            if press.dragging {
+                let drag_move = self.events.iter_mut().find(
+                    |e| matches!(e, PointerEvent::DragMove { button: b, .. } if *b == button),
+                );
+                if let Some(PointerEvent::DragMove {
+                    position: p,
+                    delta: d,
+                    ..
+                }) = drag_move
+                {
+                    *p = position;
+                    *d += delta;
+                } else {
+                    self.events.push(PointerEvent::DragMove {
+                        button,
+                        origin,
+                        position,
+                        delta,
+                    });
+                }
+            } else if !press.long_pressed
+                && origin.distance(position) > self.settings.drag_threshold
+            {
+                press.dragging = true;
+                self.events.push(PointerEvent::DragStart {
+                    button,
+                    origin,
+                    position,
+                });
+            }
+        }
+    }
+
+    /// Moves to the next frame and recognizes long presses
+    pub(super) fn update(&mut self, position: cgmath::Point2<f32>, time: f64) {
+        self.events.clear();
+
+        for press in self.presses.iter_mut() {
+            if !press.dragging
+                && !press.long_pressed
+                && (time - press.start_time) as f32 >= self.settings.long_press_duration
+            {
+                press.long_pressed = true;
+                self.events.push(PointerEvent::LongPress {
+                    button: press.button,
+                    position,
+                });
+            }
+        }
+    }
+
+    /// Returns the point where the drag of the `button` started if it is being dragged
+    pub(super) fn drag_origin(&self, button: MouseButton) -> Option<cgmath::Point2<f32>> {
+        self.presses
+            .iter()
+            .find(|p| p.button == button && p.dragging)
+            .map(|p| p.origin)
+    }
+
+    pub(super) fn events(&self) -> &[PointerEvent] {
+        &self.events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputHandler;
+
+    fn click(input: &mut InputHandler) {
+        input.simulate_mouse_button_press(&MouseButton::Left);
+        input.advance_time(0.05);
+        input.simulate_mouse_button_release(&MouseButton::Left);
+    }
+
+    #[test]
+    fn press_and_release_in_place_is_a_click() {
+        let mut input = InputHandler::new();
+        input.simulate_cursor_move(cgmath::Point2::new(10.0, 10.0));
+        click(&mut input);
+        assert!(input.is_mouse_button_clicked(&MouseButton::Left));
+        assert!(!input.is_mouse_button_double_clicked(&MouseButton::Left));
+    }
+
+    #[test]
+    fn second_click_within_interval_is_also_a_double_click() {
+        let mut input = InputHandler::new();
+        click(&mut input);
+        input.advance_time(0.1);
+        click(&mut input);
+        let position = cgmath::Point2::new(0.0, 0.0);
+        let button = MouseButton::Left;
+        assert_eq!(
+            input.pointer_events(),
+            &[
+                PointerEvent::Click { button, position },
+                PointerEvent::DoubleClick { button, position }
+            ]
+        );
+
+        input.advance_time(0.1);
+        click(&mut input);
+        assert!(!input.is_mouse_button_double_clicked(&MouseButton::Left));
+    }
+
+    #[test]
+    fn moving_held_button_past_threshold_drags_it() {
+        let mut input = InputHandler::new();
+        let button = MouseButton::Left;
+        let origin = cgmath::Point2::new(10.0, 10.0);
+        input.simulate_cursor_move(origin);
+        input.simulate_mouse_button_press(&button);
+        input.advance_frame();
+
+        input.simulate_cursor_move(cgmath::Point2::new(12.0, 10.0));
+        assert!(input.pointer_events().is_empty());
+        assert_eq!(input.mouse_drag_origin(&button), None);
+
+        let position = cgmath::Point2::new(30.0, 10.0);
+        input.simulate_cursor_move(position);
+        assert_eq!(
+            input.pointer_events(),
+            &[PointerEvent::DragStart {
+                button,
+                origin,
+                position
+            }]
+        );
+        assert_eq!(input.mouse_drag_origin(&button), Some(origin));
+
+        input.advance_frame();
+        input.simulate_cursor_move(cgmath::Point2::new(40.0, 10.0));
+        input.simulate_cursor_move(cgmath::Point2::new(50.0, 10.0));
+        assert_eq!(
+            input.pointer_events(),
+            &[PointerEvent::DragMove {
+                button,
+                origin,
+                position: cgmath::Point2::new(50.0, 10.0),
+                delta: cgmath::Vector2::new(20.0, 0.0),
+            }]
+        );
+
+        input.advance_frame();
+        input.simulate_mouse_button_release(&button);
+        assert_eq!(
+            input.pointer_events(),
+            &[PointerEvent::DragEnd {
+                button,
+                origin,
+                position: cgmath::Point2::new(50.0, 10.0),
+            }]
+        );
+        assert!(!input.is_mouse_button_clicked(&button));
+    }
+
+    #[test]
+    fn drag_ends_when_cursor_leaves_window() {
+        let mut input = InputHandler::new();
+        let button = MouseButton::Left;
+        input.simulate_mouse_button_press(&button);
+        input.simulate_cursor_move(cgmath::Point2::new(20.0, 0.0));
+        input.advance_frame();
+
+        input.simulate_cursor_left();
+        assert!(matches!(
+            input.pointer_events(),
+            [PointerEvent::DragEnd { .. }]
+        ));
+        assert_eq!(input.mouse_drag_origin(&button), None);
+    }
+
+    #[test]
+    fn held_button_is_long_pressed_without_a_click() {
+        let mut input = InputHandler::new();
+        input.simulate_mouse_button_press(&MouseButton::Right);
+        input.advance_time(0.6);
+        assert!(input.is_mouse_button_long_pressed(&MouseButton::Right));
+
+        input.simulate_mouse_button_release(&MouseButton::Right);
+        assert!(!input.is_mouse_button_clicked(&MouseButton::Right));
+    }
+}
@@ -0,0 +1,273 @@
+use super::Pressable;
+
+/// One step of an input sequence
+#[derive(Debug, Clone)]
+pub struct SequenceStep {
+    /// Inputs that have to be held at the same time, e.g. Down and Right for Down-Forward
+    pub inputs: Vec<Pressable>,
+    /// Longest time in seconds since the previous step, ignored for the first step
+    pub window: f32,
+}
+
+/// Sequence of steps, e.g. Down, Down-Forward, Forward + Punch
+///
+/// A step is performed when the held inputs out of all the inputs used in the sequence
+/// become exactly the inputs of the step. Inputs that are not used in the sequence
+/// and states between the steps are ignored
+#[derive(Debug, Clone)]
+pub struct InputSequence {
+    pub steps: Vec<SequenceStep>,
+}
+
+impl InputSequence {
+    pub fn new(steps: Vec<SequenceStep>) -> Self {
+        Self { steps }
+    }
+
+    /// Returns all inputs used in the sequence
+    fn inputs(&self) -> Vec<Pressable> {
+        let mut inputs: Vec<Pressable> = vec![];
+        for input in self.steps.iter().flat_map(|s| s.inputs.iter()) {
+            if !inputs.contains(input) {
+                inputs.push(*input);
+            }
+        }
+        inputs
+    }
+}
+
+/// Inputs held after a press or a release
+struct HistoryEntry {
+    held: Vec<Pressable>,
+    time: f64,
+    frame: u64,
+}
+
+/// Keeps track of the recent changes of held inputs
+pub(super) struct InputHistory {
+    entries: Vec<HistoryEntry>,
+}
+
+impl InputHistory {
+    /// Time in seconds the entries are kept for, no sequence can be longer than that
+    const DURATION: f64 = 5.0;
+
+    pub(super) fn new() -> Self {
+        Self { entries: vec![] }
+    }
+
+    /// Stores inputs that are `held` after a change at `time` seconds during the `frame`
+    pub(super) fn record(&mut self, held: Vec<Pressable>, time: f64, frame: u64) {
+        self.entries.push(HistoryEntry { held, time, frame });
+    }
+
+    /// Removes entries that are too old to be a part of any sequence
+    pub(super) fn trim(&mut self, time: f64) {
+        let first_recent = self
+            .entries
+            .iter()
+            .position(|e| time - e.time <= Self::DURATION)
+            .unwrap_or(self.entries.len());
+        self.entries.drain(..first_recent);
+    }
+
+    /// Returns true if the last step of the `sequence` has been performed during the `frame`
+    pub(super) fn is_completed(&self, sequence: &InputSequence, frame: u64) -> bool {
+        let last_step = match sequence.steps.last() {
+            Some(step) => step,
+            None => return false,
+        };
+        let inputs = sequence.inputs();
+
+        for index in (0..self.entries.len()).rev() {
+            if self.entries[index].frame != frame {
+                break;
+            }
+            if self.is_step_performed(index, last_step, &inputs)
+                && self.are_previous_steps_performed(index, sequence, &inputs)
+            {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Walks back from the last step performed at the entry `index`
+    /// and looks for the latest entry matching each of the previous steps
+    fn are_previous_steps_performed(
+        &self,
+        mut index: usize,
+        sequence: &InputSequence,
+        inputs: &[Pressable],
+    ) -> bool {
+        for step_index in (0..sequence.steps.len() - 1).rev() {
+            let step = &sequence.steps[step_index];
+            let window = sequence.steps[step_index + 1].window as f64;
+            let time = self.entries[index].time;
+
+            let previous = (0..index)
+                .rev()
+                .take_while(|&i| time - self.entries[i].time <= window)
+                .find(|&i| self.is_step_performed(i, step, inputs));
+
+            match previous {
+                Some(previous) => index = previous,
+                None => return false,
+            }
+        }
+
+        true
+    }
+
+    /// Returns true if the held inputs became exactly the inputs of the `step` at the entry `index`
+    fn is_step_performed(&self, index: usize, step: &SequenceStep, inputs: &[Pressable]) -> bool {
+        let matches = |entry: &HistoryEntry| {
+            let held: Vec<&Pressable> = entry.held.iter().filter(|i| inputs.contains(i)).collect();
+            held.len() == step.inputs.len() && step.inputs.iter().all(|i| held.contains(&i))
+        };
+
+        matches(&self.entries[index]) && (index == 0 || !matches(&self.entries[index - 1]))
+    }
+}
+
+/// Press of any of the inputs remembered for some time until it is consumed
+pub(super) struct BufferedAction {
+    pub(super) inputs: Vec<Pressable>,
+    pub(super) duration: f32,
+    pub(super) pressed_at: Option<f64>,
+}
+
+impl BufferedAction {
+    pub(super) fn new(inputs: &[Pressable], duration: f32) -> Self {
+        Self {
+            inputs: inputs.to_vec(),
+            duration,
+            pressed_at: None,
+        }
+    }
+
+    /// Remembers the press of the `input` at `time` seconds if it triggers the action
+    pub(super) fn accept_press(&mut self, input: &Pressable, time: f64) {
+        if self.inputs.contains(input) {
+            self.pressed_at = Some(time);
+        }
+    }
+
+    /// Forgets the press after the action's duration passes
+    pub(super) fn update(&mut self, time: f64) {
+        if let Some(pressed_at) = self.pressed_at {
+            if time - pressed_at > self.duration as f64 {
+                self.pressed_at = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputHandler;
+    use winit::event::VirtualKeyCode;
+
+    const DOWN: VirtualKeyCode = VirtualKeyCode::Down;
+    const RIGHT: VirtualKeyCode = VirtualKeyCode::Right;
+    const PUNCH: VirtualKeyCode = VirtualKeyCode::Z;
+
+    fn step(keys: &[VirtualKeyCode], window: f32) -> SequenceStep {
+        SequenceStep {
+            inputs: keys.iter().map(|k| Pressable::KeyboardKey(*k)).collect(),
+            window,
+        }
+    }
+
+    /// Down, Down-Forward, Forward + Punch
+    fn fireball() -> InputSequence {
+        InputSequence::new(vec![
+            step(&[DOWN], 0.0),
+            step(&[DOWN, RIGHT], 0.2),
+            step(&[RIGHT], 0.2),
+            step(&[RIGHT, PUNCH], 0.2),
+        ])
+    }
+
+    fn perform_fireball(input: &mut InputHandler, dt: f32) {
+        input.simulate_key_press(&DOWN);
+        input.advance_time(dt);
+        input.simulate_key_press(&RIGHT);
+        input.advance_time(dt);
+        input.simulate_key_release(&DOWN);
+        input.advance_time(dt);
+        input.simulate_key_press(&PUNCH);
+    }
+
+    #[test]
+    fn sequence_performed_within_windows_is_completed_for_one_frame() {
+        let mut input = InputHandler::new();
+        let sequence = fireball();
+        perform_fireball(&mut input, 0.1);
+        assert!(input.is_sequence_completed(&sequence));
+
+        input.advance_time(0.1);
+        assert!(!input.is_sequence_completed(&sequence));
+    }
+
+    #[test]
+    fn sequence_with_too_slow_step_is_not_completed() {
+        let mut input = InputHandler::new();
+        perform_fireball(&mut input, 0.3);
+        assert!(!input.is_sequence_completed(&fireball()));
+    }
+
+    #[test]
+    fn sequence_out_of_order_is_not_completed() {
+        let mut input = InputHandler::new();
+        input.simulate_key_press(&RIGHT);
+        input.advance_time(0.1);
+        input.simulate_key_press(&DOWN);
+        input.advance_time(0.1);
+        input.simulate_key_release(&DOWN);
+        input.advance_time(0.1);
+        input.simulate_key_press(&PUNCH);
+        assert!(!input.is_sequence_completed(&fireball()));
+    }
+
+    #[test]
+    fn unrelated_inputs_are_ignored() {
+        let mut input = InputHandler::new();
+        input.simulate_key_press(&VirtualKeyCode::LShift);
+        perform_fireball(&mut input, 0.1);
+        assert!(input.is_sequence_completed(&fireball()));
+    }
+
+    #[test]
+    fn buffered_action_is_kept_for_its_duration() {
+        let mut input = InputHandler::new();
+        let jump = Pressable::KeyboardKey(VirtualKeyCode::Space);
+        input.add_buffered_action("jump", &[jump], 0.2);
+        assert!(!input.is_action_buffered("jump"));
+
+        input.simulate_key_press(&VirtualKeyCode::Space);
+        input.advance_time(0.1);
+        input.simulate_key_release(&VirtualKeyCode::Space);
+        input.advance_time(0.05);
+        assert!(input.is_action_buffered("jump"));
+
+        input.advance_time(0.1);
+        assert!(!input.is_action_buffered("jump"));
+    }
+
+    #[test]
+    fn buffered_action_is_consumed_once() {
+        let mut input = InputHandler::new();
+        let button = Pressable::MouseButton(winit::event::MouseButton::Left);
+        input.add_buffered_action("attack", &[button], 1.0);
+        input.simulate_mouse_button_press(&winit::event::MouseButton::Left);
+        input.advance_frame();
+
+        assert!(input.consume_buffered_action("attack"));
+        assert!(!input.is_action_buffered("attack"));
+        assert!(!input.consume_buffered_action("attack"));
+        assert!(!input.consume_buffered_action("unknown"));
+    }
+}
@@ -0,0 +1,414 @@
+use cgmath::{Angle, EuclideanSpace, InnerSpace, MetricSpace};
+
+pub use winit::event::TouchPhase;
+
+/// State of a single finger touching the screen
+///
+/// After the first frame a finger that is still touching the screen is in `TouchPhase::Moved`,
+/// even if it did not move, check `delta` to find out
+#[derive(Debug, Copy, Clone)]
+pub struct TouchPoint {
+    /// Identifier of the finger, stays the same until the finger is lifted
+    pub id: u64,
+    pub phase: TouchPhase,
+    /// Position in game coordinates, see `InputHandler::game_position`
+    pub position: cgmath::Point2<f32>,
+    /// Position where the finger has touched the screen
+    pub start_position: cgmath::Point2<f32>,
+    /// Movement of the finger during the current frame
+    pub delta: cgmath::Vector2<f32>,
+}
+
+/// Gestures recognized from the touches
+///
+/// Each gesture is reported only during one frame, positions are in game coordinates
+/// unlike the window's pixels of `PointerEvent`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Gesture {
+    Tap {
+        position: cgmath::Point2<f32>,
+    },
+    /// The second tap within the double tap interval, reported in addition to the tap
+    /// the same way `PointerEvent::DoubleClick` is
+    DoubleTap {
+        position: cgmath::Point2<f32>,
+    },
+    /// Reported while the finger is still touching the screen,
+    /// no tap is reported when it is lifted
+    LongPress {
+        position: cgmath::Point2<f32>,
+    },
+    Swipe {
+        start: cgmath::Point2<f32>,
+        end: cgmath::Point2<f32>,
+        /// Average velocity of the swipe in game coordinates per second
+        velocity: cgmath::Vector2<f32>,
+    },
+    /// Two fingers moving relative to each other
+    Pinch {
+        /// Point between the fingers
+        center: cgmath::Point2<f32>,
+        /// Change of the distance between the fingers during the current frame,
+        /// greater than 1.0 if they move apart
+        scale: f32,
+        /// Change of the angle of the line between the fingers during the current frame
+        rotation: cgmath::Rad<f32>,
+    },
+}
+
+/// Thresholds used to tell gestures apart, distances are in game coordinates
+#[derive(Debug, Copy, Clone)]
+pub struct GestureSettings {
+    /// Longest time in seconds a finger can touch the screen for a tap
+    pub tap_max_duration: f32,
+    /// Longest distance a finger can move for a tap or a long press
+    pub tap_max_distance: f32,
+    /// Longest time in seconds between two taps for a double tap
+    pub double_tap_interval: f32,
+    /// Time in seconds a finger has to touch the screen for a long press
+    pub long_press_duration: f32,
+    /// Shortest distance a finger has to move for a swipe
+    pub swipe_min_distance: f32,
+    /// Longest time in seconds a finger can touch the screen for a swipe
+    pub swipe_max_duration: f32,
+}
+
+impl Default for GestureSettings {
+    fn default() -> Self {
+        Self {
+            tap_max_duration: 0.3,
+            tap_max_distance: 10.0,
+            double_tap_interval: 0.3,
+            long_press_duration: 0.5,
+            swipe_min_distance: 50.0,
+            swipe_max_duration: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct TrackedTouch {
+    point: TouchPoint,
+    start_time: f64,
+    long_pressed: bool,
+    /// Touches that were a part of a multi-touch are not recognized as single finger gestures
+    multi_touch: bool,
+}
+
+impl TrackedTouch {
+    fn is_active(&self) -> bool {
+        matches!(self.point.phase, TouchPhase::Started | TouchPhase::Moved)
+    }
+}
+
+/// Tracks fingers touching the screen and recognizes gestures
+pub(super) struct TouchTracker {
+    touches: Vec<TrackedTouch>,
+    gestures: Vec<Gesture>,
+    pub(super) settings: GestureSettings,
+    last_tap: Option<(f64, cgmath::Point2<f32>)>,
+    /// Ids of the two fingers, distance and angle between them
+    pinch: Option<(u64, u64, f32, f32)>,
+}
+
+impl TouchTracker {
+    pub(super) fn new() -> Self {
+        Self {
+            touches: vec![],
+            gestures: vec![],
+            settings: GestureSettings::default(),
+            last_tap: None,
+            pinch: None,
+        }
+    }
+
+    /// Handles processing and storage of a touch event that happened at `time` seconds
+    pub(super) fn accept_touch(
+        &mut self,
+        id: u64,
+        phase: TouchPhase,
+        position: cgmath::Point2<f32>,
+        time: f64,
+    ) {
+        match phase {
+            TouchPhase::Started => {
+                self.touches.retain(|t| t.point.id != id);
+                let multi_touch = self.touches.iter().any(|t| t.is_active());
+                for touch in self.touches.iter_mut().filter(|t| t.is_active()) {
+                    touch.multi_touch = true;
+                }
+
+                self.touches.push(TrackedTouch {
+                    point: TouchPoint {
+                        id,
+                        phase,
+                        position,
+                        start_position: position,
+                        delta: cgmath::Vector2::new(0.0, 0.0),
+                    },
+                    start_time: time,
+                    long_pressed: false,
+                    multi_touch,
+                });
+            }
+            TouchPhase::Moved | TouchPhase::Ended | TouchPhase::Cancelled => {
+                let touch = match self.touches.iter_mut().find(|t| t.point.id == id) {
+                    Some(touch) => touch,
+                    None => return,
+                };
+                touch.point.delta += position - touch.point.position;
+                touch.point.position = position;
+                touch.point.phase = phase;
+
+                let touch = *touch;
+                if phase == TouchPhase::Ended {
+                    self.recognize_release(&touch, time);
+                }
+            }
+        }
+
+        self.recognize_pinch();
+    }
+
+    /// Recognizes gestures that end when the finger is lifted
+    fn recognize_release(&mut self, touch: &TrackedTouch, time: f64) {
+        if touch.multi_touch || touch.long_pressed {
+            return;
+        }
+
+        let duration = (time - touch.start_time) as f32;
+        let start = touch.point.start_position;
+        let end = touch.point.position;
+        let distance = start.distance(end);
+
+        if duration <= self.settings.tap_max_duration && distance <= self.settings.tap_max_distance
+        {
+            self.gestures.push(Gesture::Tap { position: end });
+            match self.last_tap {
+                Some((tap_time, tap_position))
+                    if (time - tap_time) as f32 <= self.settings.double_tap_interval
+                        && tap_position.distance(end) <= self.settings.tap_max_distance =>
+                {
+                    self.gestures.push(Gesture::DoubleTap { position: end });
+                    self.last_tap = None;
+                }
+                _ => self.last_tap = Some((time, end)),
+            }
+        } else if distance >= self.settings.swipe_min_distance
+            && duration <= self.settings.swipe_max_duration
+        {
+            self.gestures.push(Gesture::Swipe {
+                start,
+                end,
+                velocity: (end - start) / duration.max(f32::EPSILON),
+            });
+        }
+    }
+
+    /// Recognizes movement of exactly two fingers relative to each other
+    fn recognize_pinch(&mut self) {
+        let active: Vec<&TouchPoint> = self
+            .touches
+            .iter()
+            .filter(|t| t.is_active())
+            .map(|t| &t.point)
+            .collect();
+
+        if active.len() != 2 {
+            self.pinch = None;
+            return;
+        }
+
+        let (a, b) = (active[0], active[1]);
+        let offset = b.position - a.position;
+        let distance = offset.magnitude();
+        let angle = offset.y.atan2(offset.x);
+        let center = a.position.midpoint(b.position);
+
+        if let Some((id_a, id_b, previous_distance, previous_angle)) = self.pinch {
+            if id_a == a.id && id_b == b.id && previous_distance > 0.0 {
+                let scale = distance / previous_distance;
+                let rotation = cgmath::Rad(angle - previous_angle).normalize_signed();
+
+                if scale != 1.0 || rotation.0 != 0.0 {
+                    let pinch = self
+                        .gestures
+                        .iter_mut()
+                        .find(|g| matches!(g, Gesture::Pinch { .. }));
+                    if let Some(Gesture::Pinch {
+                        center: c,
+                        scale: s,
+                        rotation: r,
+                    }) = pinch
+                    {
+                        *c = center;
+                        *s *= scale;
+                        *r += rotation;
+                    } else {
+                        self.gestures.push(Gesture::Pinch {
+                            center,
+                            scale,
+                            rotation,
+                        });
+                    }
+                }
+            }
+        }
+
+        self.pinch = Some((a.id, b.id, distance, angle));
+    }
+
+    /// Moves touches to the next frame and recognizes gestures that depend on time
+    pub(super) fn update(&mut self, time: f64) {
+        self.gestures.clear();
+        self.touches.retain(|t| t.is_active());
+
+        for touch in self.touches.iter_mut() {
+            touch.point.phase = TouchPhase::Moved;
+            touch.point.delta = cgmath::Vector2::new(0.0, 0.0);
+
+            if !touch.long_pressed
+                && !touch.multi_touch
+                && (time - touch.start_time) as f32 >= self.settings.long_press_duration
+                && touch.point.start_position.distance(touch.point.position)
+                    <= self.settings.tap_max_distance
+            {
+                touch.long_pressed = true;
+                self.gestures.push(Gesture::LongPress {
+                    position: touch.point.position,
+                });
+            }
+        }
+    }
+
+    pub(super) fn touches(&self) -> impl Iterator<Item = &TouchPoint> {
+        self.touches.iter().map(|t| &t.point)
+    }
+
+    pub(super) fn gestures(&self) -> &[Gesture] {
+        &self.gestures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::InputHandler;
+
+    fn tap(input: &mut InputHandler, position: cgmath::Point2<f32>) {
+        input.simulate_touch(0, TouchPhase::Started, position);
+        input.advance_time(0.1);
+        input.simulate_touch(0, TouchPhase::Ended, position);
+    }
+
+    #[test]
+    fn short_touch_is_a_tap() {
+        let mut input = InputHandler::new();
+        let position = cgmath::Point2::new(10.0, 20.0);
+        tap(&mut input, position);
+        assert_eq!(input.gestures(), &[Gesture::Tap { position }]);
+
+        input.advance_frame();
+        assert!(input.gestures().is_empty());
+    }
+
+    #[test]
+    fn second_tap_within_interval_is_also_a_double_tap() {
+        let mut input = InputHandler::new();
+        let position = cgmath::Point2::new(10.0, 20.0);
+        tap(&mut input, position);
+        input.advance_time(0.1);
+        tap(&mut input, position);
+        assert_eq!(
+            input.gestures(),
+            &[Gesture::Tap { position }, Gesture::DoubleTap { position }]
+        );
+    }
+
+    #[test]
+    fn taps_too_far_apart_in_time_are_single_taps() {
+        let mut input = InputHandler::new();
+        let position = cgmath::Point2::new(10.0, 20.0);
+        tap(&mut input, position);
+        input.advance_time(1.0);
+        tap(&mut input, position);
+        assert_eq!(input.gestures(), &[Gesture::Tap { position }]);
+    }
+
+    #[test]
+    fn held_touch_is_a_long_press_without_a_tap() {
+        let mut input = InputHandler::new();
+        let position = cgmath::Point2::new(10.0, 20.0);
+        input.simulate_touch(0, TouchPhase::Started, position);
+        input.advance_time(0.6);
+        assert_eq!(input.gestures(), &[Gesture::LongPress { position }]);
+
+        input.advance_time(0.1);
+        input.simulate_touch(0, TouchPhase::Ended, position);
+        assert!(input.gestures().is_empty());
+    }
+
+    #[test]
+    fn touches_are_in_game_coordinates() {
+        let mut input = InputHandler::new();
+        input.simulate_resize(1600, 1200, 2.0);
+        input.simulate_touch(3, TouchPhase::Started, cgmath::Point2::new(200.0, 100.0));
+        let touch = input.touch(3).unwrap();
+        assert_eq!(touch.position, cgmath::Point2::new(100.0, 50.0));
+    }
+
+    #[test]
+    fn two_fingers_spreading_and_turning_are_a_pinch() {
+        let mut input = InputHandler::new();
+        input.simulate_touch(0, TouchPhase::Started, cgmath::Point2::new(100.0, 100.0));
+        input.simulate_touch(1, TouchPhase::Started, cgmath::Point2::new(200.0, 100.0));
+        input.advance_time(0.1);
+        assert!(input.gestures().is_empty());
+
+        // The second finger moves twice as far away and a quarter turn around the first one
+        input.simulate_touch(1, TouchPhase::Moved, cgmath::Point2::new(200.0, 200.0));
+        input.simulate_touch(1, TouchPhase::Moved, cgmath::Point2::new(100.0, 300.0));
+        match input.gestures() {
+            [Gesture::Pinch {
+                center,
+                scale,
+                rotation,
+            }] => {
+                assert_eq!(*center, cgmath::Point2::new(100.0, 200.0));
+                assert!((scale - 2.0).abs() < 1e-5);
+                assert!((rotation.0 - std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+            }
+            gestures => panic!("Expected a pinch, got {:?}", gestures),
+        }
+
+        input.advance_time(0.1);
+        assert!(input.gestures().is_empty());
+
+        // Fingers of a pinch don't tap when they are lifted
+        input.simulate_touch(0, TouchPhase::Ended, cgmath::Point2::new(100.0, 100.0));
+        input.simulate_touch(1, TouchPhase::Ended, cgmath::Point2::new(100.0, 300.0));
+        assert!(input.gestures().is_empty());
+    }
+
+    #[test]
+    fn fast_long_movement_is_a_swipe() {
+        let mut input = InputHandler::new();
+        let start = cgmath::Point2::new(0.0, 0.0);
+        let end = cgmath::Point2::new(100.0, 0.0);
+        input.simulate_touch(0, TouchPhase::Started, start);
+        input.advance_time(0.2);
+        input.simulate_touch(0, TouchPhase::Moved, end);
+        input.simulate_touch(0, TouchPhase::Ended, end);
+        match input.gestures() {
+            [Gesture::Swipe {
+                start: s,
+                end: e,
+                velocity,
+            }] => {
+                assert_eq!((*s, *e), (start, end));
+                assert!((velocity.x - 500.0).abs() < 1.0);
+            }
+            gestures => panic!("Expected a swipe, got {:?}", gestures),
+        }
+    }
+}
@@ -70,6 +70,30 @@ pub struct WindowSettings {
     pub resize_mode: ResizeMode,
 }
 
+impl WindowSettings {
+    /// Returns position and size `(x, y, width, height)` of the area the game is drawn to
+    /// inside of the window of `screen_size` physical pixels
+    ///
+    /// With `ResizeMode::KeepAspectRatio` the area keeps the aspect ratio of the logical size
+    /// and stays in the center of the window, otherwise it covers the whole window
+    pub(crate) fn game_area(&self, screen_size: PhysicalSize<u32>) -> (f32, f32, f32, f32) {
+        let (width, height): (f32, f32) = screen_size.to_logical::<f32>(1.0).into();
+        if self.resize_mode != ResizeMode::KeepAspectRatio {
+            return (0.0, 0.0, width, height);
+        }
+
+        let aspect = self.logical_width as f32 / self.logical_height as f32;
+        let (area_width, area_height) = if width > height * aspect {
+            (height * aspect, height)
+        } else {
+            (width, width / aspect)
+        };
+        let area_x = (width - area_width) / 2.0;
+        let area_y = (height - area_height) / 2.0;
+        (area_x, area_y, area_width, area_height)
+    }
+}
+
 pub struct Game {
     title: String,
     game_objects: Vec<Box<dyn GameObject>>,
@@ -110,7 +134,8 @@ impl Game {
         .unwrap();
 
         let mut graphics_engine = GraphicsEngine::new(&window, self.window_settings);
-        let mut input_handler = InputHandler::new();
+        let mut input_handler = InputHandler::with_window_settings(self.window_settings);
+        input_handler.accept_resize(window.inner_size(), window.scale_factor());
 
         let mut game_state = GameState {
             frame_size: PhysicalSize::new(
@@ -138,6 +163,7 @@ impl Game {
             match event {
                 Event::WindowEvent { window_id, event } if window_id == window.id() => {
                     input_handler.accept_input(&event);
+                    input_handler.apply_cursor_state(&window);
                     match event {
                         WindowEvent::CloseRequested => {
                             self.call_end();
@@ -154,6 +180,7 @@ impl Game {
                         _ => {}
                     }
                 }
+                Event::DeviceEvent { event, .. } => input_handler.accept_device_input(&event),
                 Event::RedrawRequested(window_id) if window_id == window.id() => {
                     let now = std::time::Instant::now();
                     let dt = now - last_time;
@@ -171,7 +198,8 @@ impl Game {
                         *control_flow = ControlFlow::Exit;
                     }
 
-                    input_handler.update_input_state();
+                    input_handler.apply_cursor_state(&window);
+                    input_handler.update_input_state(game_state.dt);
                     graphics_engine.update();
 
                     match graphics_engine.render() {